@@ -1,12 +1,19 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use futures::stream::{self, StreamExt};
 use governor::{Quota, RateLimiter};
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
+use std::collections::BTreeMap;
+use std::future::Future;
 use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::charset::DetectedEncoding;
+use crate::error::{ErrorCode, ToolError};
+use crate::fetch::{self, FetchOptions, Fetcher, ResponseInfo};
+use crate::retry::{RetriesExhausted, RetryPolicy};
+
 /// Result of a single fetch operation
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FetchResult {
@@ -25,11 +32,36 @@ pub struct FetchResult {
     /// Error message (if failed)
     pub error: Option<String>,
 
+    /// Machine-readable failure category (if failed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+
     /// Response time in milliseconds
     pub response_time_ms: u64,
 
     /// Content length in bytes
     pub content_length: Option<usize>,
+
+    /// Final URL after redirects
+    pub final_url: Option<String>,
+
+    /// Selected response headers
+    pub headers: Option<BTreeMap<String, String>>,
+
+    /// Detected body encoding
+    pub encoding: Option<DetectedEncoding>,
+
+    /// Body was cut off at the size limit
+    #[serde(default)]
+    pub body_truncated: bool,
+
+    /// Requests made, including retries (0 if none was sent)
+    #[serde(default)]
+    pub attempts: u32,
+
+    /// Failure that caused the last retry, when the URL was retried
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
 }
 
 /// Batch fetch options
@@ -49,6 +81,12 @@ pub struct BatchOptions {
 
     /// Follow redirects
     pub follow_redirects: bool,
+
+    /// Retry policy for every URL (the request's, or the fetcher's default, when `None`)
+    pub retry: Option<RetryPolicy>,
+
+    /// Method, headers, body and query applied to every URL
+    pub request: FetchOptions,
 }
 
 impl Default for BatchOptions {
@@ -59,19 +97,28 @@ impl Default for BatchOptions {
             timeout: Duration::from_secs(30),
             fail_fast: false,
             follow_redirects: true,
+            retry: None,
+            request: FetchOptions::default(),
         }
     }
 }
 
 /// Fetch multiple URLs in parallel with rate limiting
+///
+/// `on_result` receives every result with the number of URLs finished so far.
+/// When `cancelled` resolves, the in-flight fetches are dropped and the results
+/// gathered until then are returned, marked `cancelled`.
 pub async fn fetch_batch(
-    client: &reqwest::Client,
+    fetcher: &Fetcher,
     urls: Vec<String>,
     options: BatchOptions,
+    mut on_result: impl FnMut(&FetchResult, usize),
+    cancelled: impl Future<Output = ()>,
 ) -> Result<BatchFetchResult> {
     if urls.is_empty() {
         return Ok(BatchFetchResult {
             results: Vec::new(),
+            cancelled: false,
             stats: BatchStats {
                 total: 0,
                 success: 0,
@@ -84,6 +131,7 @@ pub async fn fetch_batch(
     }
 
     let start_time = std::time::Instant::now();
+    let total = urls.len();
 
     tracing::info!(
         "Batch fetching {} URLs (concurrent: {}, rate_limit: {:?})",
@@ -98,12 +146,18 @@ pub async fn fetch_batch(
         Arc::new(RateLimiter::direct(quota))
     });
 
+    let request = FetchOptions {
+        retry: options.retry.or(options.request.retry),
+        ..options.request.clone()
+    };
+
     // Create stream of fetch tasks
     let fetch_stream = stream::iter(urls.into_iter().enumerate().map(|(index, url)| {
-        let client = client.clone();
+        let fetcher = fetcher.clone();
         let rate_limiter = rate_limiter.clone();
         let timeout = options.timeout;
         let follow_redirects = options.follow_redirects;
+        let request = &request;
 
         async move {
             // Rate limiting
@@ -114,43 +168,75 @@ pub async fn fetch_batch(
             tracing::debug!("[{}] Fetching: {}", index, url);
 
             let start = std::time::Instant::now();
-            let result = fetch_single_url(&client, &url, timeout, follow_redirects).await;
+            let result = fetch_single_url(&fetcher, &url, request, timeout, follow_redirects).await;
             let elapsed = start.elapsed();
 
             match result {
-                Ok((status, content, content_length)) => {
+                Ok((info, content, content_length)) => {
                     tracing::debug!("[{}] Success: {} ({}ms)", index, url, elapsed.as_millis());
                     FetchResult {
                         url,
-                        status,
+                        status: info.status,
                         success: true,
                         content: Some(content),
                         error: None,
+                        error_code: None,
                         response_time_ms: elapsed.as_millis() as u64,
                         content_length,
+                        final_url: Some(info.final_url),
+                        headers: Some(info.headers),
+                        encoding: info.encoding,
+                        body_truncated: info.body_truncated,
+                        attempts: info.attempts,
+                        last_error: info.last_error,
                     }
                 }
                 Err(e) => {
                     tracing::warn!("[{}] Failed: {} - {}", index, url, e);
+                    let failure = ToolError::classify(ErrorCode::Network, "Failed to fetch", &e);
                     FetchResult {
                         url,
-                        status: 0,
+                        status: failure.status.unwrap_or(0),
                         success: false,
                         content: None,
-                        error: Some(e.to_string()),
+                        error: Some(format!("{:#}", e)),
+                        error_code: Some(failure.code),
                         response_time_ms: elapsed.as_millis() as u64,
                         content_length: None,
+                        final_url: None,
+                        headers: None,
+                        encoding: None,
+                        body_truncated: false,
+                        attempts: failure.attempts.unwrap_or(0),
+                        last_error: e.downcast_ref::<RetriesExhausted>().and_then(|retried| retried.last_error.clone()),
                     }
                 }
             }
         }
     }));
 
-    // Execute fetches with concurrency control
-    let results: Vec<FetchResult> = fetch_stream
-        .buffer_unordered(options.max_concurrent)
-        .collect()
-        .await;
+    // Execute fetches with concurrency control until done or cancelled
+    let mut fetch_stream = std::pin::pin!(fetch_stream.buffer_unordered(options.max_concurrent));
+    let mut cancelled = std::pin::pin!(cancelled);
+    let mut results = Vec::with_capacity(total);
+    let mut was_cancelled = false;
+
+    loop {
+        tokio::select! {
+            result = fetch_stream.next() => match result {
+                Some(result) => {
+                    on_result(&result, results.len() + 1);
+                    results.push(result);
+                }
+                None => break,
+            },
+            _ = &mut cancelled => {
+                tracing::info!("Batch fetch cancelled after {} of {} URLs", results.len(), total);
+                was_cancelled = true;
+                break;
+            }
+        }
+    }
 
     // Check fail_fast option
     if options.fail_fast {
@@ -170,38 +256,42 @@ pub async fn fetch_batch(
         stats.total_time_ms
     );
 
-    Ok(BatchFetchResult { results, stats })
+    Ok(BatchFetchResult {
+        results,
+        cancelled: was_cancelled,
+        stats,
+    })
 }
 
 /// Fetch a single URL with timeout
 async fn fetch_single_url(
-    client: &reqwest::Client,
+    fetcher: &Fetcher,
     url: &str,
+    request: &FetchOptions,
     timeout: Duration,
     _follow_redirects: bool,
-) -> Result<(u16, String, Option<usize>)> {
+) -> Result<(ResponseInfo, String, Option<usize>)> {
+    let url = &fetch::apply_query(url, &request.query)?;
+    let auth = fetch::auth_headers(fetcher, url, request)?;
+
+    let crawl_delay = fetcher.check_robots(url, request).await?;
+
     // Note: redirect policy is set globally on the client (limited to 10 redirects)
     // Cannot be overridden per-request in reqwest 0.12
-    let response = client
-        .get(url)
-        .timeout(timeout)
-        .send()
-        .await
-        .context(format!("Failed to fetch {}", url))?;
-
-    let status = response.status().as_u16();
+    let request_builder = fetch::build_request(fetcher, url, request, auth).timeout(timeout);
+    let response = fetcher
+        .send_with_retries(request_builder, url, crawl_delay, &fetcher.retry_policy(request))
+        .await?;
+
     let content_length = response.content_length().map(|len| len as usize);
 
     if !response.status().is_success() {
-        anyhow::bail!("HTTP {} for {}", status, url);
+        return Err(fetch::status_error(&response, url));
     }
 
-    let content = response
-        .text()
-        .await
-        .context("Failed to read response body")?;
+    let (content, info) = fetch::decode_text(response, fetcher.body_limit(request)).await?;
 
-    Ok((status, content, content_length))
+    Ok((info, content, content_length))
 }
 
 /// Batch fetch result with statistics
@@ -210,6 +300,10 @@ pub struct BatchFetchResult {
     /// Individual fetch results
     pub results: Vec<FetchResult>,
 
+    /// Batch was cancelled; `results` only holds the URLs finished before that
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+
     /// Batch statistics
     pub stats: BatchStats,
 }
@@ -269,7 +363,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_batch() {
-        let client = reqwest::Client::new();
+        let fetcher = Fetcher::new(reqwest::Client::new(), None);
 
         let urls = vec![
             "https://httpbin.org/delay/1".to_string(),
@@ -283,9 +377,11 @@ mod tests {
             timeout: Duration::from_secs(10),
             fail_fast: false,
             follow_redirects: true,
+            retry: None,
+            request: FetchOptions::default(),
         };
 
-        let result = fetch_batch(&client, urls, options).await;
+        let result = fetch_batch(&fetcher, urls, options, |_, _| {}, std::future::pending()).await;
         assert!(result.is_ok());
 
         let results = result.unwrap();
@@ -305,8 +401,15 @@ mod tests {
                 success: true,
                 content: Some("test".to_string()),
                 error: None,
+                error_code: None,
                 response_time_ms: 100,
                 content_length: Some(4),
+                final_url: Some("https://example.com/".to_string()),
+                headers: None,
+                encoding: None,
+                body_truncated: false,
+                attempts: 1,
+                last_error: None,
             },
             FetchResult {
                 url: "https://example2.com".to_string(),
@@ -314,8 +417,15 @@ mod tests {
                 success: false,
                 content: None,
                 error: Some("Not found".to_string()),
+                error_code: Some(ErrorCode::HttpStatus),
                 response_time_ms: 50,
                 content_length: None,
+                final_url: None,
+                headers: None,
+                encoding: None,
+                body_truncated: false,
+                attempts: 1,
+                last_error: None,
             },
         ];
 
@@ -327,9 +437,128 @@ mod tests {
         assert_eq!(stats.total_bytes, 4);
     }
 
+    #[tokio::test]
+    async fn test_progress_and_cancellation() {
+        use crate::test_util;
+
+        let base = test_util::serve(|_| test_util::response("200 OK", &[], b"ok")).await;
+        let fetcher = test_util::loopback_fetcher(None);
+        let urls: Vec<String> = (0..5).map(|i| format!("{}/{}", base, i)).collect();
+        let options = BatchOptions {
+            max_concurrent: 1,
+            rate_limit: None,
+            ..Default::default()
+        };
+
+        let mut done = Vec::new();
+        let result = fetch_batch(&fetcher, urls.clone(), options.clone(), |_, n| done.push(n), std::future::pending())
+            .await
+            .unwrap();
+        assert_eq!(done, [1, 2, 3, 4, 5]);
+        assert!(!result.cancelled);
+        assert_eq!(result.stats.success, 5);
+
+        // Cancel once two URLs have finished: the rest are dropped
+        let cancel = tokio::sync::Notify::new();
+        let on_result = |_: &FetchResult, n: usize| {
+            if n == 2 {
+                cancel.notify_one();
+            }
+        };
+        let result = fetch_batch(&fetcher, urls, options, on_result, cancel.notified()).await.unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.results.len(), 2);
+        assert!(result.results.iter().all(|r| r.success));
+    }
+
+    #[tokio::test]
+    async fn test_robots_denied_per_url() {
+        use crate::robots::{RobotsCache, DEFAULT_ROBOTS_TTL};
+        use crate::test_util;
+
+        let base = test_util::serve(|request| {
+            if request.starts_with("GET /robots.txt") {
+                test_util::response("200 OK", &[], b"User-agent: *\nDisallow: /private\n")
+            } else {
+                test_util::response("200 OK", &[], b"page")
+            }
+        })
+        .await;
+        let robots = Arc::new(RobotsCache::new("ModelContextProtocol", DEFAULT_ROBOTS_TTL));
+        let fetcher = test_util::loopback_fetcher(None).with_robots(robots);
+        let urls = vec![format!("{}/public", base), format!("{}/private", base)];
+
+        let result = fetch_batch(&fetcher, urls, BatchOptions::default(), |_, _| {}, std::future::pending())
+            .await
+            .unwrap();
+        let denied: Vec<_> = result.results.iter().filter(|r| !r.success).collect();
+        assert_eq!(result.stats.success, 1);
+        assert_eq!(denied.len(), 1);
+        assert!(denied[0].url.ends_with("/private"));
+        assert_eq!(denied[0].error_code, Some(ErrorCode::RobotsDenied));
+        assert_eq!(denied[0].attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy_per_call() {
+        use crate::test_util;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Every other request fails, /down always does
+        let hits = Arc::new(AtomicUsize::new(0));
+        let h = hits.clone();
+        let base = test_util::serve(move |request| {
+            if request.starts_with("GET /down") {
+                test_util::response("503 Service Unavailable", &[], b"")
+            } else if h.fetch_add(1, Ordering::SeqCst).is_multiple_of(2) {
+                test_util::response("502 Bad Gateway", &[], b"")
+            } else {
+                test_util::response("200 OK", &[], b"page")
+            }
+        })
+        .await;
+        let fetcher = test_util::loopback_fetcher(None);
+
+        let options = BatchOptions {
+            retry: Some(RetryPolicy { base_delay_ms: 10, ..Default::default() }),
+            ..Default::default()
+        };
+        let result = fetch_batch(&fetcher, vec![base.clone()], options, |_, _| {}, std::future::pending())
+            .await
+            .unwrap();
+        let fetched = &result.results[0];
+        assert!(fetched.success);
+        assert_eq!(fetched.attempts, 2);
+        assert!(fetched.last_error.as_deref().unwrap().contains("502"));
+
+        let options = BatchOptions {
+            retry: Some(RetryPolicy { max_retries: 2, base_delay_ms: 10, ..Default::default() }),
+            ..Default::default()
+        };
+        let result = fetch_batch(&fetcher, vec![format!("{}/down", base)], options, |_, _| {}, std::future::pending())
+            .await
+            .unwrap();
+        let failed = &result.results[0];
+        assert!(!failed.success);
+        assert_eq!(failed.attempts, 3);
+        let error = failed.error.as_deref().unwrap();
+        assert!(error.contains("gave up after 3 attempts") && error.contains("HTTP 503"), "{}", error);
+        assert!(failed.last_error.as_deref().unwrap().contains("503"));
+
+        let options = BatchOptions {
+            retry: Some(RetryPolicy { max_retries: 0, ..Default::default() }),
+            ..Default::default()
+        };
+        let result = fetch_batch(&fetcher, vec![base], options, |_, _| {}, std::future::pending())
+            .await
+            .unwrap();
+        assert!(!result.results[0].success);
+        assert_eq!(result.results[0].attempts, 1);
+    }
+
     #[tokio::test]
     async fn test_rate_limiting() {
-        let client = reqwest::Client::new();
+        let fetcher = Fetcher::new(reqwest::Client::new(), None);
 
         let urls = vec![
             "https://httpbin.org/delay/0".to_string(),
@@ -343,10 +572,12 @@ mod tests {
             timeout: Duration::from_secs(10),
             fail_fast: false,
             follow_redirects: true,
+            retry: None,
+            request: FetchOptions::default(),
         };
 
         let start = std::time::Instant::now();
-        let result = fetch_batch(&client, urls, options).await;
+        let result = fetch_batch(&fetcher, urls, options, |_, _| {}, std::future::pending()).await;
         let elapsed = start.elapsed();
 
         assert!(result.is_ok());
@@ -0,0 +1,237 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Bytes scanned for `<meta>` and XML encoding declarations (HTML spec prescan)
+const PRESCAN_BYTES: usize = 1024;
+
+static COMMENT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+static META_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<meta[\s/]([^>]*)>").unwrap());
+static ATTR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)([a-z_:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+});
+static XML_DECL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^<\?xml\s[^>]*?encoding\s*=\s*["']([^"']+)["']"#).unwrap()
+});
+
+/// Where the document encoding was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingSource {
+    /// Byte order mark
+    Bom,
+    /// `charset` parameter of the Content-Type header
+    Header,
+    /// `<meta charset>` or `<meta http-equiv="Content-Type">`
+    Meta,
+    /// XML declaration (`<?xml ... encoding="..."?>`)
+    Xml,
+    /// No declaration found: UTF-8 if valid, otherwise windows-1252
+    Default,
+}
+
+/// Detected document encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DetectedEncoding {
+    /// Encoding name (WHATWG canonical name, e.g. "Shift_JIS")
+    pub name: String,
+
+    /// Where the encoding was taken from
+    pub source: EncodingSource,
+}
+
+/// Determine the encoding of a response body
+///
+/// Follows the order of the HTML encoding sniffing algorithm: BOM, transport
+/// layer (Content-Type charset), `<meta>` prescan, then the XML declaration.
+pub fn sniff_encoding(bytes: &[u8], content_type: Option<&str>) -> (&'static Encoding, EncodingSource) {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return (encoding, EncodingSource::Bom);
+    }
+
+    if let Some(encoding) = content_type.and_then(charset_from_content_type) {
+        return (encoding, EncodingSource::Header);
+    }
+
+    let head = &bytes[..bytes.len().min(PRESCAN_BYTES)];
+    // Labels are ASCII, so a Latin-1 view of the prefix is enough to find them
+    let head: String = head.iter().map(|&b| b as char).collect();
+
+    if let Some(encoding) = prescan_meta(&head) {
+        return (encoding, EncodingSource::Meta);
+    }
+
+    if let Some(encoding) = XML_DECL_RE
+        .captures(&head)
+        .and_then(|caps| Encoding::for_label(caps[1].trim().as_bytes()))
+    {
+        return (ascii_compatible(encoding), EncodingSource::Xml);
+    }
+
+    let default = if is_utf8(bytes) { UTF_8 } else { WINDOWS_1252 };
+    (default, EncodingSource::Default)
+}
+
+/// Valid UTF-8, allowing a sequence cut off at the end by the body size limit.
+/// The cut is only tolerated after other non-ASCII text decoded cleanly, so a
+/// lone trailing Latin-1 byte still falls back to windows-1252.
+fn is_utf8(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => {
+            let valid = &bytes[..e.valid_up_to()];
+            e.error_len().is_none() && !valid.is_ascii()
+        }
+    }
+}
+
+/// Decode a response body into text, reporting the encoding used
+pub fn decode(bytes: &[u8], content_type: Option<&str>) -> (String, DetectedEncoding) {
+    let (encoding, source) = sniff_encoding(bytes, content_type);
+    let (text, _) = match source {
+        EncodingSource::Bom => encoding.decode_with_bom_removal(bytes),
+        _ => encoding.decode_without_bom_handling(bytes),
+    };
+
+    let detected = DetectedEncoding {
+        name: encoding.name().to_string(),
+        source,
+    };
+    (text.into_owned(), detected)
+}
+
+/// Extract the `charset` parameter from a Content-Type value
+fn charset_from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes())
+    })
+}
+
+/// Find an encoding declared by a `<meta>` element
+fn prescan_meta(head: &str) -> Option<&'static Encoding> {
+    let head = COMMENT_RE.replace_all(head, "");
+
+    META_RE.captures_iter(&head).find_map(|meta| {
+        let mut charset = None;
+        let mut http_equiv = None;
+        let mut content = None;
+
+        for attr in ATTR_RE.captures_iter(&meta[1]) {
+            let value = attr.get(2).or(attr.get(3)).or(attr.get(4)).map_or("", |m| m.as_str());
+            match attr[1].to_ascii_lowercase().as_str() {
+                "charset" => charset = charset.or(Some(value)),
+                "http-equiv" => http_equiv = http_equiv.or(Some(value)),
+                "content" => content = content.or(Some(value)),
+                _ => {}
+            }
+        }
+
+        let encoding = match (charset, http_equiv, content) {
+            (Some(label), _, _) => Encoding::for_label(label.trim().as_bytes()),
+            (None, Some(equiv), Some(content)) if equiv.eq_ignore_ascii_case("content-type") => {
+                charset_from_meta_content(content)
+            }
+            _ => None,
+        };
+        encoding.map(ascii_compatible)
+    })
+}
+
+/// Extract a charset from a `<meta http-equiv>` content value (`text/html; charset=...`)
+fn charset_from_meta_content(content: &str) -> Option<&'static Encoding> {
+    let lower = content.to_ascii_lowercase();
+    let start = lower.find("charset")? + "charset".len();
+    let value = content[start..].trim_start().strip_prefix('=')?.trim_start();
+    let value = value.trim_start_matches(['"', '\'']);
+    let end = value.find(['"', '\'', ';', ' ', '\t']).unwrap_or(value.len());
+    Encoding::for_label(&value.as_bytes()[..end])
+}
+
+/// In-document declarations of UTF-16 are read as UTF-8, and x-user-defined
+/// as windows-1252, as the HTML spec requires
+fn ascii_compatible(encoding: &'static Encoding) -> &'static Encoding {
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        UTF_8
+    } else if encoding == encoding_rs::X_USER_DEFINED {
+        WINDOWS_1252
+    } else {
+        encoding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_order() {
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode("日本語");
+        let html = [b"<html><head><meta charset=\"shift_jis\"></head><body>".as_slice(), &sjis].concat();
+
+        let (text, detected) = decode(&html, Some("text/html"));
+        assert!(text.contains("日本語"));
+        assert_eq!(detected, DetectedEncoding { name: "Shift_JIS".to_string(), source: EncodingSource::Meta });
+
+        // Header beats meta
+        let (_, detected) = decode(&html, Some("text/html; charset=\"windows-1251\""));
+        assert_eq!(detected.name, "windows-1251");
+        assert_eq!(detected.source, EncodingSource::Header);
+
+        // BOM beats header
+        let bom = [b"\xEF\xBB\xBF".as_slice(), "héllo".as_bytes()].concat();
+        let (text, detected) = decode(&bom, Some("text/html; charset=windows-1252"));
+        assert_eq!(text, "héllo");
+        assert_eq!(detected.source, EncodingSource::Bom);
+    }
+
+    #[test]
+    fn test_meta_http_equiv() {
+        let (cp1251, _, _) = encoding_rs::WINDOWS_1251.encode("Привет");
+        let html = [
+            b"<!-- <meta charset=\"gb18030\"> --><META HTTP-EQUIV='Content-Type' CONTENT='text/html; charset=windows-1251'>".as_slice(),
+            &cp1251,
+        ]
+        .concat();
+        let (text, detected) = decode(&html, None);
+        assert!(text.ends_with("Привет"));
+        assert_eq!(detected.name, "windows-1251");
+        assert_eq!(detected.source, EncodingSource::Meta);
+
+        // UTF-16 declared in-document means UTF-8
+        let (_, detected) = decode(b"<meta charset=utf-16>hi", None);
+        assert_eq!(detected.name, "UTF-8");
+    }
+
+    #[test]
+    fn test_xml_declaration() {
+        let (gb, _, _) = encoding_rs::GB18030.encode("中文");
+        let xml = [b"<?xml version=\"1.0\" encoding=\"GB18030\"?><title>".as_slice(), &gb, b"</title>"].concat();
+        let (text, detected) = decode(&xml, Some("application/xml"));
+        assert!(text.contains("中文"));
+        assert_eq!(detected.name, "gb18030");
+        assert_eq!(detected.source, EncodingSource::Xml);
+    }
+
+    #[test]
+    fn test_default_encoding() {
+        let (_, detected) = decode("plain ütf-8".as_bytes(), Some("text/plain"));
+        assert_eq!(detected.name, "UTF-8");
+        assert_eq!(detected.source, EncodingSource::Default);
+
+        let (text, detected) = decode(b"caf\xE9", None);
+        assert_eq!(text, "café");
+        assert_eq!(detected.name, "windows-1252");
+
+        // A body truncated in the middle of a character is still UTF-8
+        let body = "Привет мир".as_bytes();
+        let (text, detected) = decode(&body[..body.len() - 1], None);
+        assert_eq!(detected.name, "UTF-8");
+        assert!(text.starts_with("Привет ми"));
+    }
+}
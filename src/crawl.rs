@@ -0,0 +1,578 @@
+//! Breadth-first site crawler
+//!
+//! Starts from seed URLs and follows links level by level through the shared
+//! fetch pipeline, so robots.txt, the per-host scheduler, retries and the
+//! destination policy apply to every page. The crawl stops at the depth limit,
+//! when the frontier runs dry, or when the page, byte or time budget is spent.
+
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::charset;
+use crate::error::{ErrorCode, ToolError};
+use crate::fetch::{self, FetchOptions, Fetcher};
+use crate::html_convert;
+use crate::links::{self, LinkExtractionOptions};
+use crate::metadata::{self, PageMetadata};
+use crate::pagination;
+use crate::ssrf::DestinationPolicy;
+use crate::validation;
+
+/// Default link depth followed from the seeds
+pub const DEFAULT_MAX_DEPTH: usize = 2;
+
+/// Default number of pages fetched
+pub const DEFAULT_MAX_PAGES: usize = 20;
+
+/// Default total body bytes downloaded
+pub const DEFAULT_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Default wall time of a crawl in seconds
+pub const DEFAULT_MAX_DURATION_SECS: u64 = 120;
+
+/// Default characters of Markdown kept per page
+pub const DEFAULT_MAX_CONTENT_LENGTH: usize = 5000;
+
+/// Which discovered links are followed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CrawlScope {
+    /// Same host as a seed
+    #[default]
+    SameHost,
+    /// Same registrable domain (eTLD+1) as a seed, e.g. `docs.example.com` from `www.example.com`
+    SameDomain,
+    /// Same origin as a seed and under its directory (`/docs/intro` covers `/docs/...`)
+    PathPrefix,
+    /// URLs matching `pattern`
+    Regex,
+}
+
+/// What each page reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PageContent {
+    /// Readable Markdown of the page
+    #[default]
+    Markdown,
+    /// Title, description, Open Graph and other metadata
+    Metadata,
+    /// Only status and links
+    None,
+}
+
+/// Crawl limits and scope
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    /// Links followed from the seeds (0 fetches only the seeds)
+    pub max_depth: usize,
+
+    /// Pages fetched, including failures
+    pub max_pages: usize,
+
+    /// Total body bytes downloaded
+    pub max_bytes: u64,
+
+    /// Wall time of the whole crawl
+    pub max_duration: Duration,
+
+    /// Which discovered links are followed
+    pub scope: CrawlScope,
+
+    /// URL regex for [`CrawlScope::Regex`]
+    pub pattern: Option<Regex>,
+
+    /// What each page reports
+    pub content: PageContent,
+
+    /// Characters of Markdown kept per page
+    pub max_content_length: Option<usize>,
+
+    /// Pages fetched at once (the per-host scheduler still applies)
+    pub max_concurrent: usize,
+
+    /// Cache, credentials and size limit applied to every page
+    pub request: FetchOptions,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_pages: DEFAULT_MAX_PAGES,
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_duration: Duration::from_secs(DEFAULT_MAX_DURATION_SECS),
+            scope: CrawlScope::SameHost,
+            pattern: None,
+            content: PageContent::Markdown,
+            max_content_length: Some(DEFAULT_MAX_CONTENT_LENGTH),
+            max_concurrent: 4,
+            request: FetchOptions::default(),
+        }
+    }
+}
+
+/// A crawled page
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrawlPage {
+    /// URL that was fetched, as given in the seeds or linked (not normalized)
+    pub url: String,
+
+    /// Final URL after redirects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+
+    /// Links followed from the seeds to reach the page
+    pub depth: usize,
+
+    /// Page the URL was discovered on (none for seeds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+
+    /// HTTP status (0 if no response)
+    pub status: u16,
+
+    /// Response content type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+
+    /// Markdown of the page (`markdown` content, HTML pages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markdown: Option<String>,
+
+    /// Markdown was cut off at `max_content_length`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+
+    /// Page metadata (`metadata` content, HTML pages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<PageMetadata>,
+
+    /// Absolute links found on the page, in and out of scope: the edges of the link graph
+    #[serde(default)]
+    pub links: Vec<String>,
+
+    /// Body bytes downloaded
+    pub bytes: u64,
+
+    /// Failure message (if the page failed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Machine-readable failure category (if the page failed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+}
+
+/// Budget that ended a crawl early
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    MaxPages,
+    MaxBytes,
+    MaxDuration,
+    Cancelled,
+}
+
+/// Result of a crawl
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrawlResult {
+    /// Pages in the order they finished
+    pub pages: Vec<CrawlPage>,
+
+    /// Budget that stopped the crawl (none if the frontier or depth limit was reached)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<StopReason>,
+
+    /// Crawl statistics
+    pub stats: CrawlStats,
+}
+
+/// Crawl statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CrawlStats {
+    /// Pages fetched successfully
+    pub pages_ok: usize,
+
+    /// Pages that failed (including robots.txt denials)
+    pub pages_failed: usize,
+
+    /// Body bytes downloaded
+    pub total_bytes: u64,
+
+    /// Deepest level fetched
+    pub max_depth_reached: usize,
+
+    /// In-scope URLs discovered but not fetched
+    pub frontier_remaining: usize,
+
+    /// Total time elapsed in milliseconds
+    pub total_time_ms: u64,
+}
+
+/// Normalize a URL for deduplication
+///
+/// Drops the fragment and an empty query and sorts query parameters; scheme and
+/// host case, default ports and empty paths are already normalized by parsing.
+pub fn normalize_url(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    if url.query().is_some_and(str::is_empty) {
+        url.set_query(None);
+    } else if url.query().is_some() {
+        let mut pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        pairs.sort();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// Which URLs are in scope, relative to the seeds
+struct Scope<'a> {
+    kind: CrawlScope,
+    pattern: Option<&'a Regex>,
+    seeds: Vec<Url>,
+}
+
+impl Scope<'_> {
+    fn contains(&self, url: &Url) -> bool {
+        match self.kind {
+            CrawlScope::SameHost => self.seeds.iter().any(|seed| seed.host_str() == url.host_str()),
+            CrawlScope::SameDomain => {
+                let domain = url.host_str().map(registrable_domain);
+                self.seeds.iter().any(|seed| seed.host_str().map(registrable_domain) == domain)
+            }
+            CrawlScope::PathPrefix => self.seeds.iter().any(|seed| {
+                let directory = &seed.path()[..=seed.path().rfind('/').unwrap_or(0)];
+                seed.origin() == url.origin() && url.path().starts_with(directory)
+            }),
+            CrawlScope::Regex => self.pattern.is_some_and(|pattern| pattern.is_match(url.as_str())),
+        }
+    }
+}
+
+// eTLD+1 of a host, or the host itself for IP literals and bare suffixes
+fn registrable_domain(host: &str) -> &str {
+    psl::domain_str(host).unwrap_or(host)
+}
+
+/// Crawl breadth-first from `seeds`
+///
+/// Discovered links are followed when in scope and accepted by `policy` (the
+/// domain lists and SSRF address check). `on_page` receives every page with the
+/// number finished so far. When `cancelled` resolves, in-flight fetches are
+/// dropped and the pages gathered until then are returned.
+pub async fn crawl(
+    fetcher: &Fetcher,
+    policy: &DestinationPolicy,
+    seeds: Vec<String>,
+    options: CrawlOptions,
+    mut on_page: impl FnMut(&CrawlPage, usize),
+    cancelled: impl Future<Output = ()>,
+) -> Result<CrawlResult> {
+    if options.scope == CrawlScope::Regex && options.pattern.is_none() {
+        anyhow::bail!("The regex scope requires a pattern");
+    }
+
+    let start = Instant::now();
+    let seeds = seeds
+        .iter()
+        .map(|seed| Url::parse(seed).with_context(|| format!("Invalid seed URL {}", seed)))
+        .collect::<Result<Vec<_>>>()?;
+    let scope = Scope {
+        kind: options.scope,
+        pattern: options.pattern.as_ref(),
+        seeds: seeds.clone(),
+    };
+
+    let mut seen = HashSet::new();
+    let mut frontier: Vec<(String, Option<String>)> = seeds
+        .iter()
+        .filter(|url| seen.insert(normalize_url(url)))
+        .map(|url| (url.to_string(), None))
+        .collect();
+
+    tracing::info!("Crawling from {} seeds (depth: {}, pages: {})", frontier.len(), options.max_depth, options.max_pages);
+
+    let mut cancelled = std::pin::pin!(cancelled);
+    let mut deadline = std::pin::pin!(tokio::time::sleep(options.max_duration));
+    let mut pages: Vec<CrawlPage> = Vec::new();
+    let mut stats = CrawlStats::default();
+    let mut stop_reason = None;
+
+    'levels: for depth in 0..=options.max_depth {
+        if frontier.is_empty() {
+            break;
+        }
+        let remaining = options.max_pages.saturating_sub(pages.len());
+        if frontier.len() > remaining {
+            stop_reason = Some(StopReason::MaxPages);
+        }
+        if remaining == 0 {
+            break;
+        }
+        let level: Vec<_> = frontier.drain(..remaining.min(frontier.len())).collect();
+        let mut next = Vec::new();
+
+        let fetches = stream::iter(level.into_iter().map(|(url, parent)| {
+            let options = &options;
+            async move { fetch_page(fetcher, url, parent, depth, options).await }
+        }));
+        let mut fetches = std::pin::pin!(fetches.buffer_unordered(options.max_concurrent.max(1)));
+
+        loop {
+            tokio::select! {
+                page = fetches.next() => {
+                    let Some(page) = page else { break };
+
+                    if let Some(final_url) = page.final_url.as_deref().and_then(|url| Url::parse(url).ok()) {
+                        seen.insert(normalize_url(&final_url));
+                    }
+                    if depth < options.max_depth {
+                        for link in &page.links {
+                            let Ok(parsed) = Url::parse(link) else { continue };
+                            if scope.contains(&parsed)
+                                && is_allowed(policy, link)
+                                && seen.insert(normalize_url(&parsed))
+                            {
+                                next.push((link.clone(), Some(page.url.clone())));
+                            }
+                        }
+                    }
+
+                    stats.total_bytes += page.bytes;
+                    stats.max_depth_reached = depth;
+                    if page.error.is_some() {
+                        stats.pages_failed += 1;
+                    } else {
+                        stats.pages_ok += 1;
+                    }
+                    on_page(&page, pages.len() + 1);
+                    pages.push(page);
+
+                    if stats.total_bytes >= options.max_bytes {
+                        stop_reason = Some(StopReason::MaxBytes);
+                        break 'levels;
+                    }
+                }
+                _ = &mut deadline => {
+                    stop_reason = Some(StopReason::MaxDuration);
+                    break 'levels;
+                }
+                _ = &mut cancelled => {
+                    tracing::info!("Crawl cancelled after {} pages", pages.len());
+                    stop_reason = Some(StopReason::Cancelled);
+                    break 'levels;
+                }
+            }
+        }
+
+        frontier.extend(next);
+    }
+
+    // Discovered links that were never fetched
+    stats.frontier_remaining = frontier.len();
+    stats.total_time_ms = start.elapsed().as_millis() as u64;
+
+    tracing::info!(
+        "Crawl finished: {} pages ({} failed), {} bytes in {}ms",
+        pages.len(),
+        stats.pages_failed,
+        stats.total_bytes,
+        stats.total_time_ms
+    );
+
+    Ok(CrawlResult {
+        pages,
+        stop_reason,
+        stats,
+    })
+}
+
+// Discovered links must pass the same checks as URLs passed to tools
+fn is_allowed(policy: &DestinationPolicy, url: &str) -> bool {
+    validation::validate_url(url, &policy.access).is_ok() && policy.check_url(url).is_ok()
+}
+
+/// Fetch one page and extract its links and content
+async fn fetch_page(
+    fetcher: &Fetcher,
+    url: String,
+    parent: Option<String>,
+    depth: usize,
+    options: &CrawlOptions,
+) -> CrawlPage {
+    let mut page = CrawlPage {
+        url,
+        final_url: None,
+        depth,
+        parent,
+        status: 0,
+        content_type: None,
+        markdown: None,
+        truncated: false,
+        metadata: None,
+        links: Vec::new(),
+        bytes: 0,
+        error: None,
+        error_code: None,
+    };
+
+    let (body, info) = match fetch::fetch_url_bytes(fetcher, &page.url, &options.request).await {
+        Ok(fetched) => fetched,
+        Err(err) => {
+            tracing::debug!("Crawl failed for {}: {:#}", page.url, err);
+            let failure = ToolError::classify(ErrorCode::Network, "Failed to fetch", &err);
+            page.status = failure.status.unwrap_or(0);
+            page.error = Some(failure.message);
+            page.error_code = Some(failure.code);
+            return page;
+        }
+    };
+
+    page.status = info.status;
+    page.bytes = body.len() as u64;
+    page.final_url = Some(info.final_url.clone());
+    let content_type = info.headers.get("content-type").cloned().unwrap_or_default();
+    page.content_type = (!content_type.is_empty()).then(|| content_type.clone());
+
+    if fetch::detect_content_type(&content_type, &body) != fetch::ContentType::Html {
+        return page;
+    }
+
+    let (html, _) = charset::decode(&body, Some(&content_type));
+    let found = links::extract_links(&html, &info.final_url, LinkExtractionOptions::default()).unwrap_or_default();
+    // Links are fetched as written; the normalized form only detects duplicates
+    let mut seen = HashSet::new();
+    page.links = found
+        .iter()
+        .filter_map(|link| Url::parse(&link.href).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .filter(|url| seen.insert(normalize_url(url)))
+        .map(|mut url| {
+            url.set_fragment(None);
+            url.to_string()
+        })
+        .collect();
+
+    match options.content {
+        PageContent::Markdown => match html_convert::html_to_markdown(&html, &info.final_url, Default::default()) {
+            Ok(markdown) => {
+                let text = pagination::paginate(&markdown, 0, options.max_content_length);
+                page.truncated = text.truncated;
+                page.markdown = Some(text.content);
+            }
+            Err(err) => page.error = Some(format!("Failed to convert HTML: {:#}", err)),
+        },
+        PageContent::Metadata => match metadata::extract_metadata(&html, &info.final_url) {
+            Ok(metadata) => page.metadata = Some(metadata),
+            Err(err) => page.error = Some(format!("Failed to extract metadata: {:#}", err)),
+        },
+        PageContent::None => {}
+    }
+
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robots::{RobotsCache, DEFAULT_ROBOTS_TTL};
+    use crate::test_util;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_normalize_url() {
+        let url = Url::parse("HTTP://Example.COM:80/a?b=2&a=1#top").unwrap();
+        assert_eq!(normalize_url(&url), "http://example.com/a?a=1&b=2");
+        assert_eq!(normalize_url(&Url::parse("https://example.com?").unwrap()), "https://example.com/");
+        assert_eq!(
+            normalize_url(&Url::parse("https://example.com/b?y=2&x=1").unwrap()),
+            normalize_url(&Url::parse("https://example.com/b?x=1&y=2#top").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_scope_contains() {
+        let seeds = vec![Url::parse("https://www.example.co.uk/docs/intro").unwrap()];
+        let scope = |kind| Scope { kind, pattern: None, seeds: seeds.clone() };
+        let docs = Url::parse("https://docs.example.co.uk/").unwrap();
+        let guide = Url::parse("https://www.example.co.uk/docs/guide/1").unwrap();
+        assert!(!scope(CrawlScope::SameHost).contains(&docs));
+        assert!(scope(CrawlScope::SameDomain).contains(&docs));
+        assert!(!scope(CrawlScope::SameDomain).contains(&Url::parse("https://other.co.uk/").unwrap()));
+        assert!(scope(CrawlScope::PathPrefix).contains(&guide));
+        assert!(!scope(CrawlScope::PathPrefix).contains(&Url::parse("https://www.example.co.uk/blog").unwrap()));
+
+        let pattern = Regex::new("/guide/").unwrap();
+        let regex = Scope { kind: CrawlScope::Regex, pattern: Some(&pattern), seeds: seeds.clone() };
+        assert!(regex.contains(&guide));
+        assert!(!regex.contains(&docs));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_site() {
+        let base = test_util::serve(|request| {
+            let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+            let html = |body: &str| test_util::response("200 OK", &[("Content-Type", "text/html")], body.as_bytes());
+            match path.as_str() {
+                "/robots.txt" => test_util::response("200 OK", &[], b"User-agent: *\nDisallow: /private\n"),
+                "/" => html(
+                    r##"<h1>Home</h1><a href="/a#intro">A</a> <a href="/b?y=2&x=1">B</a>
+                    <a href="/private">P</a> <a href="https://elsewhere.example/">Out</a>"##,
+                ),
+                "/a" => html(r#"<p>Page A</p><a href="/">Home</a> <a href="/b?x=1&y=2">B</a> <a href="/a/deep">Deep</a>"#),
+                // Only served with the query as written on the home page
+                "/b?y=2&x=1" => html("<p>Page B</p>"),
+                _ => test_util::response("404 Not Found", &[], b"missing"),
+            }
+        })
+        .await;
+        let robots = Arc::new(RobotsCache::new("ModelContextProtocol", DEFAULT_ROBOTS_TTL));
+        let fetcher = test_util::loopback_fetcher(None).with_robots(robots);
+        let policy = test_util::loopback_policy();
+
+        let options = CrawlOptions { max_depth: 1, ..Default::default() };
+        let result = crawl(&fetcher, &policy, vec![format!("{}/", base)], options, |_, _| {}, std::future::pending())
+            .await
+            .unwrap();
+
+        // Seed plus its in-scope links, each once; /a/deep is beyond the depth limit
+        let mut urls: Vec<&str> = result.pages.iter().map(|page| page.url.strip_prefix(&base).unwrap()).collect();
+        urls.sort();
+        assert_eq!(urls, vec!["/", "/a", "/b?y=2&x=1", "/private"]);
+        assert_eq!(result.stop_reason, None);
+        assert_eq!(result.stats.pages_ok, 3);
+        assert_eq!(result.stats.max_depth_reached, 1);
+        assert_eq!(result.stats.frontier_remaining, 0);
+
+        let home = result.pages.iter().find(|page| page.depth == 0).unwrap();
+        assert!(home.links.contains(&format!("{}/a", base)));
+        assert!(home.links.contains(&format!("{}/b?y=2&x=1", base)));
+        assert!(home.links.contains(&"https://elsewhere.example/".to_string()));
+        let page_a = result.pages.iter().find(|page| page.url.ends_with("/a")).unwrap();
+        assert!(page_a.markdown.as_deref().unwrap().starts_with("Page A"));
+        assert_eq!(page_a.depth, 1);
+        let private = result.pages.iter().find(|page| page.url.ends_with("/private")).unwrap();
+        assert_eq!(private.error_code, Some(ErrorCode::RobotsDenied));
+        assert_eq!(private.parent.as_deref(), Some(home.url.as_str()));
+
+        // The page budget stops the crawl and leaves the rest in the frontier
+        let options = CrawlOptions { max_pages: 2, content: PageContent::Metadata, ..Default::default() };
+        let result = crawl(&fetcher, &policy, vec![format!("{}/", base)], options, |_, _| {}, std::future::pending())
+            .await
+            .unwrap();
+        assert_eq!(result.pages.len(), 2);
+        assert_eq!(result.stop_reason, Some(StopReason::MaxPages));
+        assert_eq!(result.stats.frontier_remaining, 3);
+        assert!(result.pages[0].metadata.is_some());
+        assert!(result.pages[0].markdown.is_none());
+    }
+}
@@ -0,0 +1,362 @@
+//! Domain patterns, per-domain override blocks and the domain access policy
+
+use anyhow::{bail, Context, Result};
+use ipnet::IpNet;
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::ssrf;
+
+/// Host pattern: a host or registrable domain, a glob, a regex or a CIDR range
+///
+/// - `example.co.uk` matches that host, and since it is a registrable domain
+///   (eTLD+1) also every host under it; `api.example.com` matches one host only
+/// - Globs use `*` for any run of characters (dots included) and `?` for one
+///   character of a label, so `*.example.com` matches every subdomain but not
+///   `example.com` itself
+/// - `/regex/` is matched against the lowercase host
+/// - A CIDR range or IP address matches IP literals and, where the policy
+///   checks them, the addresses a host resolves to
+#[derive(Clone)]
+pub struct DomainPattern {
+    pattern: String,
+    kind: PatternKind,
+}
+
+#[derive(Clone)]
+enum PatternKind {
+    Host { registrable: bool },
+    Glob(Regex),
+    Regex(Regex),
+    Cidr(IpNet),
+}
+
+impl DomainPattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let pattern = pattern.trim();
+        if let Some(regex) = pattern.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            let regex = Regex::new(regex).with_context(|| format!("Invalid domain regex {}", pattern))?;
+            return Ok(Self { pattern: pattern.to_string(), kind: PatternKind::Regex(regex) });
+        }
+
+        let pattern = normalize(pattern);
+        if pattern.is_empty() {
+            bail!("Empty domain pattern");
+        }
+        if pattern.contains('/') || pattern.parse::<IpAddr>().is_ok() {
+            let net = ssrf::parse_cidr(&pattern)?;
+            return Ok(Self { pattern, kind: PatternKind::Cidr(net) });
+        }
+
+        let kind = if pattern.contains(['*', '?']) {
+            let mut regex = String::from("^");
+            for c in pattern.chars() {
+                match c {
+                    '*' => regex.push_str(".*"),
+                    '?' => regex.push_str("[^.]"),
+                    c => regex.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+            regex.push('$');
+            PatternKind::Glob(Regex::new(&regex).with_context(|| format!("Invalid domain pattern {}", pattern))?)
+        } else {
+            PatternKind::Host { registrable: psl::domain_str(&pattern) == Some(pattern.as_str()) }
+        };
+
+        Ok(Self { pattern, kind })
+    }
+
+    /// Check a host name or IP literal
+    pub fn matches(&self, host: &str) -> bool {
+        let host = normalize(host);
+        match &self.kind {
+            PatternKind::Host { registrable } => {
+                host == self.pattern
+                    || (*registrable && host.strip_suffix(&self.pattern).is_some_and(|sub| sub.ends_with('.')))
+            }
+            PatternKind::Glob(regex) | PatternKind::Regex(regex) => regex.is_match(&host),
+            PatternKind::Cidr(net) => host.parse::<IpAddr>().is_ok_and(|ip| net.contains(&ip)),
+        }
+    }
+
+    /// Check a resolved address (CIDR patterns only)
+    pub fn matches_ip(&self, ip: IpAddr) -> bool {
+        matches!(&self.kind, PatternKind::Cidr(net) if net.contains(&ip))
+    }
+
+    fn is_cidr(&self) -> bool {
+        matches!(self.kind, PatternKind::Cidr(_))
+    }
+}
+
+fn normalize(host: &str) -> String {
+    host.trim()
+        .trim_end_matches('.')
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase()
+}
+
+impl fmt::Debug for DomainPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DomainPattern({:?})", self.pattern)
+    }
+}
+
+impl fmt::Display for DomainPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl std::str::FromStr for DomainPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(pattern: &str) -> Result<Self> {
+        Self::new(pattern)
+    }
+}
+
+impl Serialize for DomainPattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.pattern)
+    }
+}
+
+impl<'de> Deserialize<'de> for DomainPattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Self::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// `[[domains]]` block: overrides for hosts matching a pattern
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainRule {
+    /// Hosts the block applies to
+    #[serde(rename = "match")]
+    pub pattern: DomainPattern,
+
+    /// Sustained requests per second to one host (0 = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_second: Option<f64>,
+
+    /// Requests allowed at once before the rate applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+
+    /// Requests in flight to one host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<usize>,
+
+    /// Headers added to every request; headers passed by the call take precedence
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl DomainRule {
+    /// Headers of the block as a header map
+    pub fn header_map(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name {} for {}", name, self.pattern))?;
+            let value = HeaderValue::from_str(value)
+                .with_context(|| format!("Invalid value of header {} for {}", name, self.pattern))?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+/// The block that applies to `host`: the first one matching it
+pub fn rule_for<'a>(rules: &'a [DomainRule], host: &str) -> Option<&'a DomainRule> {
+    rules.iter().find(|rule| rule.pattern.matches(host))
+}
+
+/// Domain allow and deny lists, checked before every request and redirect hop
+///
+/// A host matching a deny pattern is refused; with a non-empty allow list, so
+/// is a host matching none of its patterns. CIDR patterns are also checked
+/// against the addresses a host name resolves to.
+#[derive(Debug, Clone, Default)]
+pub struct AccessPolicy {
+    pub allow: Vec<DomainPattern>,
+    pub deny: Vec<DomainPattern>,
+}
+
+impl AccessPolicy {
+    pub fn new(allow: Vec<DomainPattern>, deny: Vec<DomainPattern>) -> Self {
+        Self { allow, deny }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Check a URL host (name or IP literal)
+    ///
+    /// A host name matching no allow pattern passes when the allow list has
+    /// CIDR patterns, which [`check_addr`](Self::check_addr) applies once it is resolved.
+    pub fn check_host(&self, host: &str) -> Result<(), PolicyDenied> {
+        if let Some(pattern) = self.deny.iter().find(|pattern| pattern.matches(host)) {
+            return Err(PolicyDenied::new(host, "deny_domains", Some(pattern)));
+        }
+        let is_name = normalize(host).parse::<IpAddr>().is_err();
+        let deferred = is_name && self.allow.iter().any(DomainPattern::is_cidr);
+        if !self.allow.is_empty() && !deferred && !self.allow.iter().any(|pattern| pattern.matches(host)) {
+            return Err(PolicyDenied::new(host, "allow_domains", None));
+        }
+        Ok(())
+    }
+
+    /// Check an address `host` resolved to
+    pub fn check_addr(&self, host: &str, ip: IpAddr) -> Result<(), PolicyDenied> {
+        if let Some(pattern) = self.deny.iter().find(|pattern| pattern.matches_ip(ip)) {
+            return Err(PolicyDenied::new(&format!("{} ({})", host, ip), "deny_domains", Some(pattern)));
+        }
+        let allowed = self.allow.is_empty()
+            || self.allow.iter().any(|pattern| pattern.matches(host) || pattern.matches_ip(ip));
+        if !allowed {
+            return Err(PolicyDenied::new(&format!("{} ({})", host, ip), "allow_domains", None));
+        }
+        Ok(())
+    }
+}
+
+/// Destination refused by the domain allow/deny lists
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{}", self.describe())]
+pub struct PolicyDenied {
+    pub host: String,
+    /// List that refused the host: `deny_domains` or `allow_domains`
+    pub list: &'static str,
+    /// Deny pattern that matched
+    pub rule: Option<String>,
+}
+
+impl PolicyDenied {
+    fn new(host: &str, list: &'static str, rule: Option<&DomainPattern>) -> Self {
+        Self {
+            host: host.to_string(),
+            list,
+            rule: rule.map(ToString::to_string),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match &self.rule {
+            Some(rule) => format!("Host {} is denied by {} rule '{}'", self.host, self.list, rule),
+            None => format!("Host {} matches no {} rule", self.host, self.list),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_patterns() {
+        let glob = DomainPattern::new("*.Example.com").unwrap();
+        assert!(glob.matches("a.example.com"));
+        assert!(glob.matches("a.b.EXAMPLE.com."));
+        assert!(!glob.matches("example.com"));
+        assert!(!glob.matches("badexample.com"));
+
+        let label = DomainPattern::new("api-?.example.org").unwrap();
+        assert!(label.matches("api-1.example.org"));
+        assert!(!label.matches("api-10.example.org"));
+
+        // A registrable domain covers its subdomains, under a multi-label suffix too
+        let site = DomainPattern::new("example.co.uk").unwrap();
+        assert!(site.matches("example.co.uk"));
+        assert!(site.matches("www.example.co.uk"));
+        assert!(!site.matches("other.co.uk"));
+        assert!(!site.matches("notexample.co.uk"));
+
+        // Other plain patterns match one host only
+        let host = DomainPattern::new("api.example.com").unwrap();
+        assert!(host.matches("api.example.com"));
+        assert!(!host.matches("v2.api.example.com"));
+
+        assert!(DomainPattern::new(" ").is_err());
+    }
+
+    #[test]
+    fn test_domain_rules() {
+        let rules: Vec<DomainRule> = toml::from_str::<BTreeMap<String, Vec<DomainRule>>>(
+            r#"
+            [[domains]]
+            match = "api.example.com"
+            headers = { "X-Client" = "fetch-mcp" }
+
+            [[domains]]
+            match = "example.com"
+            requests_per_second = 1
+            "#,
+        )
+        .unwrap()
+        .remove("domains")
+        .unwrap();
+
+        assert_eq!(rule_for(&rules, "api.example.com").unwrap().headers.len(), 1);
+        assert_eq!(rule_for(&rules, "www.example.com").unwrap().requests_per_second, Some(1.0));
+        assert!(rule_for(&rules, "example.org").is_none());
+        assert_eq!(rules[0].header_map().unwrap()["x-client"], "fetch-mcp");
+    }
+
+    #[test]
+    fn test_regex_and_cidr_patterns() {
+        let regex = DomainPattern::new("/^(www\\.)?(facebook|instagram)\\.com$/").unwrap();
+        assert!(regex.matches("www.Facebook.com"));
+        assert!(!regex.matches("facebook.com.evil.example"));
+        assert_eq!(regex.to_string(), "/^(www\\.)?(facebook|instagram)\\.com$/");
+        assert!(DomainPattern::new("/(/").is_err());
+
+        let cidr = DomainPattern::new("203.0.113.0/24").unwrap();
+        assert!(cidr.matches("203.0.113.7"));
+        assert!(!cidr.matches("example.com"));
+        assert!(cidr.matches_ip("203.0.113.7".parse().unwrap()));
+        assert!(DomainPattern::new("2001:db8::1").unwrap().matches("[2001:db8::1]"));
+        assert!(DomainPattern::new("10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn test_access_policy() {
+        let pattern = |value: &str| DomainPattern::new(value).unwrap();
+        let policy = AccessPolicy::new(
+            vec![pattern("example.com"), pattern("*.docs.rs")],
+            vec![pattern("private.example.com"), pattern("/\\.zip$/"), pattern("198.51.100.0/24")],
+        );
+        assert!(policy.check_host("www.example.com").is_ok());
+        assert!(policy.check_host("serde.docs.rs").is_ok());
+
+        let denied = policy.check_host("private.example.com").unwrap_err();
+        assert_eq!(denied.list, "deny_domains");
+        assert_eq!(denied.to_string(), "Host private.example.com is denied by deny_domains rule 'private.example.com'");
+        assert_eq!(policy.check_host("malware.zip").unwrap_err().rule.as_deref(), Some("/\\.zip$/"));
+
+        let missed = policy.check_host("example.org").unwrap_err();
+        assert_eq!(missed.list, "allow_domains");
+        assert!(missed.rule.is_none());
+
+        // Deny CIDR ranges apply to resolved addresses
+        assert!(policy.check_addr("www.example.com", "93.184.216.34".parse().unwrap()).is_ok());
+        assert!(policy.check_addr("www.example.com", "198.51.100.9".parse().unwrap()).is_err());
+
+        // Host names not allowed by name wait for their addresses when the allow list has ranges
+        let ranges = AccessPolicy::new(vec![pattern("192.0.2.0/24")], Vec::new());
+        assert!(ranges.check_host("intranet.example").is_ok());
+        assert!(ranges.check_addr("intranet.example", "192.0.2.10".parse().unwrap()).is_ok());
+        assert!(ranges.check_addr("intranet.example", "192.0.3.10".parse().unwrap()).is_err());
+        assert!(ranges.check_host("192.0.3.10").is_err());
+
+        assert!(AccessPolicy::default().check_host("anything.example").is_ok());
+    }
+}
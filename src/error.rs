@@ -0,0 +1,236 @@
+//! Tool failures reported to clients
+//!
+//! A failing tool call returns a result with `isError: true` whose text is a
+//! JSON [`ToolError`], so clients can tell a 404 from a timeout and know whether
+//! to retry. Protocol errors are reserved for faults of the server itself.
+
+use rmcp::ErrorData as McpError;
+use rmcp::model::{Content, IntoContents};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::domains::PolicyDenied;
+use crate::fetch::HttpStatusError;
+use crate::retry::{self, RetriesExhausted};
+use crate::robots::RobotsDenied;
+use crate::ssrf::BlockedDestination;
+
+/// Machine-readable failure category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Invalid arguments or a URL outside the allowed destinations
+    Validation,
+    /// Connection failed or was reset
+    Network,
+    /// Server answered with a non-success status
+    HttpStatus,
+    /// robots.txt forbids the fetch
+    RobotsDenied,
+    /// The domain allow/deny lists refuse the destination or a redirect hop
+    PolicyDenied,
+    /// Response could not be parsed by the handler
+    Parse,
+    /// Response body exceeded the size limit and could not be parsed
+    TooLarge,
+    /// Request timed out
+    Timeout,
+    /// Unexpected failure inside the tool
+    Internal,
+}
+
+/// Failure of a tool call
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ToolError {
+    /// Failure category
+    pub code: ErrorCode,
+
+    /// Human-readable description, including the underlying cause
+    pub message: String,
+
+    /// HTTP status of the failed response (`http_status` errors)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    /// Repeating the same call may succeed
+    pub retryable: bool,
+
+    /// Seconds to wait before retrying, from the server's `Retry-After` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+
+    /// Requests made, including retries (when a request was sent)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
+}
+
+impl ToolError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            status: None,
+            retryable: matches!(code, ErrorCode::Network | ErrorCode::Timeout),
+            retry_after_secs: None,
+            attempts: None,
+        }
+    }
+
+    /// Classify an error by its typed causes, falling back to `code`
+    ///
+    /// `context` prefixes the message, e.g. "Failed to fetch URL".
+    pub fn classify(code: ErrorCode, context: &str, err: &anyhow::Error) -> Self {
+        let message = format!("{}: {:#}", context, err);
+        let retried = err.downcast_ref::<RetriesExhausted>().map(|retried| retried.attempts);
+
+        // Refused at DNS resolution or on a redirect hop, under a reqwest error
+        if err.chain().any(|cause| cause.is::<PolicyDenied>()) {
+            return Self::new(ErrorCode::PolicyDenied, message);
+        }
+        // Resolved to an address the SSRF policy blocks: refused on every attempt
+        if err.chain().any(|cause| cause.is::<BlockedDestination>()) {
+            return Self::new(ErrorCode::Validation, message);
+        }
+
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<HttpStatusError>() {
+                return Self {
+                    status: Some(err.status.as_u16()),
+                    retryable: retry::is_transient_status(err.status),
+                    retry_after_secs: err.retry_after.map(|delay| delay.as_secs()),
+                    attempts: Some(retried.unwrap_or(1)),
+                    ..Self::new(ErrorCode::HttpStatus, message)
+                };
+            }
+            if cause.is::<RobotsDenied>() {
+                return Self::new(ErrorCode::RobotsDenied, message);
+            }
+            if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+                let code = if err.is_timeout() {
+                    ErrorCode::Timeout
+                } else if err.is_builder() {
+                    ErrorCode::Validation
+                } else if err.is_decode() {
+                    ErrorCode::Parse
+                } else {
+                    ErrorCode::Network
+                };
+                return Self {
+                    // Redirect loops and refused redirect targets fail the same way again
+                    retryable: matches!(code, ErrorCode::Network | ErrorCode::Timeout) && !err.is_redirect(),
+                    attempts: Some(retried.unwrap_or(1)),
+                    ..Self::new(code, message)
+                };
+            }
+        }
+
+        Self::new(code, message)
+    }
+
+    /// Binary body cut off at `max_bytes`, which its parser can't handle
+    pub fn too_large(url: &str, max_bytes: u64) -> Self {
+        Self::new(
+            ErrorCode::TooLarge,
+            format!("Response body of {} exceeds max_bytes ({}) and was not parsed", url, max_bytes),
+        )
+    }
+}
+
+impl std::fmt::Display for ToolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl IntoContents for ToolError {
+    fn into_contents(self) -> Vec<Content> {
+        let text = serde_json::to_string(&self).unwrap_or_else(|_| self.message.clone());
+        vec![Content::text(text)]
+    }
+}
+
+// Prompts and resources have no error results; their failures are protocol errors
+impl From<ToolError> for McpError {
+    fn from(err: ToolError) -> Self {
+        let data = serde_json::to_value(&err).ok();
+        match err.code {
+            ErrorCode::Validation => McpError::invalid_params(err.message, data),
+            _ => McpError::internal_error(err.message, data),
+        }
+    }
+}
+
+/// Error mapper for `map_err`: classify the error with `code` as the fallback
+pub fn tool_err<E: Into<anyhow::Error>>(code: ErrorCode, context: &'static str) -> impl FnOnce(E) -> ToolError + Clone {
+    move |err| ToolError::classify(code, context, &err.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::{self, FetchOptions};
+    use crate::test_util;
+
+    #[tokio::test]
+    async fn test_classify_fetch_errors() {
+        let base = test_util::serve(|request| {
+            if request.starts_with("GET /busy") {
+                test_util::response("503 Service Unavailable", &[("Retry-After", "7")], b"")
+            } else {
+                test_util::response("404 Not Found", &[], b"missing")
+            }
+        })
+        .await;
+        let fetcher = test_util::loopback_fetcher(None);
+
+        let err = fetch::fetch_url_text(&fetcher, &format!("{}/gone", base), &FetchOptions::default())
+            .await
+            .unwrap_err();
+        let error = ToolError::classify(ErrorCode::Network, "Failed to fetch URL", &err);
+        assert_eq!(error.code, ErrorCode::HttpStatus);
+        assert_eq!(error.status, Some(404));
+        assert!(!error.retryable);
+        assert!(error.message.starts_with("Failed to fetch URL: HTTP 404 Not Found"));
+
+        let err = fetch::fetch_url_text(&fetcher, &format!("{}/busy", base), &FetchOptions::default())
+            .await
+            .unwrap_err();
+        let error = ToolError::classify(ErrorCode::Network, "Failed to fetch URL", &err);
+        assert_eq!(error.status, Some(503));
+        assert!(error.retryable);
+        assert_eq!(error.retry_after_secs, Some(7));
+
+        // Nothing listens on the port once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let err = fetch::fetch_url_text(&fetcher, &closed, &FetchOptions::default()).await.unwrap_err();
+        let error = ToolError::classify(ErrorCode::Parse, "Failed to fetch URL", &err);
+        assert_eq!(error.code, ErrorCode::Network);
+        assert!(error.retryable);
+
+        // A name resolving to loopback is refused by the default SSRF policy, not retried
+        let client = fetch::create_client(&fetch::ClientOptions::new(fetch::DEFAULT_USER_AGENT_AUTONOMOUS), Default::default()).unwrap();
+        let guarded = fetch::Fetcher::new(client, None);
+        let local = base.replace("127.0.0.1", "localhost");
+        let err = fetch::fetch_url_text(&guarded, &local, &FetchOptions::default()).await.unwrap_err();
+        let error = ToolError::classify(ErrorCode::Network, "Failed to fetch URL", &err);
+        assert_eq!(error.code, ErrorCode::Validation);
+        assert!(!error.retryable);
+        assert!(error.message.contains("blocked by the SSRF policy"), "{}", error.message);
+
+        // Untyped errors keep the fallback code
+        let error = tool_err(ErrorCode::Parse, "Failed to parse feed")(anyhow::anyhow!("unexpected token"));
+        assert_eq!(error.code, ErrorCode::Parse);
+        assert_eq!(error.message, "Failed to parse feed: unexpected token");
+
+        let value: serde_json::Value = serde_json::from_str(&ToolError::too_large("https://a.example/", 10)
+            .into_contents()[0]
+            .as_text()
+            .unwrap()
+            .text)
+            .unwrap();
+        assert_eq!(value["code"], "too_large");
+        assert_eq!(value["retryable"], false);
+    }
+}
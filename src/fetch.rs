@@ -1,9 +1,22 @@
 use anyhow::{Context, Result};
-use bytes::Bytes;
-use cached::proc_macro::cached;
-use reqwest::{Client, Response};
+use bytes::{Bytes, BytesMut};
+use reqwest::header::HeaderMap;
+use reqwest::{header, Client, Method, Request, RequestBuilder, Response, StatusCode};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::time::Duration;
+use url::Url;
+
+use crate::auth::CredentialStore;
+use crate::charset::{self, DetectedEncoding};
+use crate::domains::{self, DomainRule};
+use crate::http_cache::{self, CacheEntry, CacheMode, HttpCache};
+use crate::retry::{self, Attempts, RetriesExhausted, RetryPolicy};
+use crate::robots::RobotsCache;
+use crate::scheduler::Scheduler;
+use crate::ssrf::{self, DestinationPolicy};
 
 /// User agent for autonomous fetching (via tool)
 pub const DEFAULT_USER_AGENT_AUTONOMOUS: &str =
@@ -13,71 +26,707 @@ pub const DEFAULT_USER_AGENT_AUTONOMOUS: &str =
 pub const DEFAULT_USER_AGENT_MANUAL: &str =
     "ModelContextProtocol/1.0 (User-Specified; +https://github.com/modelcontextprotocol/servers)";
 
+/// Redirects followed per request
+const MAX_REDIRECTS: usize = 10;
+
+/// Default maximum response body size (after decompression)
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Default time to establish a connection, in seconds
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Default time for a whole request, in seconds
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// Settings shared by the HTTP clients
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub user_agent: String,
+
+    /// HTTP proxy URL (e.g., http://proxy:8080)
+    pub proxy_url: Option<String>,
+
+    pub connect_timeout: Duration,
+
+    pub timeout: Duration,
+}
+
+impl ClientOptions {
+    pub fn new(user_agent: &str) -> Self {
+        Self {
+            user_agent: user_agent.to_string(),
+            proxy_url: None,
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+            timeout: Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
+        }
+    }
+}
+
 /// Create HTTP client with common settings
-pub fn create_client(proxy_url: Option<&str>, user_agent: &str) -> Result<Client> {
+///
+/// Every connection is checked against the destination policy after DNS
+/// resolution, and every redirect hop is re-checked before it is followed.
+pub fn create_client(options: &ClientOptions, policy: Arc<DestinationPolicy>) -> Result<Client> {
+    build_client(options, policy, false)
+}
+
+/// Create HTTP client for requests carrying credentials
+///
+/// Same as [`create_client`], but redirects are only followed within the
+/// origin of the original request.
+pub fn create_credentialed_client(options: &ClientOptions, policy: Arc<DestinationPolicy>) -> Result<Client> {
+    build_client(options, policy, true)
+}
+
+fn build_client(options: &ClientOptions, policy: Arc<DestinationPolicy>, same_origin_redirects: bool) -> Result<Client> {
     let mut builder = Client::builder()
-        .user_agent(user_agent)
-        .timeout(Duration::from_secs(30))
+        .user_agent(&options.user_agent)
+        .connect_timeout(options.connect_timeout)
+        .timeout(options.timeout)
         .gzip(true)
         .brotli(true)
         .cookie_store(true)
-        .redirect(reqwest::redirect::Policy::limited(10));
+        .dns_resolver(Arc::new(ssrf::GuardedResolver::new(policy.clone())))
+        .redirect(ssrf::redirect_policy(policy.clone(), MAX_REDIRECTS, same_origin_redirects));
 
-    if let Some(proxy) = proxy_url {
-        builder = builder.proxy(reqwest::Proxy::all(proxy).context("Invalid proxy URL")?);
-    }
+    builder = match &options.proxy_url {
+        // The proxy resolves targets itself, bypassing the guarded resolver, so
+        // the fetcher checks each target before sending (see `Fetcher::execute`).
+        // A target that was not checked is sent direct, through the resolver.
+        Some(proxy) => {
+            if policy.proxy.is_none() {
+                anyhow::bail!("A proxy needs a destination policy built with with_proxy()");
+            }
+            reqwest::Proxy::all(proxy).context("Invalid proxy URL")?;
+            let proxy = Url::parse(proxy).context("Invalid proxy URL")?;
+            builder.proxy(reqwest::Proxy::custom(move |url| {
+                if policy.is_proxy_approved(url) {
+                    Some(proxy.clone())
+                } else {
+                    tracing::warn!("Not proxying unchecked target {}", url);
+                    None
+                }
+            }))
+        }
+        // Environment proxies (HTTP_PROXY, HTTPS_PROXY) would bypass the guard the same way
+        None => builder.no_proxy(),
+    };
 
     builder.build().context("Failed to create HTTP client")
 }
 
+/// Shared HTTP fetch pipeline used by all tools
+#[derive(Clone)]
+pub struct Fetcher {
+    /// Underlying HTTP client
+    pub client: Client,
+
+    /// Persistent HTTP cache (disabled when `None`)
+    pub cache: Option<Arc<HttpCache>>,
+
+    /// Credential profiles (no authentication when `None`)
+    pub credentials: Option<Arc<CredentialStore>>,
+
+    /// Server-wide body size limit; per-call limits may only lower it
+    pub max_body_bytes: u64,
+
+    /// robots.txt policy for autonomous requests (not checked when `None`)
+    pub robots: Option<Arc<RobotsCache>>,
+
+    /// Per-host politeness scheduler every request goes through
+    pub scheduler: Arc<Scheduler>,
+
+    /// Retry policy for calls that don't set their own
+    pub retry: RetryPolicy,
+
+    /// `[[domains]]` blocks whose headers are added to matching requests
+    pub domains: Arc<Vec<DomainRule>>,
+
+    /// Destination policy of the clients, checked again before each request
+    pub policy: Arc<DestinationPolicy>,
+}
+
+impl Fetcher {
+    pub fn new(client: Client, cache: Option<Arc<HttpCache>>) -> Self {
+        Self {
+            client,
+            cache,
+            credentials: None,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            robots: None,
+            scheduler: Arc::new(Scheduler::default()),
+            retry: RetryPolicy::default(),
+            domains: Arc::default(),
+            policy: Arc::default(),
+        }
+    }
+
+    /// Use the destination policy the clients were built with
+    pub fn with_policy(mut self, policy: Arc<DestinationPolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Effective body size limit for a request
+    pub fn body_limit(&self, options: &FetchOptions) -> u64 {
+        options
+            .max_bytes
+            .map_or(self.max_body_bytes, |max| max.min(self.max_body_bytes))
+    }
+
+    /// Attach credential profiles
+    pub fn with_credentials(mut self, credentials: Arc<CredentialStore>) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Check robots.txt before autonomous requests
+    pub fn with_robots(mut self, robots: Arc<RobotsCache>) -> Self {
+        self.robots = Some(robots);
+        self
+    }
+
+    /// Fail with [`crate::robots::RobotsDenied`] if robots.txt forbids an autonomous request
+    ///
+    /// Returns the origin's `Crawl-delay` to pass to [`Fetcher::send`].
+    pub async fn check_robots(&self, url: &str, options: &FetchOptions) -> Result<Option<Duration>> {
+        match &self.robots {
+            Some(robots) if !options.user_initiated => robots.check(self, url).await,
+            _ => Ok(None),
+        }
+    }
+
+    /// Send a request to `url` through the per-host scheduler
+    ///
+    /// Waits for the host's turn, with at least `crawl_delay` between requests,
+    /// and records 429/503 responses so later requests back off. The host's
+    /// concurrency permit is held until the response is dropped. The status is
+    /// not checked.
+    pub async fn send(&self, request: RequestBuilder, url: &str, crawl_delay: Option<Duration>) -> Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request.context(format!("Invalid request for {}", url))?;
+        self.execute(&client, request, url, crawl_delay).await
+    }
+
+    /// [`Fetcher::send`], retrying transient failures of idempotent requests
+    ///
+    /// The returned response carries the [`Attempts`] made in its extensions.
+    /// When every attempt fails, the last error is returned with a
+    /// [`RetriesExhausted`] context; a final transient status (e.g. 503) is
+    /// returned as a response for the caller to check.
+    pub async fn send_with_retries(
+        &self,
+        request: RequestBuilder,
+        url: &str,
+        crawl_delay: Option<Duration>,
+        policy: &RetryPolicy,
+    ) -> Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request.context(format!("Invalid request for {}", url))?;
+        let retries = if request.method().is_idempotent() { policy.max_retries } else { 0 };
+
+        let started = tokio::time::Instant::now();
+        let mut pending = Some(request);
+        let mut attempts = 1;
+        let mut last_error = None;
+
+        loop {
+            // Keep the original for later attempts while its body can be replayed
+            let current = match pending.as_ref().filter(|_| attempts <= retries).and_then(Request::try_clone) {
+                Some(copy) => copy,
+                None => pending.take().context("Request was already sent")?,
+            };
+
+            let result = self.execute(&client, current, url, crawl_delay).await;
+            let (error, retry_after) = match &result {
+                Ok(response) if retry::is_transient_status(response.status()) => (
+                    format!("HTTP {} for {}", response.status(), url),
+                    retry_after(response.headers(), http_cache::unix_now()),
+                ),
+                Err(e) if retry::is_transient_error(e) => (format!("{:#}", e), None),
+                _ => break finish_attempts(result, attempts, last_error),
+            };
+
+            let delay = policy.backoff(attempts, retry_after);
+            if pending.is_none() || started.elapsed() + delay > policy.max_total() {
+                break finish_attempts(result, attempts, last_error);
+            }
+
+            tracing::debug!("Attempt {} for {} failed ({}), retrying in {:?}", attempts, url, error, delay);
+            drop(result);
+            last_error = Some(error);
+            tokio::time::sleep(delay).await;
+            attempts += 1;
+        }
+    }
+
+    /// Retry policy for a call
+    pub fn retry_policy(&self, options: &FetchOptions) -> RetryPolicy {
+        options.retry.unwrap_or(self.retry)
+    }
+
+    async fn execute(&self, client: &Client, request: Request, url: &str, crawl_delay: Option<Duration>) -> Result<Response> {
+        // Addresses are checked by the resolver, names only here
+        if let Some(host) = request.url().host_str() {
+            self.policy.access.check_host(host)?;
+        }
+        let permit = self.scheduler.acquire(url, crawl_delay).await;
+        let mut response = self.execute_checked(client, request).await.context(format!("Failed to fetch {}", url))?;
+
+        let retry_after = retry_after(response.headers(), http_cache::unix_now());
+        self.scheduler.observe(url, response.status(), retry_after);
+        response.extensions_mut().insert(permit);
+        Ok(response)
+    }
+
+    /// Send a request, checking targets reached through the proxy first
+    ///
+    /// A redirect to a target not checked yet fails inside the client with
+    /// [`ssrf::UncheckedRedirect`]; the target is checked and an idempotent
+    /// request is sent again, up to the redirect limit.
+    async fn execute_checked(&self, client: &Client, request: Request) -> Result<Response> {
+        if self.policy.proxy.is_none() {
+            return Ok(client.execute(request).await?);
+        }
+
+        self.policy.approve_proxied(request.url()).await?;
+        let mut pending = request;
+        for _ in 0..MAX_REDIRECTS {
+            let again = pending.method().is_idempotent().then(|| pending.try_clone()).flatten();
+            let err = match client.execute(pending).await {
+                Ok(response) => return Ok(response),
+                Err(err) => anyhow::Error::from(err),
+            };
+            let hop = err.chain().find_map(|cause| cause.downcast_ref::<ssrf::UncheckedRedirect>());
+            match (hop, again) {
+                (Some(hop), Some(again)) => {
+                    self.policy.approve_proxied(&hop.url).await?;
+                    pending = again;
+                }
+                _ => return Err(err),
+            }
+        }
+        anyhow::bail!("Too many redirects (max {})", MAX_REDIRECTS)
+    }
+}
+
+/// Record the attempts behind a result: in the response, or as context of the error
+fn finish_attempts(result: Result<Response>, count: u32, last_error: Option<String>) -> Result<Response> {
+    match result {
+        Ok(mut response) => {
+            response.extensions_mut().insert(Attempts { count, last_error });
+            Ok(response)
+        }
+        Err(e) if count > 1 => Err(e.context(RetriesExhausted { attempts: count, last_error })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Response headers reported back to callers
+const REPORTED_RESPONSE_HEADERS: &[&str] = &[
+    "cache-control",
+    "content-language",
+    "content-length",
+    "content-type",
+    "date",
+    "etag",
+    "expires",
+    "last-modified",
+    "link",
+    "location",
+    "retry-after",
+];
+
+/// Request body for non-GET requests
+#[derive(Debug, Clone)]
+pub enum RequestBody {
+    /// Raw text body
+    Text(String),
+    /// JSON body (sets `Content-Type: application/json`)
+    Json(serde_json::Value),
+}
+
+/// Per-call fetch options
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// Cache behavior for this request
+    pub cache: CacheMode,
+
+    /// HTTP method (GET when `None`)
+    pub method: Option<Method>,
+
+    /// Extra request headers (already validated)
+    pub headers: HeaderMap,
+
+    /// Request body
+    pub body: Option<RequestBody>,
+
+    /// Query parameters appended to the URL
+    pub query: Vec<(String, String)>,
+
+    /// Credential profile to use (matched by host when `None`)
+    pub auth_profile: Option<String>,
+
+    /// Body size limit (server limit when `None`)
+    pub max_bytes: Option<u64>,
+
+    /// Requested by the user (prompts) rather than the model: robots.txt is not consulted
+    pub user_initiated: bool,
+
+    /// Retry policy (the fetcher's default when `None`)
+    pub retry: Option<RetryPolicy>,
+}
+
+impl FetchOptions {
+    /// HTTP method for this request
+    pub fn method(&self) -> Method {
+        self.method.clone().unwrap_or(Method::GET)
+    }
+
+    /// Only plain GET requests go through the HTTP cache
+    pub fn is_cacheable(&self) -> bool {
+        self.method() == Method::GET && self.headers.is_empty() && self.body.is_none()
+    }
+}
+
+/// Status, final URL and selected headers of a response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResponseInfo {
+    /// HTTP status code
+    pub status: u16,
+
+    /// Final URL after redirects
+    pub final_url: String,
+
+    /// Selected response headers
+    pub headers: BTreeMap<String, String>,
+
+    /// Detected body encoding (set once the body is decoded as text)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<DetectedEncoding>,
+
+    /// Body was cut off at the size limit
+    #[serde(default)]
+    pub body_truncated: bool,
+
+    /// Requests made, including retries (0 when served from the cache)
+    #[serde(default)]
+    pub attempts: u32,
+
+    /// Failure that caused the last retry, when the request was retried
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl ResponseInfo {
+    pub fn from_response(response: &Response) -> Self {
+        let headers = REPORTED_RESPONSE_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = response.headers().get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+
+        let attempts = response.extensions().get::<Attempts>();
+        Self {
+            status: response.status().as_u16(),
+            final_url: response.url().to_string(),
+            headers,
+            encoding: None,
+            body_truncated: false,
+            attempts: attempts.map_or(0, |attempts| attempts.count),
+            last_error: attempts.and_then(|attempts| attempts.last_error.clone()),
+        }
+    }
+}
+
+/// Append query parameters to a URL
+pub fn apply_query(url: &str, query: &[(String, String)]) -> Result<String> {
+    if query.is_empty() {
+        return Ok(url.to_string());
+    }
+    let mut parsed = url::Url::parse(url).context("Invalid URL")?;
+    parsed.query_pairs_mut().extend_pairs(query);
+    Ok(parsed.to_string())
+}
+
+/// Credential headers from the auth profile that applies to the URL
+pub fn auth_headers(fetcher: &Fetcher, url: &str, options: &FetchOptions) -> Result<Option<HeaderMap>> {
+    match (&fetcher.credentials, &options.auth_profile) {
+        (Some(store), requested) => store.headers_for(url, requested.as_deref()),
+        (None, Some(name)) => anyhow::bail!("Unknown auth profile: {} (no profiles configured)", name),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Response is the same for every caller: a cacheable GET that carries no
+/// query parameters, no credentials and no `[[domains]]` headers, the rule
+/// the HTTP cache applies
+pub fn is_shared(fetcher: &Fetcher, url: &str, options: &FetchOptions) -> bool {
+    options.is_cacheable()
+        && options.query.is_empty()
+        && matches!(auth_headers(fetcher, url, options), Ok(None))
+        && rule_headers(fetcher, url).is_none()
+}
+
+/// Headers the `[[domains]]` block matching the URL's host adds, if any
+fn rule_headers(fetcher: &Fetcher, url: &str) -> Option<HeaderMap> {
+    let url = url::Url::parse(url).ok()?;
+    let rule = domains::rule_for(&fetcher.domains, url.host_str()?)?;
+    // Validated when the config is loaded
+    rule.header_map().ok().filter(|headers| !headers.is_empty())
+}
+
+/// Build a request with the method, headers and body from the options
+///
+/// Credential headers from [`auth_headers`] are added last and sent through
+/// the credentialed client. Query parameters are not applied here; pass the
+/// URL through [`apply_query`] first.
+pub fn build_request(
+    fetcher: &Fetcher,
+    url: &str,
+    options: &FetchOptions,
+    auth: Option<HeaderMap>,
+) -> RequestBuilder {
+    let client = match (&auth, &fetcher.credentials) {
+        (Some(_), Some(store)) => store.client(),
+        _ => &fetcher.client,
+    };
+    let mut request = client.request(options.method(), url);
+
+    if let Some(headers) = rule_headers(fetcher, url) {
+        request = request.headers(headers);
+    }
+    if !options.headers.is_empty() {
+        request = request.headers(options.headers.clone());
+    }
+    if let Some(auth) = auth {
+        request = request.headers(auth);
+    }
+
+    match &options.body {
+        Some(RequestBody::Text(text)) => request.body(text.clone()),
+        Some(RequestBody::Json(json)) => request.json(json),
+        None => request,
+    }
+}
+
 /// Fetch URL and return raw response
-pub async fn fetch_url_raw(client: &Client, url: &str) -> Result<Response> {
-    tracing::debug!("Fetching URL: {}", url);
+///
+/// Goes through the HTTP cache when one is configured: fresh entries are served
+/// from disk, stale entries are revalidated with `If-None-Match`/`If-Modified-Since`.
+/// Authenticated requests and requests carrying `[[domains]]` headers are never
+/// cached. robots.txt is checked before any
+/// request leaves the server, but not for fresh cache hits.
+pub async fn fetch_url_raw(fetcher: &Fetcher, url: &str, options: &FetchOptions) -> Result<Response> {
+    let url = &apply_query(url, &options.query)?;
+    tracing::debug!("Fetching URL: {} {}", options.method(), url);
+
+    let auth = auth_headers(fetcher, url, options)?;
+    let cache = match &fetcher.cache {
+        Some(cache) if options.cache != CacheMode::Bypass && is_shared(fetcher, url, options) => cache,
+        _ => {
+            let crawl_delay = fetcher.check_robots(url, options).await?;
+            let request = build_request(fetcher, url, options, auth);
+            return send_checked(fetcher, request, url, crawl_delay, &fetcher.retry_policy(options)).await;
+        }
+    };
 
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .context(format!("Failed to fetch {}", url))?;
+    let stored = match options.cache {
+        CacheMode::Use => cache.get(url).await,
+        _ => None,
+    };
+
+    if let Some((entry, body)) = &stored {
+        if entry.is_fresh(http_cache::unix_now()) {
+            tracing::debug!("Cache hit for {}", url);
+            return entry.to_response(body.clone());
+        }
+    }
+
+    let crawl_delay = fetcher.check_robots(url, options).await?;
+    let mut request = build_request(fetcher, url, options, None);
+    if let Some((entry, _)) = &stored {
+        if let Some(etag) = entry.header("etag") {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = entry.header("last-modified") {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = fetcher
+        .send_with_retries(request, url, crawl_delay, &fetcher.retry_policy(options))
+        .await?;
+    let attempts = response.extensions().get::<Attempts>().cloned();
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some((entry, body)) = stored {
+            tracing::debug!("Revalidated cached {}", url);
+            let entry = entry.revalidated(response.headers());
+            if let Err(e) = cache.update(&entry).await {
+                tracing::warn!("Failed to update cache entry for {}: {}", url, e);
+            }
+            return replay(&entry, body, attempts);
+        }
+    }
 
     if !response.status().is_success() {
-        anyhow::bail!("HTTP {} for {}", response.status(), url);
+        return Err(status_error(&response, url));
+    }
+
+    let freshness = http_cache::response_freshness(response.headers(), http_cache::unix_now());
+    if response.status() != StatusCode::OK || !freshness.storable {
+        return Ok(response);
+    }
+
+    // Buffer cacheable responses so they can be stored and replayed
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let final_url = response.url().to_string();
+    let (body, truncated) = read_body(response, fetcher.body_limit(options)).await?;
+
+    let entry = CacheEntry::new(url, &final_url, status, &headers, body.len() as u64);
+    if truncated {
+        // Partial bodies are never stored; mark the replay so readers report truncation
+        let mut response = replay(&entry, body, attempts)?;
+        response.extensions_mut().insert(BodyTruncated);
+        return Ok(response);
+    }
+    if let Err(e) = cache.put(&entry, &body).await {
+        tracing::warn!("Failed to store cache entry for {}: {}", url, e);
+    }
+
+    replay(&entry, body, attempts)
+}
+
+/// Replay a cache entry, keeping the attempts of the request that produced it
+fn replay(entry: &CacheEntry, body: Bytes, attempts: Option<Attempts>) -> Result<Response> {
+    let mut response = entry.to_response(body)?;
+    if let Some(attempts) = attempts {
+        response.extensions_mut().insert(attempts);
+    }
+    Ok(response)
+}
+
+/// Marker on a replayed response whose body was already cut off
+#[derive(Debug, Clone, Copy)]
+struct BodyTruncated;
+
+/// Read a response body, stopping at `max_bytes`
+///
+/// Chunks arrive already decompressed, so the limit also caps the decoded size
+/// of gzip/brotli bodies. Returns the bytes read and whether the body was cut off.
+pub async fn read_body(mut response: Response, max_bytes: u64) -> Result<(Bytes, bool)> {
+    let already_truncated = response.extensions().get::<BodyTruncated>().is_some();
+    let max_bytes = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+    let mut body = BytesMut::new();
+
+    while let Some(chunk) = response.chunk().await.context("Failed to read response body")? {
+        let remaining = max_bytes - body.len();
+        if chunk.len() > remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            tracing::warn!("Response body from {} truncated at {} bytes", response.url(), max_bytes);
+            return Ok((body.freeze(), true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok((body.freeze(), already_truncated))
+}
+
+/// Send request with retries and fail on non-success status
+async fn send_checked(
+    fetcher: &Fetcher,
+    request: RequestBuilder,
+    url: &str,
+    crawl_delay: Option<Duration>,
+    policy: &RetryPolicy,
+) -> Result<Response> {
+    let response = fetcher.send_with_retries(request, url, crawl_delay, policy).await?;
+
+    if !response.status().is_success() {
+        return Err(status_error(&response, url));
     }
 
     Ok(response)
 }
 
-/// Fetch URL and return text content
-pub async fn fetch_url_text(client: &Client, url: &str) -> Result<String> {
-    let response = fetch_url_raw(client, url).await?;
-    let text = response.text().await.context("Failed to read response text")?;
-    Ok(text)
+/// [`HttpStatusError`] for a response, noting the attempts when it was retried
+pub fn status_error(response: &Response, url: &str) -> anyhow::Error {
+    let err = anyhow::Error::from(HttpStatusError::new(response, url));
+    match response.extensions().get::<Attempts>() {
+        Some(attempts) if attempts.count > 1 => err.context(RetriesExhausted {
+            attempts: attempts.count,
+            last_error: attempts.last_error.clone(),
+        }),
+        _ => err,
+    }
 }
 
-/// Fetch URL and return bytes
-pub async fn fetch_url_bytes(client: &Client, url: &str) -> Result<Bytes> {
-    let response = fetch_url_raw(client, url).await?;
-    let bytes = response.bytes().await.context("Failed to read response bytes")?;
-    Ok(bytes)
+/// Non-success HTTP status, kept typed so callers can report the status and retry hint
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("HTTP {status} for {url}")]
+pub struct HttpStatusError {
+    pub status: StatusCode,
+    pub url: String,
+    /// Delay requested by the server's `Retry-After` header
+    pub retry_after: Option<Duration>,
 }
 
-/// Cached fetch with 5-minute TTL (300 seconds)
-/// Cache key = URL, cache stores text content
-#[cached(
-    time = 300,
-    size = 100,
-    key = "String",
-    convert = r#"{ url.to_string() }"#,
-    result = true
-)]
-pub async fn fetch_url_cached(client: &Client, url: &str) -> Result<String> {
-    tracing::debug!("Cache miss for {}, fetching...", url);
-    fetch_url_text(client, url).await
+impl HttpStatusError {
+    pub fn new(response: &Response, url: &str) -> Self {
+        Self {
+            status: response.status(),
+            url: url.to_string(),
+            retry_after: retry_after(response.headers(), http_cache::unix_now()),
+        }
+    }
+}
+
+/// Parse `Retry-After`: a delay in seconds or an HTTP date
+pub fn retry_after(headers: &HeaderMap, now: u64) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    let date = date.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(date.saturating_sub(now)))
+}
+
+/// Fetch URL and return text content with response status, final URL, headers and encoding
+pub async fn fetch_url_text(fetcher: &Fetcher, url: &str, options: &FetchOptions) -> Result<(String, ResponseInfo)> {
+    let response = fetch_url_raw(fetcher, url, options).await?;
+    decode_text(response, fetcher.body_limit(options)).await
+}
+
+/// Read response body (up to `max_bytes`) and decode it using the sniffed charset
+///
+/// Unlike `Response::text`, this honors `<meta>` and XML encoding declarations,
+/// not just the Content-Type header.
+pub async fn decode_text(response: Response, max_bytes: u64) -> Result<(String, ResponseInfo)> {
+    let mut info = ResponseInfo::from_response(&response);
+    let (bytes, truncated) = read_body(response, max_bytes).await?;
+
+    let (text, encoding) = charset::decode(&bytes, info.headers.get("content-type").map(String::as_str));
+    info.encoding = Some(encoding);
+    info.body_truncated = truncated;
+    Ok((text, info))
+}
+
+/// Fetch URL and return bytes (up to the body size limit) with response info
+pub async fn fetch_url_bytes(fetcher: &Fetcher, url: &str, options: &FetchOptions) -> Result<(Bytes, ResponseInfo)> {
+    let response = fetch_url_raw(fetcher, url, options).await?;
+    let mut info = ResponseInfo::from_response(&response);
+    let (bytes, truncated) = read_body(response, fetcher.body_limit(options)).await?;
+    info.body_truncated = truncated;
+    Ok((bytes, info))
 }
 
 /// Content type detection result
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContentType {
     Html,
     Json,
@@ -88,68 +737,578 @@ pub enum ContentType {
     Text,
 }
 
-/// Detect content type from response headers and body
-#[allow(dead_code)]
-pub fn detect_content_type(response: &reqwest::Response, body: &str) -> ContentType {
-    let content_type_header = response
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
+/// Bytes of the body inspected when sniffing
+const SNIFF_BYTES: usize = 1024;
 
-    // Check HTML
-    if content_type_header.contains("text/html") || body.trim_start().starts_with("<!DOCTYPE") || body.trim_start().starts_with("<html") {
-        return ContentType::Html;
+/// Detect content type from the Content-Type header and body
+///
+/// Specific header types win; generic ones (`text/plain`, `application/octet-stream`
+/// or a missing header) fall back to sniffing the body.
+pub fn detect_content_type(content_type_header: &str, body: &[u8]) -> ContentType {
+    let mime = content_type_header
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    let head = sniff_head(body);
+
+    match mime.as_str() {
+        "application/pdf" => ContentType::Pdf,
+        "application/rss+xml" | "application/atom+xml" | "application/feed+json" => ContentType::Feed,
+        "text/html" | "application/xhtml+xml" => ContentType::Html,
+        "image/svg+xml" => ContentType::Xml,
+        m if m.starts_with("image/") => ContentType::Image,
+        m if m == "application/json" || m.ends_with("+json") => {
+            if is_json_feed(&head) { ContentType::Feed } else { ContentType::Json }
+        }
+        m if m == "application/xml" || m == "text/xml" || m.ends_with("+xml") => {
+            if is_xml_feed(&head) { ContentType::Feed } else { ContentType::Xml }
+        }
+        _ => sniff_content_type(body, &head),
     }
+}
+
+/// Lowercased start of the body with leading whitespace and BOM removed
+fn sniff_head(body: &[u8]) -> String {
+    String::from_utf8_lossy(&body[..body.len().min(SNIFF_BYTES)])
+        .trim_start_matches(|c: char| c.is_whitespace() || c == '\u{feff}')
+        .to_lowercase()
+}
 
-    // Check JSON
-    if content_type_header.contains("application/json") || body.trim_start().starts_with('{') || body.trim_start().starts_with('[') {
-        return ContentType::Json;
+fn sniff_content_type(body: &[u8], head: &str) -> ContentType {
+    if body.starts_with(b"%PDF-") {
+        return ContentType::Pdf;
     }
 
-    // Check XML
-    if content_type_header.contains("application/xml")
-        || content_type_header.contains("text/xml")
-        || body.trim_start().starts_with("<?xml")
-    {
-        return ContentType::Xml;
+    let is_image = body.starts_with(b"\x89PNG\r\n\x1a\n")
+        || body.starts_with(b"\xff\xd8\xff")
+        || body.starts_with(b"GIF87a")
+        || body.starts_with(b"GIF89a")
+        || (body.starts_with(b"RIFF") && body.get(8..12) == Some(b"WEBP".as_slice()));
+    if is_image {
+        return ContentType::Image;
     }
 
-    // Check RSS/Atom
-    if body.contains("<rss") || body.contains("<feed") {
-        return ContentType::Feed;
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        return ContentType::Html;
     }
 
-    // Check PDF
-    if content_type_header.contains("application/pdf") || body.starts_with("%PDF") {
-        return ContentType::Pdf;
+    if head.starts_with("<?xml") || head.starts_with("<rss") || head.starts_with("<feed") {
+        return if is_xml_feed(head) { ContentType::Feed } else { ContentType::Xml };
     }
 
-    // Check images
-    if content_type_header.starts_with("image/") {
-        return ContentType::Image;
+    if (head.starts_with('{') || head.starts_with('[')) && serde_json::from_slice::<serde_json::Value>(body).is_ok() {
+        return if is_json_feed(head) { ContentType::Feed } else { ContentType::Json };
     }
 
     ContentType::Text
 }
 
+fn is_xml_feed(head: &str) -> bool {
+    head.contains("<rss") || head.contains("<feed") || head.contains("<rdf:rdf")
+}
+
+fn is_json_feed(head: &str) -> bool {
+    head.contains("jsonfeed.org/version")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[tokio::test]
     async fn test_fetch_url() {
-        let client = create_client(None, DEFAULT_USER_AGENT_AUTONOMOUS).unwrap();
-        let result = fetch_url_text(&client, "https://httpbin.org/html").await;
+        let client = create_client(&ClientOptions::new(DEFAULT_USER_AGENT_AUTONOMOUS), Arc::default()).unwrap();
+        let fetcher = Fetcher::new(client, None);
+        let result = fetch_url_text(&fetcher, "https://httpbin.org/html", &FetchOptions::default()).await;
         assert!(result.is_ok());
     }
 
     #[tokio::test]
-    async fn test_content_type_detection() {
-        let html = "<!DOCTYPE html><html><body>Test</body></html>";
-        let client = create_client(None, DEFAULT_USER_AGENT_AUTONOMOUS).unwrap();
-        let response = client.get("https://httpbin.org/html").send().await.unwrap();
-        let ct = detect_content_type(&response, html);
-        assert_eq!(ct, ContentType::Html);
+    async fn test_proxy_does_not_bypass_ssrf_guard() {
+        use crate::test_util;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let proxy = test_util::serve(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            // Requests to a proxy carry the absolute target URL
+            let target = request.split_whitespace().nth(1).unwrap_or("");
+            match target.rsplit('/').next() {
+                Some("start") => test_util::response("302 Found", &[("Location", "http://127.0.0.1:9/end")], b""),
+                Some("escape") => test_util::response("302 Found", &[("Location", "http://127.0.0.2:9/")], b""),
+                _ => test_util::response("200 OK", &[], b"proxied"),
+            }
+        })
+        .await;
+        // Environment proxies are ignored; checked in a child process so this one's env stays untouched
+        let status = tokio::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "fetch::tests::env_proxy_is_ignored", "--include-ignored", "--quiet"])
+            .env("HTTP_PROXY", &proxy)
+            .env("http_proxy", &proxy)
+            .status()
+            .await
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        // A configured proxy never carries private destinations
+        let options = ClientOptions {
+            proxy_url: Some(proxy.clone()),
+            ..ClientOptions::new("test")
+        };
+        let proxied = |policy: DestinationPolicy| {
+            let policy = Arc::new(policy.with_proxy());
+            Fetcher::new(create_client(&options, policy.clone()).unwrap(), None).with_policy(policy)
+        };
+        let fetcher = proxied(DestinationPolicy::default());
+        let err = fetch_url_text(&fetcher, "http://localhost/", &FetchOptions::default()).await.unwrap_err();
+        assert!(err.chain().any(|cause| cause.is::<ssrf::BlockedDestination>()), "{:#}", err);
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+        assert!(create_client(&options, Arc::default()).is_err());
+
+        // Allowed targets go through the proxy; redirect hops are checked before they are followed
+        let fetcher = proxied(DestinationPolicy::new(&["127.0.0.1/32".to_string()], &["localhost".to_string()]).unwrap());
+        let (text, info) = fetch_url_text(&fetcher, "http://localhost:9/start", &FetchOptions::default()).await.unwrap();
+        assert_eq!(text, "proxied");
+        assert_eq!(info.final_url, "http://127.0.0.1:9/end");
+        // The first attempt stopped at the unchecked hop, the second followed it
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        let err = fetch_url_text(&fetcher, "http://localhost:9/escape", &FetchOptions::default()).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("blocked by the SSRF policy"), "{:#}", err);
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_denied_domains_checked_on_send() {
+        use crate::domains::{AccessPolicy, DomainPattern, PolicyDenied};
+        use crate::test_util;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let base = test_util::serve(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            test_util::response("200 OK", &[], b"ok")
+        })
+        .await;
+        let access = AccessPolicy::new(vec![], vec![DomainPattern::new("localhost").unwrap()]);
+        let policy = Arc::new(
+            DestinationPolicy::new(&["127.0.0.0/8".to_string()], &[])
+                .unwrap()
+                .with_access(access),
+        );
+        let client = create_client(&ClientOptions::new("test"), policy.clone()).unwrap();
+        let fetcher = Fetcher::new(client, None).with_policy(policy);
+
+        // Modules that build their own requests (reddit, wiki) go through `send`
+        let url = base.replace("127.0.0.1", "localhost");
+        let err = fetcher.send(fetcher.client.get(&url), &url, None).await.unwrap_err();
+        assert!(err.chain().any(|cause| cause.is::<PolicyDenied>()), "{:#}", err);
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        let response = fetcher.send(fetcher.client.get(&base), &base, None).await.unwrap();
+        assert!(response.status().is_success());
+    }
+
+    /// Run by `test_proxy_does_not_bypass_ssrf_guard` with `HTTP_PROXY` set
+    #[tokio::test]
+    #[ignore]
+    async fn env_proxy_is_ignored() {
+        let client = create_client(&ClientOptions::new("test"), Arc::default()).unwrap();
+        let err = client.get("http://localhost/").send().await.unwrap_err();
+        // The resolver still sees the private name instead of handing it to the proxy
+        let mut source = std::error::Error::source(&err);
+        while let Some(cause) = source {
+            if cause.is::<ssrf::BlockedDestination>() {
+                return;
+            }
+            source = cause.source();
+        }
+        panic!("{:?}", err);
+    }
+
+    #[test]
+    fn test_content_type_detection() {
+        let html = b"<!DOCTYPE html><html><body>Test</body></html>";
+        assert_eq!(detect_content_type("text/html; charset=utf-8", html), ContentType::Html);
+        assert_eq!(detect_content_type("", html), ContentType::Html);
+
+        assert_eq!(detect_content_type("application/pdf", b"%PDF-1.7"), ContentType::Pdf);
+        assert_eq!(detect_content_type("application/octet-stream", b"%PDF-1.4\n"), ContentType::Pdf);
+        assert_eq!(detect_content_type("image/png", b""), ContentType::Image);
+        assert_eq!(detect_content_type("", b"\x89PNG\r\n\x1a\n...."), ContentType::Image);
+
+        let rss = b"<?xml version=\"1.0\"?><rss version=\"2.0\"><channel></channel></rss>";
+        assert_eq!(detect_content_type("text/xml", rss), ContentType::Feed);
+        assert_eq!(detect_content_type("text/plain", rss), ContentType::Feed);
+        assert_eq!(detect_content_type("application/xml", b"<?xml version=\"1.0\"?><urlset/>"), ContentType::Xml);
+
+        assert_eq!(detect_content_type("application/json", b"{\"a\": 1}"), ContentType::Json);
+        assert_eq!(detect_content_type("text/plain", b"[1, 2, 3]"), ContentType::Json);
+        assert_eq!(
+            detect_content_type("application/json", b"{\"version\": \"https://jsonfeed.org/version/1.1\"}"),
+            ContentType::Feed
+        );
+
+        assert_eq!(detect_content_type("text/plain", b"{ not json"), ContentType::Text);
+        assert_eq!(detect_content_type("text/markdown", b"# Title"), ContentType::Text);
+    }
+
+    #[tokio::test]
+    async fn test_cache_revalidation() {
+        use crate::test_util;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let not_modified = Arc::new(AtomicUsize::new(0));
+        let (h, nm) = (hits.clone(), not_modified.clone());
+        let base = test_util::serve(move |request| {
+            h.fetch_add(1, Ordering::SeqCst);
+            if request.to_lowercase().contains("if-none-match: \"v1\"") {
+                nm.fetch_add(1, Ordering::SeqCst);
+                test_util::response("304 Not Modified", &[("ETag", "\"v1\"")], b"")
+            } else {
+                test_util::response("200 OK", &[("ETag", "\"v1\""), ("Cache-Control", "no-cache")], b"hello")
+            }
+        })
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(HttpCache::open(dir.path(), 1024).unwrap());
+        let fetcher = test_util::loopback_fetcher(Some(cache));
+        let url = format!("{}/page", base);
+
+        let first = fetch_url_text(&fetcher, &url, &FetchOptions::default()).await.unwrap().0;
+        let second = fetch_url_text(&fetcher, &url, &FetchOptions::default()).await.unwrap().0;
+        assert_eq!(first, "hello");
+        assert_eq!(second, "hello");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert_eq!(not_modified.load(Ordering::SeqCst), 1);
+
+        // Bypass sends no validators
+        let third = fetch_url_text(&fetcher, &url, &FetchOptions { cache: CacheMode::Bypass, ..Default::default() }).await.unwrap().0;
+        assert_eq!(third, "hello");
+        assert_eq!(not_modified.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, 0), None);
+
+        headers.insert(header::RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after(&headers, 0), Some(Duration::from_secs(120)));
+
+        // 1994-11-06 08:49:37 UTC is 784111777
+        headers.insert(header::RETRY_AFTER, "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap());
+        assert_eq!(retry_after(&headers, 784111777 - 30), Some(Duration::from_secs(30)));
+        assert_eq!(retry_after(&headers, 784111777 + 30), Some(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_cache_serves_fresh_entries() {
+        use crate::test_util;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let h = hits.clone();
+        let base = test_util::serve(move |_| {
+            h.fetch_add(1, Ordering::SeqCst);
+            test_util::response("200 OK", &[("Cache-Control", "max-age=300")], b"fresh")
+        })
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(HttpCache::open(dir.path(), 1024).unwrap());
+        let fetcher = test_util::loopback_fetcher(Some(cache));
+
+        for _ in 0..3 {
+            let text = fetch_url_text(&fetcher, &base, &FetchOptions::default()).await.unwrap().0;
+            assert_eq!(text, "fresh");
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        fetch_url_text(&fetcher, &base, &FetchOptions { cache: CacheMode::Refresh, ..Default::default() }).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_apply_query() {
+        let query = vec![("q".to_string(), "rust lang".to_string()), ("page".to_string(), "2".to_string())];
+        assert_eq!(
+            apply_query("https://example.com/search?x=1", &query).unwrap(),
+            "https://example.com/search?x=1&q=rust+lang&page=2"
+        );
+        assert_eq!(apply_query("https://example.com/", &[]).unwrap(), "https://example.com/");
+    }
+
+    #[tokio::test]
+    async fn test_custom_method_headers_and_body() {
+        use crate::test_util;
+
+        let base = test_util::serve(|request| {
+            // Echo the request head and body back to the caller
+            let headers = [("Content-Type", "text/plain"), ("X-Internal", "1"), ("Cache-Control", "max-age=300")];
+            test_util::response("200 OK", &headers, request.as_bytes())
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(HttpCache::open(dir.path(), 1024 * 1024).unwrap());
+        let mut fetcher = test_util::loopback_fetcher(Some(cache.clone()));
+        // Headers of a matching [[domains]] block are added; the call's own take precedence
+        let rules: BTreeMap<String, Vec<DomainRule>> = toml::from_str(
+            "[[domains]]\nmatch = \"127.0.0.1\"\nheaders = { \"X-Client\" = \"fetch-mcp\", \"X-Api-Key\" = \"default\" }\n",
+        )
+        .unwrap();
+        fetcher.domains = Arc::new(rules["domains"].clone());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let options = FetchOptions {
+            method: Some(Method::POST),
+            headers,
+            body: Some(RequestBody::Json(serde_json::json!({"query": "{ viewer { login } }"}))),
+            query: vec![("v".to_string(), "1".to_string())],
+            ..Default::default()
+        };
+
+        let (echo, info) = fetch_url_text(&fetcher, &format!("{}/graphql", base), &options)
+            .await
+            .unwrap();
+        assert!(echo.starts_with("POST /graphql?v=1 HTTP/1.1"));
+        assert!(echo.to_lowercase().contains("x-api-key: secret"));
+        assert!(!echo.to_lowercase().contains("x-api-key: default"));
+        assert!(echo.to_lowercase().contains("x-client: fetch-mcp"));
+        assert!(echo.to_lowercase().contains("content-type: application/json"));
+        assert_eq!(info.status, 200);
+        assert_eq!(info.final_url, format!("{}/graphql?v=1", base));
+        assert_eq!(info.headers.get("content-type").map(String::as_str), Some("text/plain"));
+        assert!(!info.headers.contains_key("x-internal"));
+
+        // Responses to requests carrying the block's headers are not shared or cached
+        assert!(!is_shared(&fetcher, &base, &FetchOptions::default()));
+        assert!(is_shared(&fetcher, "http://localhost/", &FetchOptions::default()));
+        let echo = fetch_url_text(&fetcher, &base, &FetchOptions::default()).await.unwrap().0;
+        assert!(echo.to_lowercase().contains("x-client: fetch-mcp"));
+        assert_eq!(cache.total_bytes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_auth_profile() {
+        use crate::auth::{AuthConfig, CredentialStore};
+        use crate::test_util;
+
+        let base = test_util::serve(|request| {
+            if request.starts_with("GET /moved") {
+                let port = request.split("127.0.0.1:").nth(1).unwrap_or("").split_whitespace().next().unwrap_or("");
+                let location = format!("http://localhost:{}/echo", port);
+                return test_util::response("302 Found", &[("Location", &location)], b"");
+            }
+            test_util::response("200 OK", &[("Cache-Control", "max-age=300")], request.as_bytes())
+        })
+        .await;
+
+        std::env::set_var("FETCH_MCP_TEST_FETCH_TOKEN", "tok-123");
+        let config: AuthConfig = toml::from_str(
+            r#"
+            [profiles.local]
+            hosts = ["127.0.0.1"]
+            type = "bearer"
+            token = { env = "FETCH_MCP_TEST_FETCH_TOKEN" }
+            "#,
+        )
+        .unwrap();
+        let options = ClientOptions::new(DEFAULT_USER_AGENT_AUTONOMOUS);
+        let client = create_credentialed_client(&options, test_util::loopback_policy()).unwrap();
+        let store = Arc::new(CredentialStore::new(config, client).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(HttpCache::open(dir.path(), 1024 * 1024).unwrap());
+        let fetcher = test_util::loopback_fetcher(Some(cache.clone())).with_credentials(store);
+
+        let echo = fetch_url_text(&fetcher, &format!("{}/echo", base), &FetchOptions::default()).await.unwrap().0;
+        assert!(echo.to_lowercase().contains("authorization: bearer tok-123"));
+        assert_eq!(cache.total_bytes(), 0);
+
+        // Credentialed responses are not shared between callers
+        assert!(!is_shared(&fetcher, &base, &FetchOptions::default()));
+        assert!(is_shared(&fetcher, "http://localhost/", &FetchOptions::default()));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", header::HeaderValue::from_static("secret"));
+        let options = FetchOptions { headers, ..Default::default() };
+        assert!(!is_shared(&fetcher, "http://localhost/", &options));
+
+        // Credentials are never forwarded to another origin
+        let err = fetch_url_text(&fetcher, &format!("{}/moved", base), &FetchOptions::default())
+            .await
+            .unwrap_err();
+        let err = format!("{:#}", err);
+        assert!(err.contains("leaves the origin"), "{}", err);
+        assert!(!err.contains("tok-123"));
+
+        let options = FetchOptions {
+            auth_profile: Some("other".to_string()),
+            ..Default::default()
+        };
+        assert!(fetch_url_text(&fetcher, &base, &options).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_decodes_declared_charset() {
+        use crate::charset::EncodingSource;
+        use crate::test_util;
+
+        let (body, _, _) = encoding_rs::WINDOWS_1251.encode("<html><head><meta charset=\"windows-1251\"></head><body>Привет</body></html>");
+        let body = body.into_owned();
+        let base = test_util::serve(move |_| test_util::response("200 OK", &[("Content-Type", "text/html")], &body)).await;
+        let fetcher = test_util::loopback_fetcher(None);
+
+        let (text, info) = fetch_url_text(&fetcher, &base, &FetchOptions::default()).await.unwrap();
+        assert!(text.contains("Привет"));
+        let encoding = info.encoding.unwrap();
+        assert_eq!(encoding.name, "windows-1251");
+        assert_eq!(encoding.source, EncodingSource::Meta);
+    }
+
+    #[tokio::test]
+    async fn test_body_size_limit() {
+        use crate::test_util;
+        use std::io::Write;
+
+        // 1 MiB of zeros compresses to about 1 KiB
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&vec![b'0'; 1024 * 1024]).unwrap();
+        let bomb = encoder.finish().unwrap();
+        let base = test_util::serve(move |request| {
+            if request.starts_with("GET /bomb") {
+                test_util::response("200 OK", &[("Content-Encoding", "gzip"), ("Cache-Control", "max-age=60")], &bomb)
+            } else {
+                test_util::response("200 OK", &[("Cache-Control", "max-age=60")], b"0123456789")
+            }
+        })
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(HttpCache::open(dir.path(), 4 * 1024 * 1024).unwrap());
+        let mut fetcher = test_util::loopback_fetcher(Some(cache.clone()));
+        fetcher.max_body_bytes = 64 * 1024;
+
+        // Decompressed size counts against the limit, and partial bodies are not cached
+        let (text, info) = fetch_url_text(&fetcher, &format!("{}/bomb", base), &FetchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(text.len(), 64 * 1024);
+        assert!(info.body_truncated);
+        assert_eq!(cache.total_bytes(), 0);
+
+        // Per-call limits may lower the server limit, but not raise it
+        let options = FetchOptions { max_bytes: Some(4), ..Default::default() };
+        let (bytes, info) = fetch_url_bytes(&fetcher, &base, &options).await.unwrap();
+        assert_eq!(&bytes[..], b"0123");
+        assert!(info.body_truncated);
+
+        let options = FetchOptions { max_bytes: Some(u64::MAX), ..Default::default() };
+        assert_eq!(fetcher.body_limit(&options), 64 * 1024);
+        let (bytes, info) = fetch_url_bytes(&fetcher, &base, &options).await.unwrap();
+        assert_eq!(&bytes[..], b"0123456789");
+        assert!(!info.body_truncated);
+    }
+
+    #[tokio::test]
+    async fn test_robots_checked_for_autonomous_requests() {
+        use crate::robots::{RobotsCache, RobotsDenied, DEFAULT_ROBOTS_TTL};
+        use crate::test_util;
+
+        let base = test_util::serve(|request| {
+            if request.starts_with("GET /robots.txt") {
+                test_util::response("200 OK", &[], b"User-agent: *\nDisallow: /private\nCrawl-delay: 0.2\n")
+            } else {
+                test_util::response("200 OK", &[], b"page")
+            }
+        })
+        .await;
+        let robots = Arc::new(RobotsCache::new("ModelContextProtocol", DEFAULT_ROBOTS_TTL));
+        let fetcher = test_util::loopback_fetcher(None).with_robots(robots);
+
+        let url = format!("{}/private/page", base);
+        let err = fetch_url_text(&fetcher, &url, &FetchOptions::default()).await.unwrap_err();
+        assert!(err.is::<RobotsDenied>());
+
+        // Fetches the user asked for are not subject to robots.txt
+        let options = FetchOptions { user_initiated: true, ..Default::default() };
+        assert_eq!(fetch_url_text(&fetcher, &url, &options).await.unwrap().0, "page");
+        assert!(fetch_url_text(&fetcher, &format!("{}/public", base), &FetchOptions::default()).await.is_ok());
+
+        // The crawl delay spaces out autonomous requests through the scheduler
+        let start = tokio::time::Instant::now();
+        for _ in 0..2 {
+            fetch_url_text(&fetcher, &format!("{}/public", base), &FetchOptions::default()).await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_failures() {
+        use crate::error::{ErrorCode, ToolError};
+        use crate::test_util;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let h = hits.clone();
+        let base = test_util::serve(move |request| {
+            let n = h.fetch_add(1, Ordering::SeqCst);
+            if request.starts_with("GET /flaky") && n % 3 == 2 {
+                test_util::response("200 OK", &[], b"finally")
+            } else if request.starts_with("GET /missing") {
+                test_util::response("404 Not Found", &[], b"")
+            } else {
+                test_util::response("503 Service Unavailable", &[("Retry-After", "0")], b"")
+            }
+        })
+        .await;
+        let mut fetcher = test_util::loopback_fetcher(None);
+        fetcher.retry = RetryPolicy { base_delay_ms: 10, ..Default::default() };
+
+        let (text, info) = fetch_url_text(&fetcher, &format!("{}/flaky", base), &FetchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(text, "finally");
+        assert_eq!(info.attempts, 3);
+        assert!(info.last_error.unwrap().contains("503"));
+
+        // Exhausted retries report the attempts and the last failure
+        hits.store(0, Ordering::SeqCst);
+        let options = FetchOptions {
+            retry: Some(RetryPolicy { max_retries: 1, base_delay_ms: 10, ..Default::default() }),
+            ..Default::default()
+        };
+        let err = fetch_url_text(&fetcher, &format!("{}/down", base), &options).await.unwrap_err();
+        let error = ToolError::classify(ErrorCode::Network, "Failed to fetch URL", &err);
+        assert_eq!((error.status, error.attempts), (Some(503), Some(2)));
+        assert!(error.message.contains("gave up after 2 attempts"));
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // Permanent failures and non-idempotent methods get a single attempt
+        hits.store(0, Ordering::SeqCst);
+        let err = fetch_url_text(&fetcher, &format!("{}/missing", base), &FetchOptions::default())
+            .await
+            .unwrap_err();
+        assert_eq!(ToolError::classify(ErrorCode::Network, "", &err).attempts, Some(1));
+        let options = FetchOptions { method: Some(Method::POST), ..Default::default() };
+        assert!(fetch_url_text(&fetcher, &format!("{}/down", base), &options).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // Destinations refused by the SSRF policy are not retried
+        let start = tokio::time::Instant::now();
+        let mut guarded = Fetcher::new(create_client(&ClientOptions::new("test"), Arc::new(DestinationPolicy::default())).unwrap(), None);
+        guarded.retry = RetryPolicy { base_delay_ms: 5_000, ..Default::default() };
+        let url = format!("{}/flaky", base.replace("127.0.0.1", "localhost"));
+        let err = fetch_url_text(&guarded, &url, &FetchOptions::default()).await.unwrap_err();
+        assert!(err.chain().any(|cause| cause.is::<ssrf::BlockedDestination>()));
+        assert!(start.elapsed() < Duration::from_secs(1));
     }
 }
@@ -1,39 +1,567 @@
 use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
 use readability::extractor::extract;
+use regex::{Captures, Regex};
+use schemars::JsonSchema;
+use scraper::{ElementRef, Html, Node};
+use serde::{Deserialize, Serialize};
 use std::io::Cursor;
+use url::Url;
 
-/// Convert HTML to simplified Markdown using Readability algorithm
-pub fn html_to_markdown(html: &str, url: &str) -> Result<String> {
-    // Use Readability to extract main content
-    let mut cursor = Cursor::new(html.as_bytes());
+/// Elements whose content never appears in the output
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "head", "title", "script", "style", "noscript", "template", "svg", "canvas", "iframe",
+    "object", "embed", "select", "option", "textarea", "button",
+];
 
-    match extract(&mut cursor, &url::Url::parse(url).context("Invalid URL")?) {
+/// Elements rendered as blocks separated by blank lines
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "center", "dd", "details", "dialog",
+    "div", "dl", "dt", "fieldset", "figcaption", "figure", "footer", "form", "h1", "h2", "h3",
+    "h4", "h5", "h6", "header", "hgroup", "hr", "html", "li", "main", "nav", "ol", "p", "pre",
+    "section", "summary", "table", "tbody", "td", "tfoot", "th", "thead", "tr", "ul",
+];
+
+/// `class="language-x"` / `class="lang-x"` on `<pre>` or `<code>`
+static CODE_CLASS_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)<(pre|code)\b([^>]*?)\bclass\s*=\s*("[^"]*"|'[^']*')"#).unwrap()
+});
+
+static LANGUAGE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(?:language|lang)-([\w+#.-]+)").unwrap());
+
+/// Link rendering style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStyle {
+    /// `[text](https://example.com/)`
+    #[default]
+    Inline,
+    /// `[text][1]` with `[1]: https://example.com/` at the end
+    Reference,
+}
+
+/// Markdown conversion options
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownOptions {
+    /// How links and images are written
+    pub link_style: LinkStyle,
+
+    /// Wrap paragraphs at this many characters (no wrapping when `None`)
+    pub wrap_width: Option<usize>,
+}
+
+/// Convert HTML to GFM Markdown using Readability algorithm
+pub fn html_to_markdown(html: &str, url: &str, options: MarkdownOptions) -> Result<String> {
+    let base = Url::parse(url).context("Invalid URL")?;
+
+    // Readability strips class attributes, so keep code languages in a data attribute
+    let prepared = preserve_code_languages(html);
+    let mut cursor = Cursor::new(prepared.as_bytes());
+
+    match extract(&mut cursor, &base) {
         Ok(product) => {
-            // Convert extracted HTML to markdown using html2text
-            let markdown = html2text::from_read(product.content.as_bytes(), 80)
-                .context("Failed to convert HTML to markdown")?;
-            Ok(markdown)
+            let body = convert_html(&product.content, Some(&base), options);
+            // Readability drops <h1>, so restore the article title as the top heading
+            let title = collapse_whitespace(&product.title);
+            let title = title.trim();
+            if title.is_empty() || body.starts_with("# ") {
+                Ok(body)
+            } else {
+                Ok(format!("# {}\n\n{}", escape_text(title), body))
+            }
         }
         Err(_) => {
             // Fallback: if readability fails, just convert raw HTML
             tracing::warn!("Readability extraction failed for {}, using raw HTML conversion", url);
-            let markdown = html2text::from_read(html.as_bytes(), 80)
-                .context("Failed to convert HTML to text")?;
-            Ok(markdown)
+            Ok(convert_html(html, Some(&base), options))
         }
     }
 }
 
+/// Convert an HTML document or fragment to GFM Markdown
+///
+/// Relative links and image sources are resolved against `base`.
+pub fn convert_html(html: &str, base: Option<&Url>, options: MarkdownOptions) -> String {
+    let document = Html::parse_document(html);
+    let mut converter = Converter {
+        base,
+        options,
+        references: Vec::new(),
+    };
+
+    let mut markdown = converter.render_blocks(document.root_element()).join("\n\n");
+
+    if !converter.references.is_empty() {
+        markdown.push_str("\n\n");
+        for (index, url) in converter.references.iter().enumerate() {
+            markdown.push_str(&format!("[{}]: {}\n", index + 1, url));
+        }
+    }
+
+    markdown.trim().to_string()
+}
+
 /// Convert HTML to plain text without markdown formatting
 pub fn html_to_text(html: &str) -> String {
     html2text::from_read(html.as_bytes(), 80)
         .unwrap_or_else(|_| String::from(html))
 }
 
+/// Copy `language-x` classes on `<pre>`/`<code>` into a `data-lang` attribute
+fn preserve_code_languages(html: &str) -> String {
+    CODE_CLASS_RE
+        .replace_all(html, |caps: &Captures| match LANGUAGE_RE.captures(&caps[3]) {
+            Some(lang) => format!("<{} data-lang=\"{}\"{}class={}", &caps[1], &lang[1], &caps[2], &caps[3]),
+            None => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+struct Converter<'a> {
+    base: Option<&'a Url>,
+    options: MarkdownOptions,
+    references: Vec<String>,
+}
+
+impl Converter<'_> {
+    /// Render children of an element as a sequence of blocks
+    fn render_blocks(&mut self, parent: ElementRef<'_>) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut inline = String::new();
+
+        for child in parent.children() {
+            match child.value() {
+                Node::Text(text) => inline.push_str(&escape_text(&collapse_whitespace(text))),
+                Node::Element(element) => {
+                    let name = element.name();
+                    let Some(element) = ElementRef::wrap(child) else { continue };
+                    if SKIPPED_ELEMENTS.contains(&name) {
+                        continue;
+                    }
+                    if BLOCK_ELEMENTS.contains(&name) {
+                        self.flush_paragraph(&mut inline, &mut blocks);
+                        let block = self.render_block(element);
+                        if !block.trim().is_empty() {
+                            blocks.push(block);
+                        }
+                    } else {
+                        inline.push_str(&self.render_inline_element(element));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.flush_paragraph(&mut inline, &mut blocks);
+        blocks
+    }
+
+    fn flush_paragraph(&self, inline: &mut String, blocks: &mut Vec<String>) {
+        let text = tidy_inline(inline);
+        if !text.is_empty() {
+            blocks.push(self.wrap(&escape_block_start(&text)));
+        }
+        inline.clear();
+    }
+
+    fn render_block(&mut self, element: ElementRef<'_>) -> String {
+        match element.value().name() {
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let text = tidy_inline(&self.render_inline(element)).replace("  \n", " ");
+                if text.is_empty() {
+                    String::new()
+                } else {
+                    format!("{} {}", "#".repeat(level), text)
+                }
+            }
+            "p" | "dt" | "dd" | "figcaption" | "summary" => {
+                let text = tidy_inline(&self.render_inline(element));
+                self.wrap(&escape_block_start(&text))
+            }
+            "pre" => code_block(element),
+            "ul" => self.render_list(element, false),
+            "ol" => self.render_list(element, true),
+            "blockquote" => {
+                let body = self.render_blocks(element).join("\n\n");
+                prefix_lines(&body, "> ", ">")
+            }
+            "table" => self.render_table(element),
+            "hr" => "---".to_string(),
+            _ => self.render_blocks(element).join("\n\n"),
+        }
+    }
+
+    fn render_list(&mut self, list: ElementRef<'_>, ordered: bool) -> String {
+        let start = list
+            .value()
+            .attr("start")
+            .and_then(|start| start.trim().parse::<usize>().ok())
+            .unwrap_or(1);
+
+        let items = list
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|child| child.value().name() == "li");
+
+        let mut rendered = Vec::new();
+        for (index, item) in items.enumerate() {
+            let marker = if ordered { format!("{}. ", start + index) } else { "- ".to_string() };
+            let blocks = self.render_blocks(item);
+            // Items with several paragraphs need blank lines; nested lists don't
+            let loose = item.children().filter_map(ElementRef::wrap).any(|c| c.value().name() == "p");
+            let body = blocks.join(if loose { "\n\n" } else { "\n" });
+
+            let indent = " ".repeat(marker.len());
+            let mut lines = body.lines();
+            let mut text = format!("{}{}", marker, lines.next().unwrap_or("").trim_end());
+            for line in lines {
+                text.push('\n');
+                if !line.is_empty() {
+                    text.push_str(&indent);
+                    text.push_str(line);
+                }
+            }
+            rendered.push(text);
+        }
+
+        rendered.join("\n")
+    }
+
+    fn render_table(&mut self, table: ElementRef<'_>) -> String {
+        let mut caption = None;
+        let mut rows: Vec<(Vec<String>, bool)> = Vec::new();
+
+        for child in table.children().filter_map(ElementRef::wrap) {
+            match child.value().name() {
+                "caption" => caption = Some(tidy_inline(&self.render_inline(child))),
+                "tr" => rows.push(self.render_row(child)),
+                "thead" | "tbody" | "tfoot" => {
+                    for row in child.children().filter_map(ElementRef::wrap) {
+                        if row.value().name() == "tr" {
+                            let (cells, header) = self.render_row(row);
+                            rows.push((cells, header || child.value().name() == "thead"));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let columns = rows.iter().map(|(cells, _)| cells.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return caption.unwrap_or_default();
+        }
+
+        // GFM tables always have a header row; use the first row when none is marked
+        let header_count = rows.iter().take_while(|(_, header)| *header).count().max(1);
+        let header_cells: Vec<String> = (0..columns)
+            .map(|column| {
+                rows[..header_count]
+                    .iter()
+                    .filter_map(|(cells, _)| cells.get(column).filter(|cell| !cell.is_empty()))
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+
+        let mut lines = vec![
+            table_row(&header_cells, columns),
+            table_row(&vec!["---".to_string(); columns], columns),
+        ];
+        for (cells, _) in &rows[header_count..] {
+            lines.push(table_row(cells, columns));
+        }
+
+        let table = lines.join("\n");
+        match caption.filter(|caption| !caption.is_empty()) {
+            Some(caption) => format!("{}\n\n{}", caption, table),
+            None => table,
+        }
+    }
+
+    fn render_row(&mut self, row: ElementRef<'_>) -> (Vec<String>, bool) {
+        let mut cells = Vec::new();
+        let mut all_headers = true;
+
+        for cell in row.children().filter_map(ElementRef::wrap) {
+            let name = cell.value().name();
+            if name != "td" && name != "th" {
+                continue;
+            }
+            all_headers &= name == "th";
+
+            let text = tidy_inline(&self.render_inline(cell))
+                .replace("  \n", " ")
+                .replace('|', "\\|");
+            cells.push(text);
+
+            let span = cell.value().attr("colspan").and_then(|span| span.parse::<usize>().ok()).unwrap_or(1);
+            cells.extend(std::iter::repeat_n(String::new(), span.clamp(1, 100) - 1));
+        }
+
+        let header = all_headers && !cells.is_empty();
+        (cells, header)
+    }
+
+    /// Render children of an element as inline Markdown
+    fn render_inline(&mut self, element: ElementRef<'_>) -> String {
+        let mut out = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => out.push_str(&escape_text(&collapse_whitespace(text))),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        out.push_str(&self.render_inline_element(child));
+                    }
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+
+    fn render_inline_element(&mut self, element: ElementRef<'_>) -> String {
+        let name = element.value().name();
+        match name {
+            _ if SKIPPED_ELEMENTS.contains(&name) => String::new(),
+            "br" => "\n".to_string(),
+            "a" => self.render_link(element),
+            "img" => self.render_image(element),
+            "strong" | "b" => emphasize(&self.render_inline(element), "**"),
+            "em" | "i" => emphasize(&self.render_inline(element), "*"),
+            "del" | "s" | "strike" => emphasize(&self.render_inline(element), "~~"),
+            "code" | "kbd" | "samp" | "tt" => code_span(&element.text().collect::<String>()),
+            "input" => match element.value().attr("type") {
+                Some(kind) if kind.eq_ignore_ascii_case("checkbox") => {
+                    let checked = element.value().attr("checked").is_some();
+                    if checked { "[x] " } else { "[ ] " }.to_string()
+                }
+                _ => String::new(),
+            },
+            // Block content nested in inline context is flattened
+            _ if BLOCK_ELEMENTS.contains(&name) => format!(" {} ", self.render_inline(element)),
+            _ => self.render_inline(element),
+        }
+    }
+
+    fn render_link(&mut self, element: ElementRef<'_>) -> String {
+        let text = tidy_inline(&self.render_inline(element)).replace("  \n", " ");
+        let href = element
+            .value()
+            .attr("href")
+            .map(str::trim)
+            .filter(|href| !href.is_empty() && !href.to_ascii_lowercase().starts_with("javascript:"))
+            .map(|href| self.resolve(href));
+
+        let Some(href) = href else { return text };
+        if text.is_empty() {
+            return format!("<{}>", href);
+        }
+
+        match self.options.link_style {
+            LinkStyle::Inline => {
+                let title = element
+                    .value()
+                    .attr("title")
+                    .map(|title| format!(" \"{}\"", title.replace('"', "\\\"")))
+                    .unwrap_or_default();
+                format!("[{}]({}{})", text, href, title)
+            }
+            LinkStyle::Reference => format!("[{}][{}]", text, self.reference(href)),
+        }
+    }
+
+    fn render_image(&mut self, element: ElementRef<'_>) -> String {
+        let alt = collapse_whitespace(element.value().attr("alt").unwrap_or("")).trim().to_string();
+        let alt = escape_text(&alt);
+        let src = element
+            .value()
+            .attr("src")
+            .map(str::trim)
+            .filter(|src| !src.is_empty() && !src.starts_with("data:"))
+            .map(|src| self.resolve(src));
+
+        match (src, self.options.link_style) {
+            (None, _) => alt,
+            (Some(src), LinkStyle::Inline) => format!("![{}]({})", alt, src),
+            (Some(src), LinkStyle::Reference) => format!("![{}][{}]", alt, self.reference(src)),
+        }
+    }
+
+    /// Resolve a link against the page URL and make it safe for `(...)`
+    fn resolve(&self, href: &str) -> String {
+        let resolved = match self.base {
+            Some(base) => base.join(href).map(|url| url.to_string()).unwrap_or_else(|_| href.to_string()),
+            None => href.to_string(),
+        };
+        resolved.replace(' ', "%20").replace('(', "%28").replace(')', "%29")
+    }
+
+    /// Reference number for a URL (1-based, deduplicated)
+    fn reference(&mut self, url: String) -> usize {
+        match self.references.iter().position(|existing| *existing == url) {
+            Some(index) => index + 1,
+            None => {
+                self.references.push(url);
+                self.references.len()
+            }
+        }
+    }
+
+    fn wrap(&self, text: &str) -> String {
+        match self.options.wrap_width {
+            Some(width) if width > 0 => wrap_text(text, width),
+            _ => text.to_string(),
+        }
+    }
+}
+
+/// Fenced code block with the language from `data-lang` or a `language-x` class
+fn code_block(pre: ElementRef<'_>) -> String {
+    let code = pre.children().filter_map(ElementRef::wrap).find(|child| child.value().name() == "code");
+    let language = [Some(pre), code]
+        .into_iter()
+        .flatten()
+        .find_map(|element| {
+            let value = element.value();
+            value
+                .attr("data-lang")
+                .map(str::to_string)
+                .or_else(|| value.attr("class").and_then(|class| LANGUAGE_RE.captures(class)).map(|caps| caps[1].to_string()))
+        })
+        .unwrap_or_default();
+
+    let text = pre.text().collect::<String>();
+    let text = text.strip_prefix('\n').unwrap_or(&text).trim_end();
+
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, text, fence)
+}
+
+/// Inline code span, with a fence longer than any backtick run inside
+fn code_span(code: &str) -> String {
+    let code = collapse_whitespace(code);
+    if code.trim().is_empty() {
+        return code;
+    }
+    let longest_run = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    let pad = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+    format!("{}{}{}{}{}", fence, pad, code, pad, fence)
+}
+
+/// Wrap inline content in emphasis markers, keeping surrounding spaces outside
+fn emphasize(inner: &str, marker: &str) -> String {
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        return inner.to_string();
+    }
+    let leading = if inner.starts_with(char::is_whitespace) { " " } else { "" };
+    let trailing = if inner.ends_with(char::is_whitespace) { " " } else { "" };
+    format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+}
+
+fn table_row(cells: &[String], columns: usize) -> String {
+    let cells: Vec<&str> = (0..columns).map(|i| cells.get(i).map_or("", String::as_str)).collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+fn prefix_lines(text: &str, prefix: &str, empty_prefix: &str) -> String {
+    text.lines()
+        .map(|line| if line.is_empty() { empty_prefix.to_string() } else { format!("{}{}", prefix, line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Collapse whitespace runs (including newlines) into single spaces
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+    out
+}
+
+/// Normalize inline content: `\n` marks hard line breaks, spaces are collapsed per line
+fn tidy_inline(text: &str) -> String {
+    text.split('\n')
+        .map(|line| collapse_whitespace(line).trim().to_string())
+        .collect::<Vec<_>>()
+        .join("  \n")
+        .trim_matches(|c: char| c.is_whitespace())
+        .to_string()
+}
+
+/// Escape characters that would otherwise start Markdown syntax
+fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '`' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Escape a paragraph start that would be read as a heading, quote or list
+fn escape_block_start(text: &str) -> String {
+    static BLOCK_START_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(#{1,6}|>|[-+]|\d+[.)])( |$)").unwrap());
+    match BLOCK_START_RE.find(text) {
+        Some(_) if text.starts_with(|c: char| c.is_ascii_digit()) => {
+            let digits = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+            format!("{}\\{}", &text[..digits], &text[digits..])
+        }
+        Some(_) => format!("\\{}", text),
+        None => text.to_string(),
+    }
+}
+
+/// Greedy word wrap that keeps hard line breaks
+fn wrap_text(text: &str, width: usize) -> String {
+    text.split("  \n")
+        .map(|line| {
+            let mut wrapped = String::new();
+            let mut current = 0;
+            for word in line.split(' ').filter(|word| !word.is_empty()) {
+                let len = word.chars().count();
+                if current > 0 && current + 1 + len > width {
+                    wrapped.push('\n');
+                    current = 0;
+                } else if current > 0 {
+                    wrapped.push(' ');
+                    current += 1;
+                }
+                wrapped.push_str(word);
+                current += len;
+            }
+            wrapped
+        })
+        .collect::<Vec<_>>()
+        .join("  \n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn convert(html: &str) -> String {
+        let base = Url::parse("https://example.com/docs/page.html").unwrap();
+        convert_html(html, Some(&base), MarkdownOptions::default())
+    }
+
     #[test]
     fn test_html_to_markdown() {
         let html = r#"
@@ -51,7 +579,7 @@ mod tests {
             </html>
         "#;
 
-        let result = html_to_markdown(html, "https://example.com");
+        let result = html_to_markdown(html, "https://example.com", MarkdownOptions::default());
         assert!(result.is_ok());
         let markdown = result.unwrap();
         // Readability may extract differently, so check for any content
@@ -59,6 +587,111 @@ mod tests {
         assert!(markdown.contains("paragraph") || markdown.contains("bold") || markdown.contains("Title"));
     }
 
+    #[test]
+    fn test_headings_and_inline() {
+        let markdown = convert(
+            "<h2>Install</h2><p>Run <code>cargo build</code> with <em>care</em> and <b>speed</b>.<br>Next line</p><h4>Notes</h4>",
+        );
+        assert_eq!(
+            markdown,
+            "## Install\n\nRun `cargo build` with *care* and **speed**.  \nNext line\n\n#### Notes"
+        );
+    }
+
+    #[test]
+    fn test_links_and_images() {
+        let markdown = convert(
+            r#"<p>See <a href="../api/index.html" title="API">the API</a>, <a href="https://rust-lang.org">Rust</a>
+            and <img src="/img/logo.png" alt="Logo"> <a href="javascript:void(0)">nothing</a></p>"#,
+        );
+        assert_eq!(
+            markdown,
+            "See [the API](https://example.com/api/index.html \"API\"), [Rust](https://rust-lang.org/) and ![Logo](https://example.com/img/logo.png) nothing"
+        );
+
+        let base = Url::parse("https://example.com/").unwrap();
+        let options = MarkdownOptions { link_style: LinkStyle::Reference, wrap_width: None };
+        let markdown = convert_html(
+            r#"<p><a href="/a">A</a> <a href="/b">B</a> <a href="/a">A again</a></p>"#,
+            Some(&base),
+            options,
+        );
+        assert_eq!(
+            markdown,
+            "[A][1] [B][2] [A again][1]\n\n[1]: https://example.com/a\n[2]: https://example.com/b"
+        );
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        let markdown = convert("<pre><code class=\"language-rust\">fn main() {\n    println!(\"hi\");\n}\n</code></pre>");
+        assert_eq!(markdown, "```rust\nfn main() {\n    println!(\"hi\");\n}\n```");
+
+        let markdown = convert("<pre>uses ``` fences</pre>");
+        assert_eq!(markdown, "````\nuses ``` fences\n````");
+
+        // Languages survive readability, which strips class attributes
+        let prepared = preserve_code_languages("<pre class='lang-python'><code>x = 1</code></pre>");
+        assert!(prepared.contains("data-lang=\"python\""));
+    }
+
+    #[test]
+    fn test_tables() {
+        let markdown = convert(
+            "<table><caption>Prices</caption><thead><tr><th>Item</th><th>Price</th></tr></thead>
+             <tbody><tr><td>Tea | green</td><td>3</td></tr><tr><td colspan=2>Total</td></tr></tbody></table>",
+        );
+        assert_eq!(
+            markdown,
+            "Prices\n\n| Item | Price |\n| --- | --- |\n| Tea \\| green | 3 |\n| Total |  |"
+        );
+    }
+
+    #[test]
+    fn test_lists_and_quotes() {
+        let markdown = convert(
+            "<ol start=3><li>Three</li><li>Four<ul><li>nested</li></ul></li></ol>
+             <ul><li><input type=checkbox checked> done</li></ul>
+             <blockquote><p>Quoted</p><p>Twice</p></blockquote>",
+        );
+        assert_eq!(
+            markdown,
+            "3. Three\n4. Four\n   - nested\n\n- [x] done\n\n> Quoted\n>\n> Twice"
+        );
+    }
+
+    #[test]
+    fn test_wrapping_and_escaping() {
+        let long = "word ".repeat(40);
+        let markdown = convert(&format!("<p>{}</p>", long));
+        assert!(!markdown.contains('\n'), "no hard wrapping by default");
+
+        let options = MarkdownOptions { wrap_width: Some(20), ..Default::default() };
+        let wrapped = convert_html(&format!("<p>{}</p>", long), None, options);
+        assert!(wrapped.lines().all(|line| line.chars().count() <= 20));
+
+        assert_eq!(convert("<p># not a heading *really*</p>"), "\\# not a heading \\*really\\*");
+        assert_eq!(convert("<p>1. not a list</p>"), "1\\. not a list");
+    }
+
+    #[test]
+    fn test_readability_keeps_structure() {
+        let paragraph = "<p>Rust programs are built with Cargo, which compiles the crate and its dependencies, and runs the tests too.</p>";
+        let html = format!(
+            r#"<html><head><title>Building Rust</title></head><body><nav><a href="/">Home</a></nav>
+            <article><h1>Building Rust</h1>{p}<h2>Example</h2>{p}
+            <pre><code class="language-rust">fn main() {{}}</code></pre>{p}
+            <p>Read <a href="guide.html">the guide</a>.</p></article></body></html>"#,
+            p = paragraph
+        );
+
+        let markdown = html_to_markdown(&html, "https://example.com/docs/", MarkdownOptions::default()).unwrap();
+        assert!(markdown.starts_with("# Building Rust\n"), "{}", markdown);
+        assert!(markdown.contains("## Example"), "{}", markdown);
+        assert!(markdown.contains("```rust\nfn main() {}\n```"), "{}", markdown);
+        assert!(markdown.contains("[the guide](https://example.com/docs/guide.html)"), "{}", markdown);
+    }
+
     #[test]
     fn test_html_to_text() {
         let html = "<p>Hello <b>world</b>!</p>";
@@ -0,0 +1,531 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Response, ResponseBuilderExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default cache size limit (256 MiB)
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Upper bound for heuristic freshness derived from Last-Modified (1 day)
+const MAX_HEURISTIC_AGE: u64 = 86_400;
+
+/// Headers that describe the connection or the client, not the stored response
+const UNSTORED_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-connection",
+    "set-cookie",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Per-call cache behavior
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheMode {
+    /// Serve fresh entries and revalidate stale ones
+    #[default]
+    Use,
+    /// Skip the stored entry, fetch from network and update the cache
+    Refresh,
+    /// Neither read nor write the cache
+    Bypass,
+}
+
+/// Stored response metadata (body is kept in a separate file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Requested URL (cache key)
+    pub url: String,
+
+    /// Final URL after redirects
+    pub final_url: String,
+
+    /// HTTP status code
+    pub status: u16,
+
+    /// Stored response headers
+    pub headers: Vec<(String, String)>,
+
+    /// Unix time (seconds) when the response was received or last revalidated
+    pub stored_at: u64,
+
+    /// Freshness directives computed from the headers
+    pub freshness: Freshness,
+
+    /// Body size in bytes
+    pub body_size: u64,
+}
+
+/// Cache directives computed from response headers (RFC 9111)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Freshness {
+    /// Whether the response may be stored at all
+    pub storable: bool,
+
+    /// Freshness lifetime in seconds
+    pub lifetime: u64,
+
+    /// Age of the response when it was received (Age header)
+    pub initial_age: u64,
+
+    /// Every use must be revalidated (`no-cache`)
+    pub no_cache: bool,
+}
+
+impl CacheEntry {
+    /// Build entry from a network response
+    pub fn new(url: &str, final_url: &str, status: u16, headers: &HeaderMap, body_size: u64) -> Self {
+        let now = unix_now();
+        Self {
+            url: url.to_string(),
+            final_url: final_url.to_string(),
+            status,
+            headers: storable_headers(headers),
+            stored_at: now,
+            freshness: response_freshness(headers, now),
+            body_size,
+        }
+    }
+
+    /// Current age of the stored response in seconds
+    pub fn age(&self, now: u64) -> u64 {
+        self.freshness.initial_age + now.saturating_sub(self.stored_at)
+    }
+
+    /// Check if the entry can be served without revalidation
+    pub fn is_fresh(&self, now: u64) -> bool {
+        !self.freshness.no_cache && self.age(now) < self.freshness.lifetime
+    }
+
+    /// Get stored header value (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Merge headers from a 304 response and restart the freshness clock
+    pub fn revalidated(mut self, not_modified: &HeaderMap) -> Self {
+        let mut merged = self.header_map();
+        for name in not_modified.keys() {
+            if UNSTORED_HEADERS.contains(&name.as_str()) || name == "content-length" {
+                continue;
+            }
+            merged.remove(name);
+            for value in not_modified.get_all(name) {
+                merged.append(name.clone(), value.clone());
+            }
+        }
+
+        let now = unix_now();
+        self.headers = storable_headers(&merged);
+        self.stored_at = now;
+        self.freshness = response_freshness(&merged, now);
+        self
+    }
+
+    /// Stored headers as a header map
+    pub fn header_map(&self) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (k, v) in &self.headers {
+            if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(k.as_bytes()), HeaderValue::from_str(v)) {
+                map.append(name, value);
+            }
+        }
+        map
+    }
+
+    /// Rebuild a response from the stored entry and body
+    pub fn to_response(&self, body: Bytes) -> Result<Response> {
+        let final_url = url::Url::parse(&self.final_url).context("Invalid cached URL")?;
+        let mut builder = http::Response::builder().status(self.status).url(final_url);
+        if let Some(headers) = builder.headers_mut() {
+            *headers = self.header_map();
+        }
+        let response = builder.body(body).context("Failed to rebuild cached response")?;
+        Ok(Response::from(response))
+    }
+}
+
+/// Compute storability and freshness lifetime from response headers
+pub fn response_freshness(headers: &HeaderMap, now: u64) -> Freshness {
+    let get = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    let mut no_store = false;
+    let mut no_cache = false;
+    let mut max_age = None;
+
+    for directive in headers.get_all("cache-control").iter().filter_map(|v| v.to_str().ok()) {
+        for part in directive.split(',') {
+            let part = part.trim().to_ascii_lowercase();
+            let (name, value) = match part.split_once('=') {
+                Some((n, v)) => (n.trim().to_string(), Some(v.trim().trim_matches('"').to_string())),
+                None => (part, None),
+            };
+            match name.as_str() {
+                "no-store" => no_store = true,
+                "no-cache" => no_cache = true,
+                "max-age" => max_age = value.and_then(|v| v.parse::<u64>().ok()),
+                _ => {}
+            }
+        }
+    }
+
+    if get("pragma").is_some_and(|p| p.to_ascii_lowercase().contains("no-cache")) && max_age.is_none() {
+        no_cache = true;
+    }
+
+    let date = get("date").and_then(parse_http_date).unwrap_or(now);
+    let lifetime = if let Some(max_age) = max_age {
+        max_age
+    } else if let Some(expires) = get("expires") {
+        // Invalid Expires values (e.g. "0") mean already expired
+        parse_http_date(expires).map(|e| e.saturating_sub(date)).unwrap_or(0)
+    } else if let Some(last_modified) = get("last-modified").and_then(parse_http_date) {
+        (date.saturating_sub(last_modified) / 10).min(MAX_HEURISTIC_AGE)
+    } else {
+        0
+    };
+
+    let has_validator = get("etag").is_some() || get("last-modified").is_some();
+    let vary_any = get("vary").is_some_and(|v| v.trim() == "*");
+    let storable = !no_store && !vary_any && (lifetime > 0 || has_validator);
+
+    Freshness {
+        storable,
+        lifetime,
+        initial_age: get("age").and_then(|a| a.trim().parse().ok()).unwrap_or(0),
+        no_cache,
+    }
+}
+
+/// Headers to keep when storing a response
+fn storable_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(name, _)| !UNSTORED_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn parse_http_date(value: &str) -> Option<u64> {
+    httpdate::parse_http_date(value.trim())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// Current Unix time in seconds
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Size and recency of an entry, for eviction
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    size: u64,
+    /// Recency stamp from `HttpCache::clock` (stored time for entries loaded from disk)
+    last_used: u64,
+}
+
+/// Persistent on-disk HTTP cache shared by all tools
+///
+/// Each entry is stored as `<sha256>.json` (metadata) and `<sha256>.body`.
+/// Least recently used entries are evicted once `max_bytes` is exceeded.
+pub struct HttpCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    index: Mutex<HashMap<String, IndexEntry>>,
+    clock: AtomicU64,
+}
+
+impl HttpCache {
+    /// Open (or create) a cache directory and index existing entries
+    pub fn open(dir: &Path, max_bytes: u64) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cache directory {}", dir.display()))?;
+
+        let mut index = HashMap::new();
+        for entry in std::fs::read_dir(dir).context("Failed to read cache directory")? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let meta = std::fs::read(&path)
+                .ok()
+                .and_then(|data| serde_json::from_slice::<CacheEntry>(&data).ok());
+            match meta {
+                Some(meta) => {
+                    index.insert(
+                        key.to_string(),
+                        IndexEntry {
+                            size: meta.body_size,
+                            last_used: meta.stored_at,
+                        },
+                    );
+                }
+                None => {
+                    // Corrupt or outdated entry
+                    let _ = std::fs::remove_file(&path);
+                    let _ = std::fs::remove_file(path.with_extension("body"));
+                }
+            }
+        }
+
+        let newest = index.values().map(|e| e.last_used).max().unwrap_or(0);
+        let cache = Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            index: Mutex::new(index),
+            clock: AtomicU64::new(newest + 1),
+        };
+        cache.evict();
+
+        tracing::info!(
+            "HTTP cache at {} ({} bytes of {} max)",
+            dir.display(),
+            cache.total_bytes(),
+            max_bytes
+        );
+        Ok(cache)
+    }
+
+    /// Cache key for a URL
+    pub fn key(url: &str) -> String {
+        let digest = Sha256::digest(format!("GET {}", url).as_bytes());
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn body_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.body", key))
+    }
+
+    /// Look up stored entry and body
+    pub async fn get(&self, url: &str) -> Option<(CacheEntry, Bytes)> {
+        let key = Self::key(url);
+        let meta = tokio::fs::read(self.meta_path(&key)).await.ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&meta).ok()?;
+        let body = tokio::fs::read(self.body_path(&key)).await.ok()?;
+
+        if entry.url != url || body.len() as u64 != entry.body_size {
+            self.remove(url).await;
+            return None;
+        }
+
+        if let Ok(mut index) = self.index.lock() {
+            if let Some(item) = index.get_mut(&key) {
+                item.last_used = self.tick();
+            }
+        }
+
+        Some((entry, Bytes::from(body)))
+    }
+
+    /// Store entry and body, evicting old entries if needed
+    pub async fn put(&self, entry: &CacheEntry, body: &Bytes) -> Result<()> {
+        if entry.body_size > self.max_bytes {
+            tracing::debug!("Not caching {} ({} bytes exceeds cache size)", entry.url, entry.body_size);
+            return Ok(());
+        }
+
+        let key = Self::key(&entry.url);
+        tokio::fs::write(self.body_path(&key), body)
+            .await
+            .context("Failed to write cache body")?;
+        self.write_meta(&key, entry).await?;
+
+        if let Ok(mut index) = self.index.lock() {
+            index.insert(
+                key,
+                IndexEntry {
+                    size: entry.body_size,
+                    last_used: self.tick(),
+                },
+            );
+        }
+        self.evict();
+        Ok(())
+    }
+
+    /// Replace entry metadata after a successful revalidation
+    pub async fn update(&self, entry: &CacheEntry) -> Result<()> {
+        self.write_meta(&Self::key(&entry.url), entry).await
+    }
+
+    async fn write_meta(&self, key: &str, entry: &CacheEntry) -> Result<()> {
+        let meta = serde_json::to_vec(entry).context("Failed to serialize cache entry")?;
+        tokio::fs::write(self.meta_path(key), meta)
+            .await
+            .context("Failed to write cache metadata")
+    }
+
+    /// Remove entry from cache
+    pub async fn remove(&self, url: &str) {
+        let key = Self::key(url);
+        let _ = tokio::fs::remove_file(self.meta_path(&key)).await;
+        let _ = tokio::fs::remove_file(self.body_path(&key)).await;
+        if let Ok(mut index) = self.index.lock() {
+            index.remove(&key);
+        }
+    }
+
+    /// Total size of stored bodies in bytes
+    pub fn total_bytes(&self) -> u64 {
+        self.index
+            .lock()
+            .map(|index| index.values().map(|e| e.size).sum())
+            .unwrap_or(0)
+    }
+
+    /// Drop least recently used entries until the cache fits in `max_bytes`
+    fn evict(&self) {
+        let Ok(mut index) = self.index.lock() else {
+            return;
+        };
+
+        let mut total: u64 = index.values().map(|e| e.size).sum();
+        if total <= self.max_bytes {
+            return;
+        }
+
+        let mut by_age: Vec<(String, IndexEntry)> = index.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        by_age.sort_by_key(|(_, e)| e.last_used);
+
+        for (key, entry) in by_age {
+            if total <= self.max_bytes {
+                break;
+            }
+            let _ = std::fs::remove_file(self.meta_path(&key));
+            let _ = std::fs::remove_file(self.body_path(&key));
+            index.remove(&key);
+            total = total.saturating_sub(entry.size);
+            tracing::debug!("Evicted cache entry {} ({} bytes)", key, entry.size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (k, v) in pairs {
+            map.append(*k, HeaderValue::from_str(v).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_freshness_max_age() {
+        let f = response_freshness(&headers(&[("cache-control", "public, max-age=600")]), 1_000);
+        assert!(f.storable);
+        assert_eq!(f.lifetime, 600);
+        assert!(!f.no_cache);
+    }
+
+    #[test]
+    fn test_freshness_expires() {
+        let f = response_freshness(
+            &headers(&[
+                ("date", "Mon, 01 Jan 2024 00:00:00 GMT"),
+                ("expires", "Mon, 01 Jan 2024 01:00:00 GMT"),
+            ]),
+            0,
+        );
+        assert_eq!(f.lifetime, 3600);
+        assert!(f.storable);
+    }
+
+    #[test]
+    fn test_freshness_no_store_and_validators() {
+        let f = response_freshness(&headers(&[("cache-control", "no-store"), ("etag", "\"a\"")]), 0);
+        assert!(!f.storable);
+
+        // No lifetime but a validator: storable, must revalidate
+        let f = response_freshness(&headers(&[("cache-control", "no-cache"), ("etag", "\"a\"")]), 0);
+        assert!(f.storable);
+        assert!(f.no_cache);
+
+        // Neither lifetime nor validator: not worth storing
+        let f = response_freshness(&headers(&[("content-type", "text/html")]), 0);
+        assert!(!f.storable);
+    }
+
+    #[test]
+    fn test_entry_age_and_revalidation() {
+        let mut entry = CacheEntry::new(
+            "https://example.com/",
+            "https://example.com/",
+            200,
+            &headers(&[("cache-control", "max-age=60"), ("etag", "\"v1\""), ("set-cookie", "a=b")]),
+            4,
+        );
+        assert!(entry.is_fresh(entry.stored_at + 30));
+        assert!(!entry.is_fresh(entry.stored_at + 61));
+        assert_eq!(entry.header("ETag"), Some("\"v1\""));
+        assert_eq!(entry.header("set-cookie"), None);
+
+        entry.stored_at -= 120;
+        let entry = entry.revalidated(&headers(&[("cache-control", "max-age=300")]));
+        assert!(entry.is_fresh(unix_now()));
+        assert_eq!(entry.freshness.lifetime, 300);
+        assert_eq!(entry.header("etag"), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_cache_roundtrip_and_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HttpCache::open(dir.path(), 10).unwrap();
+        let h = headers(&[("cache-control", "max-age=60")]);
+
+        let first = CacheEntry::new("https://a.example/", "https://a.example/", 200, &h, 6);
+        cache.put(&first, &Bytes::from_static(b"first!")).await.unwrap();
+        let (entry, body) = cache.get("https://a.example/").await.unwrap();
+        assert_eq!(entry.status, 200);
+        assert_eq!(&body[..], b"first!");
+
+        let response = entry.to_response(body).unwrap();
+        assert_eq!(response.url().as_str(), "https://a.example/");
+        assert_eq!(response.text().await.unwrap(), "first!");
+
+        // Second entry pushes total over 10 bytes, evicting the first
+        let second = CacheEntry::new("https://b.example/", "https://b.example/", 200, &h, 6);
+        cache.put(&second, &Bytes::from_static(b"second")).await.unwrap();
+        assert!(cache.get("https://a.example/").await.is_none());
+        assert!(cache.get("https://b.example/").await.is_some());
+        assert_eq!(cache.total_bytes(), 6);
+
+        // Entries survive reopening
+        drop(cache);
+        let reopened = HttpCache::open(dir.path(), 10).unwrap();
+        assert!(reopened.get("https://b.example/").await.is_some());
+    }
+}
@@ -1,35 +1,66 @@
+mod auth;
 mod batch;
+mod charset;
+mod config;
+mod crawl;
+mod domains;
+mod error;
 mod feed;
 mod fetch;
 mod html_convert;
+mod http_cache;
 mod image;
 mod links;
 mod logging;
 mod metadata;
+mod output;
+mod pagination;
 mod pdf;
+mod progress;
 mod reddit;
+mod resources;
+mod retry;
 mod robots;
+mod scheduler;
 mod search;
 mod selector;
 mod sitemap;
+mod ssrf;
+mod structured_data;
 mod validation;
 mod wiki;
 
+#[cfg(test)]
+mod test_util;
+
 use anyhow::Result;
 use clap::Parser;
 use rmcp::{
     ErrorData as McpError, ServerHandler, ServiceExt,
-    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
-    model::{CallToolResult, Content, Implementation, ServerCapabilities, ServerInfo},
-    tool, tool_handler, tool_router,
+    handler::server::{
+        router::{prompt::PromptRouter, tool::ToolRouter},
+        wrapper::{Json, Parameters},
+    },
+    model::{
+        AnnotateAble, GetPromptRequestParam, GetPromptResult, Implementation,
+        ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, Meta, PaginatedRequestParam, PromptMessage,
+        PromptMessageRole, RawResource, RawResourceTemplate, ReadResourceRequestParam,
+        ReadResourceResult, ResourceContents, ResourceTemplate, ServerCapabilities, ServerInfo,
+    },
+    service::RequestContext,
+    RoleServer,
+    prompt, prompt_handler, prompt_router, tool, tool_handler, tool_router,
     transport::stdio,
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde_json::json;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use error::{ErrorCode, ToolError, tool_err};
+
 /// Fetch MCP Server - Advanced web content fetching with 13+ tools
 #[derive(Parser, Debug)]
 #[command(name = "fetch-mcp-rs")]
@@ -43,6 +74,14 @@ struct Cli {
     #[arg(long)]
     ignore_robots_txt: bool,
 
+    /// Product token matched against robots.txt User-agent lines (default: from the user agent)
+    #[arg(long, value_name = "TOKEN")]
+    robots_user_agent: Option<String>,
+
+    /// Seconds a fetched robots.txt is reused
+    #[arg(long)]
+    robots_cache_ttl: Option<u64>,
+
     /// HTTP proxy URL (e.g., http://proxy:8080)
     #[arg(long)]
     proxy_url: Option<String>,
@@ -62,22 +101,202 @@ struct Cli {
     /// Bind address for stream mode
     #[arg(short = 'b', long, default_value = "127.0.0.1")]
     bind: String,
+
+    /// Allow private/loopback destinations in this CIDR range (repeatable)
+    #[arg(long = "allow-cidr", value_name = "CIDR")]
+    allow_cidrs: Vec<String>,
+
+    /// Allow private destinations for this host, e.g. wiki.corp or *.corp (repeatable)
+    #[arg(long = "allow-host", value_name = "HOST")]
+    allow_hosts: Vec<String>,
+
+    /// Only fetch hosts matching this pattern: host, *.glob, /regex/ or CIDR (repeatable)
+    #[arg(long = "allow-domain", value_name = "PATTERN")]
+    allow_domains: Vec<domains::DomainPattern>,
+
+    /// Never fetch hosts matching this pattern: host, *.glob, /regex/ or CIDR (repeatable)
+    #[arg(long = "deny-domain", value_name = "PATTERN")]
+    deny_domains: Vec<domains::DomainPattern>,
+
+    /// Directory for the persistent HTTP cache (disabled if not set)
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Maximum total size of cached response bodies in bytes
+    #[arg(long)]
+    cache_max_bytes: Option<u64>,
+
+    /// Maximum response body size in bytes (after decompression)
+    #[arg(long)]
+    max_body_bytes: Option<u64>,
+
+    /// Seconds to establish a connection
+    #[arg(long, value_name = "SECS")]
+    connect_timeout: Option<u64>,
+
+    /// Seconds for a whole request, per attempt
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Requests per second to one host (0 = unlimited; overrides the config file default)
+    #[arg(long, value_name = "RPS")]
+    host_rate_limit: Option<f64>,
+
+    /// Requests in flight to one host (overrides the config file default)
+    #[arg(long, value_name = "N")]
+    max_concurrent_per_host: Option<usize>,
+
+    /// Retries of transient failures for idempotent requests (0 disables retrying)
+    #[arg(long, value_name = "N")]
+    max_retries: Option<u32>,
+
+    /// Withhold this tool from clients (repeatable)
+    #[arg(long = "disable-tool", value_name = "NAME")]
+    disabled_tools: Vec<String>,
+
+    /// Config file (TOML); command line flags override its settings
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Override config file settings with the flags that were given
+    ///
+    /// Allow and deny list flags replace the file's lists; `--disable-tool` adds to them.
+    fn apply(&self, config: &mut config::Config) {
+        if let Some(user_agent) = &self.user_agent {
+            config.user_agent = Some(user_agent.clone());
+        }
+        if let Some(proxy_url) = &self.proxy_url {
+            config.proxy_url = Some(proxy_url.clone());
+        }
+        if let Some(max_body_bytes) = self.max_body_bytes {
+            config.max_body_bytes = max_body_bytes;
+        }
+        if let Some(secs) = self.connect_timeout {
+            config.timeouts.connect_secs = secs;
+        }
+        if let Some(secs) = self.timeout {
+            config.timeouts.request_secs = secs;
+        }
+        if let Some(dir) = &self.cache_dir {
+            config.cache.dir = Some(dir.clone());
+        }
+        if let Some(max_bytes) = self.cache_max_bytes {
+            config.cache.max_bytes = max_bytes;
+        }
+        if self.ignore_robots_txt {
+            config.robots.enabled = false;
+        }
+        if let Some(token) = &self.robots_user_agent {
+            config.robots.user_agent = Some(token.clone());
+        }
+        if let Some(ttl) = self.robots_cache_ttl {
+            config.robots.cache_ttl_secs = ttl;
+        }
+        if let Some(max_retries) = self.max_retries {
+            config.retry.max_retries = max_retries;
+        }
+        if !self.allow_cidrs.is_empty() {
+            config.network.allow_cidrs = self.allow_cidrs.clone();
+        }
+        if !self.allow_hosts.is_empty() {
+            config.network.allow_hosts = self.allow_hosts.clone();
+        }
+        if !self.allow_domains.is_empty() {
+            config.network.allow_domains = self.allow_domains.clone();
+        }
+        if !self.deny_domains.is_empty() {
+            config.network.deny_domains = self.deny_domains.clone();
+        }
+        config.tools.disabled.extend(self.disabled_tools.iter().cloned());
+        if let Some(rate) = self.host_rate_limit {
+            config.scheduler.requests_per_second = rate;
+        }
+        if let Some(max_concurrent) = self.max_concurrent_per_host {
+            config.scheduler.max_concurrent = max_concurrent;
+        }
+    }
 }
 
 /// Global server state
 struct ServerState {
-    client: reqwest::Client,
+    fetcher: fetch::Fetcher,
+    policy: Arc<ssrf::DestinationPolicy>,
+    documents: resources::DocumentStore,
+    /// User agent for tool requests
     user_agent: String,
-    ignore_robots: bool,
+    /// User agent for fetches requested by the user through prompts
+    manual_user_agent: String,
+    /// Effective configuration, after command line overrides
+    config: config::Config,
+    /// Config file the settings were loaded from
+    config_file: Option<PathBuf>,
 }
 
 impl ServerState {
-    fn new(user_agent: String, ignore_robots: bool, proxy_url: Option<&str>) -> Result<Self> {
-        let client = fetch::create_client(proxy_url, &user_agent)?;
+    /// Build the shared fetch pipeline from the effective configuration
+    ///
+    /// `default_user_agent` applies to tool requests when the config sets none.
+    fn new(config: config::Config, config_file: Option<PathBuf>, default_user_agent: &str) -> Result<Self> {
+        let user_agent = config.user_agent.clone().unwrap_or_else(|| default_user_agent.to_string());
+        tracing::debug!("User-Agent: {}", user_agent);
+
+        let access = domains::AccessPolicy::new(config.network.allow_domains.clone(), config.network.deny_domains.clone());
+        let mut policy = ssrf::DestinationPolicy::new(&config.network.allow_cidrs, &config.network.allow_hosts)?
+            .with_access(access);
+        if config.proxy_url.is_some() {
+            policy = policy.with_proxy();
+        }
+        let policy = Arc::new(policy);
+        let client_options = fetch::ClientOptions {
+            proxy_url: config.proxy_url.clone(),
+            connect_timeout: std::time::Duration::from_secs(config.timeouts.connect_secs),
+            timeout: std::time::Duration::from_secs(config.timeouts.request_secs),
+            ..fetch::ClientOptions::new(&user_agent)
+        };
+
+        // Open persistent HTTP cache if configured
+        let cache = config
+            .cache
+            .dir
+            .as_deref()
+            .map(|dir| http_cache::HttpCache::open(dir, config.cache.max_bytes))
+            .transpose()?;
+
+        let client = fetch::create_client(&client_options, policy.clone())?;
+        let mut fetcher = fetch::Fetcher::new(client, cache.map(Arc::new)).with_policy(policy.clone());
+        fetcher.max_body_bytes = config.max_body_bytes;
+        fetcher.retry = config.retry;
+        // One scheduler for all tools, so concurrent calls share each host's limits
+        fetcher.scheduler = Arc::new(scheduler::Scheduler::new(config.scheduler.clone(), config.domains.clone()));
+        fetcher.domains = Arc::new(config.domains.clone());
+
+        if !config.auth.profiles.is_empty() {
+            let client = fetch::create_credentialed_client(&client_options, policy.clone())?;
+            fetcher = fetcher.with_credentials(Arc::new(auth::CredentialStore::new(config.auth.clone(), client)?));
+        }
+
+        // robots.txt groups are matched on the product token, not the full user agent
+        if config.robots.enabled {
+            let token = config.robots.user_agent.clone()
+                .unwrap_or_else(|| robots::product_token(&user_agent).to_string());
+            let ttl = std::time::Duration::from_secs(config.robots.cache_ttl_secs);
+            fetcher = fetcher.with_robots(Arc::new(robots::RobotsCache::new(&token, ttl)));
+        }
+
+        // Prompts fetch on the user's behalf; a configured user agent applies to them too
+        let manual_user_agent = config.user_agent.clone()
+            .unwrap_or_else(|| fetch::DEFAULT_USER_AGENT_MANUAL.to_string());
+
         Ok(Self {
-            client,
+            fetcher,
+            policy,
+            documents: resources::DocumentStore::default(),
             user_agent,
-            ignore_robots,
+            manual_user_agent,
+            config,
+            config_file,
         })
     }
 }
@@ -86,14 +305,74 @@ impl ServerState {
 struct FetchServer {
     state: Arc<ServerState>,
     tool_router: ToolRouter<Self>,
+    prompt_router: PromptRouter<Self>,
 }
 
 impl FetchServer {
-    fn new(state: Arc<ServerState>) -> Self {
-        Self {
+    /// Create the server, offering only the tools enabled by the config
+    fn new(state: Arc<ServerState>) -> Result<Self> {
+        let mut tool_router = Self::tool_router();
+
+        let tools = &state.config.tools;
+        for name in tools.enabled.iter().flatten().chain(&tools.disabled) {
+            if !tool_router.has_route(name) {
+                anyhow::bail!("Unknown tool '{}' in tool configuration", name);
+            }
+        }
+        let withheld: Vec<String> = tool_router
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .filter(|name| !tools.is_enabled(name))
+            .collect();
+        for name in withheld {
+            tool_router.remove_route(&name);
+        }
+
+        // Prompts fetch like the fetch tool, so they go with it
+        let prompt_router = if tools.is_enabled("fetch") { Self::prompt_router() } else { PromptRouter::new() };
+
+        Ok(Self {
             state,
-            tool_router: Self::tool_router(),
+            tool_router,
+            prompt_router,
+        })
+    }
+
+    /// Refuse fetches through resources and prompts when the fetch tool is disabled
+    fn check_fetch_enabled(&self) -> Result<(), McpError> {
+        if self.state.config.tools.is_enabled("fetch") {
+            return Ok(());
+        }
+        Err(McpError::invalid_request("Fetching URLs is disabled: the fetch tool is not enabled", None))
+    }
+
+    /// URI templates offered by `resources/templates/list`
+    fn resource_templates(&self) -> Vec<ResourceTemplate> {
+        let template = |uri_template: &str, name: &str, description: &str| {
+            RawResourceTemplate {
+                uri_template: uri_template.to_string(),
+                name: name.to_string(),
+                title: None,
+                description: Some(description.to_string()),
+                mime_type: None,
+            }
+            .no_annotation()
+        };
+        let mut templates = vec![template(
+            "fetch://cache/{hash}{?start_index,max_length}",
+            "fetched_document",
+            "Re-read a previously fetched document without refetching it",
+        )];
+        if self.state.config.tools.is_enabled("fetch") {
+            let fetch_url = template(
+                "fetch://url/{url}{?start_index,max_length}",
+                "fetch_url",
+                "Fetch a URL and return it converted like the fetch tool (Markdown for HTML)",
+            );
+            templates.insert(0, fetch_url);
         }
+        templates
     }
 
     fn server_info(&self) -> ServerInfo {
@@ -101,6 +380,8 @@ impl FetchServer {
             protocol_version: Default::default(),
             capabilities: ServerCapabilities::builder()
                 .enable_tools()
+                .enable_resources()
+                .enable_prompts()
                 .build(),
             server_info: Implementation {
                 name: "fetch-mcp-rs".to_string(),
@@ -112,6 +393,141 @@ impl FetchServer {
             instructions: None,
         }
     }
+
+    /// Validate URL format and check its host against the SSRF policy
+    fn validate_url(&self, url: &str) -> Result<String, ToolError> {
+        let url = validation::validate_url(url, &self.state.policy.access)
+            .map_err(tool_err(ErrorCode::Validation, "URL validation failed"))?;
+        self.state.policy.check_url(&url)
+            .map_err(tool_err(ErrorCode::Validation, "Destination blocked by SSRF policy"))?;
+        Ok(url)
+    }
+
+    /// Fetch a URL and convert the body with the handler for its content type
+    ///
+    /// The body is `None` for PDFs and images cut off by the size limit, which can't be parsed.
+    async fn fetch_converted(
+        &self,
+        url: &str,
+        options: &fetch::FetchOptions,
+        raw: bool,
+        markdown: html_convert::MarkdownOptions,
+    ) -> Result<(Option<ConvertedBody>, fetch::ResponseInfo), ToolError> {
+        let (body, mut info) = fetch::fetch_url_bytes(&self.state.fetcher, url, options)
+            .await
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch URL"))?;
+
+        let content_type = info.headers.get("content-type").cloned().unwrap_or_default();
+        let kind = fetch::detect_content_type(&content_type, &body);
+
+        // PDF and image parsers need the complete body
+        if info.body_truncated && matches!(kind, fetch::ContentType::Pdf | fetch::ContentType::Image) {
+            return Ok((None, info));
+        }
+
+        let final_url = info.final_url.clone();
+        let mut decode_text = || {
+            let (text, encoding) = charset::decode(&body, Some(&content_type));
+            info.encoding = Some(encoding);
+            text
+        };
+
+        // Route the body to the handler for its content type
+        let (handler, text, details) = match kind {
+            fetch::ContentType::Pdf => {
+                let pdf_info = pdf::extract_pdf_text(&body, None)
+                    .map_err(tool_err(ErrorCode::Parse, "Failed to extract PDF text"))?;
+                let details = output::PdfDetails { num_pages: pdf_info.num_pages, metadata: pdf_info.metadata };
+                ("pdf", pdf_info.text, Some(details))
+            }
+            fetch::ContentType::Image => {
+                let image_info = image::extract_image_info(&body)
+                    .map_err(tool_err(ErrorCode::Parse, "Failed to extract image info"))?;
+                let text = serde_json::to_string_pretty(&image_info)
+                    .map_err(tool_err(ErrorCode::Internal, "Failed to serialize image info"))?;
+                ("image", text, None)
+            }
+            _ if raw => ("raw", decode_text(), None),
+            fetch::ContentType::Html => {
+                let markdown = html_convert::html_to_markdown(&decode_text(), &final_url, markdown)
+                    .map_err(tool_err(ErrorCode::Parse, "Failed to convert HTML"))?;
+                ("html", markdown, None)
+            }
+            fetch::ContentType::Feed => {
+                let feed_info = feed::parse_feed(&decode_text(), default_max_items())
+                    .map_err(tool_err(ErrorCode::Parse, "Failed to parse feed"))?;
+                let text = serde_json::to_string_pretty(&feed_info)
+                    .map_err(tool_err(ErrorCode::Internal, "Failed to serialize feed"))?;
+                ("feed", text, None)
+            }
+            fetch::ContentType::Json => {
+                let text = decode_text();
+                match serde_json::from_str::<serde_json::Value>(&text) {
+                    Ok(value) => {
+                        let pretty = serde_json::to_string_pretty(&value)
+                            .map_err(tool_err(ErrorCode::Internal, "Failed to serialize JSON"))?;
+                        ("json", pretty, None)
+                    }
+                    // Cut off by the size limit or malformed: pass through as text
+                    Err(_) => ("text", text, None),
+                }
+            }
+            fetch::ContentType::Xml | fetch::ContentType::Text => ("text", decode_text(), None),
+        };
+
+        Ok((Some(ConvertedBody { handler, text, details }), info))
+    }
+
+    /// Fetch a URL with default options and keep it as a resource
+    ///
+    /// Documents fetched with credentials are not kept, since the store is shared
+    /// by all sessions; the flag tells whether the document was stored.
+    async fn fetch_document(&self, url: &str) -> Result<(Arc<resources::StoredDocument>, bool), McpError> {
+        let url = self.validate_url(url)?;
+
+        let options = fetch::FetchOptions::default();
+        let (converted, info) = self.fetch_converted(&url, &options, false, Default::default()).await?;
+        let converted = converted.ok_or_else(|| ToolError::too_large(&url, self.state.fetcher.body_limit(&options)))?;
+
+        let is_shared = fetch::is_shared(&self.state.fetcher, &url, &options);
+        let document = resources::StoredDocument {
+            url,
+            final_url: info.final_url,
+            handler: converted.handler.to_string(),
+            content: converted.text,
+            fetched_at: http_cache::unix_now(),
+        };
+        if is_shared {
+            Ok((self.state.documents.insert(document), true))
+        } else {
+            Ok((Arc::new(document), false))
+        }
+    }
+
+    /// Fetch a URL on the user's behalf: manual user agent, no robots.txt check
+    async fn fetch_for_prompt(&self, url: &str) -> Result<String, McpError> {
+        self.check_fetch_enabled()?;
+        let url = self.validate_url(url)?;
+
+        let mut options = fetch::FetchOptions {
+            user_initiated: true,
+            ..Default::default()
+        };
+        let user_agent = reqwest::header::HeaderValue::from_str(&self.state.manual_user_agent)
+            .map_err(internal_err("Invalid user agent"))?;
+        options.headers.insert(reqwest::header::USER_AGENT, user_agent);
+
+        let (converted, _) = self.fetch_converted(&url, &options, false, Default::default()).await?;
+        let converted = converted.ok_or_else(|| ToolError::too_large(&url, self.state.fetcher.body_limit(&options)))?;
+        Ok(converted.text)
+    }
+}
+
+// Response body converted by its content-type handler, before pagination
+struct ConvertedBody {
+    handler: &'static str,
+    text: String,
+    details: Option<output::PdfDetails>,
 }
 
 // Helper for internal errors
@@ -123,6 +539,84 @@ fn internal_err<T: ToString>(msg: &'static str) -> impl FnOnce(T) -> McpError +
 // Tool Input Schemas
 // ============================================================================
 
+// Cache and credential selection shared by all fetch-style tools (flattened into each)
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+struct FetchControlArgs {
+    #[serde(default)]
+    cache: http_cache::CacheMode,
+    #[serde(default)]
+    auth_profile: Option<String>,
+    #[serde(default)]
+    max_bytes: Option<u64>,
+}
+
+impl FetchControlArgs {
+    /// Build fetch options for a plain GET
+    fn to_fetch_options(&self) -> fetch::FetchOptions {
+        fetch::FetchOptions {
+            cache: self.cache,
+            auth_profile: self.auth_profile.clone(),
+            max_bytes: self.max_bytes,
+            ..Default::default()
+        }
+    }
+}
+
+// HTTP request customization shared by `fetch` and `fetch_batch` (flattened into both)
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+struct RequestArgs {
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    headers: Option<HashMap<String, String>>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    json_body: Option<serde_json::Value>,
+    #[serde(default)]
+    query: Option<HashMap<String, String>>,
+}
+
+impl RequestArgs {
+    /// Validate request arguments and build fetch options
+    fn to_fetch_options(&self, control: &FetchControlArgs) -> Result<fetch::FetchOptions, ToolError> {
+        let method = self.method.as_deref()
+            .map(validation::validate_method)
+            .transpose()
+            .map_err(tool_err(ErrorCode::Validation, "Method validation failed"))?;
+
+        let headers = self.headers.as_ref()
+            .map(validation::validate_headers)
+            .transpose()
+            .map_err(tool_err(ErrorCode::Validation, "Header validation failed"))?
+            .unwrap_or_default();
+
+        let body = match (&self.body, &self.json_body) {
+            (Some(_), Some(_)) => {
+                return Err(ToolError::new(ErrorCode::Validation, "Only one of body and json_body may be set"));
+            }
+            (Some(text), None) => Some(fetch::RequestBody::Text(text.clone())),
+            (None, Some(json)) => Some(fetch::RequestBody::Json(json.clone())),
+            (None, None) => None,
+        };
+
+        if body.is_some() && matches!(method, None | Some(reqwest::Method::GET) | Some(reqwest::Method::HEAD)) {
+            return Err(ToolError::new(ErrorCode::Validation, "Request body requires POST, PUT, PATCH, DELETE or OPTIONS"));
+        }
+
+        let mut query: Vec<(String, String)> = self.query.clone().unwrap_or_default().into_iter().collect();
+        query.sort();
+
+        Ok(fetch::FetchOptions {
+            method,
+            headers,
+            body,
+            query,
+            ..control.to_fetch_options()
+        })
+    }
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 struct FetchArgs {
     url: String,
@@ -132,11 +626,47 @@ struct FetchArgs {
     start_index: Option<usize>,
     #[serde(default)]
     raw: bool,
+    #[serde(default)]
+    link_style: html_convert::LinkStyle,
+    #[serde(default)]
+    wrap_width: Option<usize>,
+    #[serde(flatten)]
+    control: FetchControlArgs,
+    #[serde(flatten)]
+    request: RequestArgs,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FetchPromptArgs {
+    /// URL to fetch
+    url: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SummarizePageArgs {
+    /// URL of the page to summarize
+    url: String,
+    /// What the summary should focus on
+    #[serde(default)]
+    focus: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ComparePagesArgs {
+    /// First URL
+    url_a: String,
+    /// Second URL
+    url_b: String,
+    /// What to compare (default: content, claims and coverage)
+    #[serde(default)]
+    aspect: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 struct FetchMetadataArgs {
     url: String,
+    #[serde(flatten)]
+    control: FetchControlArgs,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -144,6 +674,8 @@ struct FetchFeedArgs {
     url: String,
     #[serde(default = "default_max_items")]
     max_items: usize,
+    #[serde(flatten)]
+    control: FetchControlArgs,
 }
 
 fn default_max_items() -> usize {
@@ -154,17 +686,54 @@ fn default_max_items() -> usize {
 struct FetchWithSelectorArgs {
     url: String,
     selector: String,
+    #[serde(flatten)]
+    control: FetchControlArgs,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 struct ExtractTableArgs {
     url: String,
     table_selector: Option<String>,
+    #[serde(flatten)]
+    control: FetchControlArgs,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 struct FetchSitemapArgs {
+    /// Sitemap URL, or a site (bare domain or root URL) whose sitemaps are discovered
     url: String,
+    /// Levels of nested sitemap indexes followed
+    #[serde(default = "default_sitemap_depth")]
+    max_depth: usize,
+    /// Sitemap files fetched (max 500)
+    #[serde(default = "default_sitemap_files")]
+    max_sitemaps: usize,
+    /// URL entries returned (max 50000)
+    #[serde(default = "default_sitemap_urls")]
+    max_urls: usize,
+    /// Keep entries with `lastmod` at or after this W3C date/time (e.g. 2024-01-31)
+    #[serde(default)]
+    lastmod_after: Option<String>,
+    /// Keep entries with `lastmod` at or before this W3C date/time
+    #[serde(default)]
+    lastmod_before: Option<String>,
+    /// Keep entries whose URL matches this regex
+    #[serde(default)]
+    pattern: Option<String>,
+    #[serde(flatten)]
+    control: FetchControlArgs,
+}
+
+fn default_sitemap_depth() -> usize {
+    sitemap::DEFAULT_MAX_DEPTH
+}
+
+fn default_sitemap_files() -> usize {
+    sitemap::DEFAULT_MAX_SITEMAPS
+}
+
+fn default_sitemap_urls() -> usize {
+    sitemap::DEFAULT_MAX_URLS
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -174,6 +743,8 @@ struct FetchLinksArgs {
     internal_only: bool,
     #[serde(default)]
     external_only: bool,
+    #[serde(flatten)]
+    control: FetchControlArgs,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -183,6 +754,15 @@ struct FetchBatchArgs {
     max_concurrent: usize,
     #[serde(default = "default_rate_limit")]
     rate_limit: Option<u32>,
+    /// Retries of timeouts, connection failures and 429/502/503/504 (server default when omitted)
+    #[serde(default)]
+    retry: Option<retry::RetryPolicy>,
+    #[serde(default)]
+    auth_profile: Option<String>,
+    #[serde(default)]
+    max_bytes: Option<u64>,
+    #[serde(flatten)]
+    request: RequestArgs,
 }
 
 fn default_max_concurrent() -> usize {
@@ -193,6 +773,56 @@ fn default_rate_limit() -> Option<u32> {
     Some(10)
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CrawlArgs {
+    /// Seed URLs the crawl starts from
+    urls: Vec<String>,
+    /// Links followed from the seeds (0 fetches only the seeds)
+    #[serde(default = "default_crawl_depth")]
+    max_depth: usize,
+    /// Pages fetched, including failures (max 500)
+    #[serde(default = "default_crawl_pages")]
+    max_pages: usize,
+    /// Total body bytes downloaded
+    #[serde(default)]
+    max_total_bytes: Option<u64>,
+    /// Wall time of the crawl in seconds
+    #[serde(default = "default_crawl_duration")]
+    max_duration_secs: u64,
+    /// Which discovered links are followed
+    #[serde(default)]
+    scope: crawl::CrawlScope,
+    /// URL regex for the `regex` scope
+    #[serde(default)]
+    pattern: Option<String>,
+    /// What each page reports
+    #[serde(default)]
+    content: crawl::PageContent,
+    /// Characters of Markdown kept per page
+    #[serde(default = "default_crawl_content_length")]
+    max_content_length: usize,
+    #[serde(default = "default_max_concurrent")]
+    max_concurrent: usize,
+    #[serde(flatten)]
+    control: FetchControlArgs,
+}
+
+fn default_crawl_depth() -> usize {
+    crawl::DEFAULT_MAX_DEPTH
+}
+
+fn default_crawl_pages() -> usize {
+    crawl::DEFAULT_MAX_PAGES
+}
+
+fn default_crawl_duration() -> u64 {
+    crawl::DEFAULT_MAX_DURATION_SECS
+}
+
+fn default_crawl_content_length() -> usize {
+    crawl::DEFAULT_MAX_CONTENT_LENGTH
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 struct SearchInPageArgs {
     url: String,
@@ -205,6 +835,8 @@ struct SearchInPageArgs {
     max_matches: usize,
     #[serde(default)]
     extract_words: bool,
+    #[serde(flatten)]
+    control: FetchControlArgs,
 }
 
 fn default_max_matches() -> usize {
@@ -272,12 +904,16 @@ struct FetchPdfArgs {
     url: String,
     #[serde(default)]
     max_pages: Option<usize>,
+    #[serde(flatten)]
+    control: FetchControlArgs,
 }
 
 #[cfg(feature = "images")]
 #[derive(Debug, Deserialize, JsonSchema)]
 struct FetchImageArgs {
     url: String,
+    #[serde(flatten)]
+    control: FetchControlArgs,
 }
 
 // ============================================================================
@@ -286,199 +922,196 @@ struct FetchImageArgs {
 
 #[tool_router]
 impl FetchServer {
-    /// Fetch URL content and convert HTML to Markdown
-    #[tool(name = "fetch", description = "Fetch URL content and convert HTML to Markdown using Readability algorithm")]
-    async fn fetch(&self, Parameters(args): Parameters<FetchArgs>) -> Result<CallToolResult, McpError> {
+    /// Fetch URL content and route it to the handler for its content type
+    #[tool(name = "fetch", description = "Fetch URL content: HTML is converted to Markdown using Readability, PDFs, feeds, JSON and images are routed to their handlers")]
+    async fn fetch(&self, Parameters(args): Parameters<FetchArgs>) -> Result<Json<output::FetchOutput>, ToolError> {
         // Validate URL format
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
-
-        // Validate robots.txt if not ignored
-        if !self.state.ignore_robots {
-            robots::check_robots_txt_allowed(&self.state.client, &url, &self.state.user_agent)
-                .await
-                .map_err(internal_err("robots.txt check failed"))?;
-        }
+        let url = self.validate_url(&args.url)?;
 
-        let html = fetch::fetch_url_text(&self.state.client, &url)
-            .await
-            .map_err(internal_err("Failed to fetch URL"))?;
-
-        let content = if args.raw {
-            let text = if let Some(max_len) = args.max_length {
-                let start = args.start_index.unwrap_or(0);
-                let end = (start + max_len).min(html.len());
-                html[start..end].to_string()
-            } else {
-                html
-            };
-            json!({"content": text, "raw": true})
-        } else {
-            let markdown = html_convert::html_to_markdown(&html, &args.url)
-                .map_err(internal_err("Failed to convert HTML"))?;
-            let text = if let Some(max_len) = args.max_length {
-                let start = args.start_index.unwrap_or(0);
-                let end = (start + max_len).min(markdown.len());
-                markdown[start..end].to_string()
-            } else {
-                markdown
-            };
-            json!({"content": text, "url": args.url})
+        let options = args.request.to_fetch_options(&args.control)?;
+        let max_bytes = self.state.fetcher.body_limit(&options);
+        let markdown = html_convert::MarkdownOptions {
+            link_style: args.link_style,
+            wrap_width: args.wrap_width,
+        };
+        let (converted, info) = self.fetch_converted(&url, &options, args.raw, markdown).await?;
+        let Some(ConvertedBody { handler, text, details }) = converted else {
+            return Err(ToolError::too_large(&url, max_bytes));
         };
 
-        Ok(CallToolResult {
-            content: vec![Content::text(content.to_string())],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        // Plain GETs are kept as resources so clients can re-read them without refetching.
+        // The store is shared by all sessions, so responses tied to credentials or
+        // caller headers are left out.
+        let is_shared = fetch::is_shared(&self.state.fetcher, &url, &options);
+        let resource_uri = is_shared.then(|| {
+            self.state.documents.insert(resources::StoredDocument {
+                url: url.clone(),
+                final_url: info.final_url.clone(),
+                handler: handler.to_string(),
+                content: text.clone(),
+                fetched_at: http_cache::unix_now(),
+            }).uri()
+        });
+
+        let page = pagination::paginate(&text, args.start_index.unwrap_or(0), args.max_length);
+        let max_bytes = info.body_truncated.then_some(max_bytes);
+
+        Ok(Json(output::FetchOutput {
+            url: args.url,
+            handler: handler.to_string(),
+            page,
+            raw: args.raw,
+            pdf: details,
+            resource_uri,
+            response: info,
+            max_bytes,
+        }))
     }
 
     /// Extract Open Graph metadata
-    #[tool(name = "fetch_metadata", description = "Extract Open Graph, Schema.org, and HTML metadata from a URL")]
-    async fn fetch_metadata(&self, Parameters(args): Parameters<FetchMetadataArgs>) -> Result<CallToolResult, McpError> {
+    #[tool(name = "fetch_metadata", description = "Extract Open Graph, Schema.org (JSON-LD, Microdata, RDFa), HTML metadata and link relations (canonical, hreflang, AMP, prev/next, feeds, oEmbed, manifest, icons, rel=me) from a URL")]
+    async fn fetch_metadata(&self, Parameters(args): Parameters<FetchMetadataArgs>) -> Result<Json<output::Extracted<metadata::PageMetadata>>, ToolError> {
         // Validate URL
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+        let url = self.validate_url(&args.url)?;
 
-        let html = fetch::fetch_url_text(&self.state.client, &url)
+        let options = args.control.to_fetch_options();
+        let (html, info) = fetch::fetch_url_text(&self.state.fetcher, &url, &options)
             .await
-            .map_err(internal_err("Failed to fetch URL"))?;
-
-        let metadata = metadata::extract_metadata(&html, &url)
-            .map_err(internal_err("Failed to extract metadata"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch URL"))?;
 
-        let result = serde_json::to_string_pretty(&metadata)
-            .map_err(internal_err("Failed to serialize metadata"))?;
+        // Relative links resolve against the page after redirects
+        let metadata = metadata::extract_metadata(&html, &info.final_url)
+            .map_err(tool_err(ErrorCode::Parse, "Failed to extract metadata"))?;
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        let max_bytes = self.state.fetcher.body_limit(&options);
+        Ok(Json(output::Extracted::new(metadata, &info, max_bytes)))
     }
 
     /// Parse RSS/Atom feed
     #[tool(name = "fetch_feed", description = "Parse RSS, Atom, or JSON Feed from a URL")]
-    async fn fetch_feed(&self, Parameters(args): Parameters<FetchFeedArgs>) -> Result<CallToolResult, McpError> {
+    async fn fetch_feed(&self, Parameters(args): Parameters<FetchFeedArgs>) -> Result<Json<output::Extracted<feed::FeedInfo>>, ToolError> {
         // Validate URL
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+        let url = self.validate_url(&args.url)?;
 
-        let content = fetch::fetch_url_text(&self.state.client, &url)
+        let options = args.control.to_fetch_options();
+        let (content, info) = fetch::fetch_url_text(&self.state.fetcher, &url, &options)
             .await
-            .map_err(internal_err("Failed to fetch URL"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch URL"))?;
 
         let feed_info = feed::parse_feed(&content, args.max_items)
-            .map_err(internal_err("Failed to parse feed"))?;
-
-        let result = serde_json::to_string_pretty(&feed_info)
-            .map_err(internal_err("Failed to serialize feed"))?;
+            .map_err(tool_err(ErrorCode::Parse, "Failed to parse feed"))?;
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        let max_bytes = self.state.fetcher.body_limit(&options);
+        Ok(Json(output::Extracted::new(feed_info, &info, max_bytes)))
     }
 
     /// Extract elements using CSS selector
     #[tool(name = "fetch_with_selector", description = "Fetch URL and extract elements using CSS selector")]
-    async fn fetch_with_selector(&self, Parameters(args): Parameters<FetchWithSelectorArgs>) -> Result<CallToolResult, McpError> {
+    async fn fetch_with_selector(&self, Parameters(args): Parameters<FetchWithSelectorArgs>) -> Result<Json<output::Extracted<output::ElementList>>, ToolError> {
         // Validate URL and selector
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+        let url = self.validate_url(&args.url)?;
         let selector = validation::validate_selector(&args.selector)
-            .map_err(internal_err("Selector validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Selector validation failed"))?;
 
-        let html = fetch::fetch_url_text(&self.state.client, &url)
+        let options = args.control.to_fetch_options();
+        let (html, info) = fetch::fetch_url_text(&self.state.fetcher, &url, &options)
             .await
-            .map_err(internal_err("Failed to fetch URL"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch URL"))?;
 
         let elements = selector::select_elements(&html, &selector)
-            .map_err(internal_err("Failed to select elements"))?;
-
-        let result = serde_json::to_string_pretty(&elements)
-            .map_err(internal_err("Failed to serialize elements"))?;
+            .map_err(tool_err(ErrorCode::Parse, "Failed to select elements"))?;
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        let max_bytes = self.state.fetcher.body_limit(&options);
+        Ok(Json(output::Extracted::new(output::ElementList { elements }, &info, max_bytes)))
     }
 
     /// Extract tables from HTML
     #[tool(name = "extract_table", description = "Extract tables from HTML page")]
-    async fn extract_table(&self, Parameters(args): Parameters<ExtractTableArgs>) -> Result<CallToolResult, McpError> {
+    async fn extract_table(&self, Parameters(args): Parameters<ExtractTableArgs>) -> Result<Json<output::Extracted<output::TableList>>, ToolError> {
         // Validate URL
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+        let url = self.validate_url(&args.url)?;
 
-        let html = fetch::fetch_url_text(&self.state.client, &url)
+        let options = args.control.to_fetch_options();
+        let (html, info) = fetch::fetch_url_text(&self.state.fetcher, &url, &options)
             .await
-            .map_err(internal_err("Failed to fetch URL"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch URL"))?;
 
         let tables = selector::extract_table(&html, args.table_selector.as_deref())
-            .map_err(internal_err("Failed to extract tables"))?;
-
-        let result = serde_json::to_string_pretty(&tables)
-            .map_err(internal_err("Failed to serialize tables"))?;
+            .map_err(tool_err(ErrorCode::Parse, "Failed to extract tables"))?;
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        let max_bytes = self.state.fetcher.body_limit(&options);
+        Ok(Json(output::Extracted::new(output::TableList { tables }, &info, max_bytes)))
     }
 
-    /// Parse sitemap.xml
-    #[tool(name = "fetch_sitemap", description = "Parse sitemap.xml or sitemap index from a URL")]
-    async fn fetch_sitemap(&self, Parameters(args): Parameters<FetchSitemapArgs>) -> Result<CallToolResult, McpError> {
-        // Validate URL
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+    /// Parse sitemaps, following indexes
+    #[tool(name = "fetch_sitemap", description = "Read a sitemap (XML, gzip or plain text) and the sitemaps nested under it, or discover a site's sitemaps from robots.txt and common paths. Entries can be filtered by lastmod range and URL regex")]
+    async fn fetch_sitemap(&self, Parameters(args): Parameters<FetchSitemapArgs>) -> Result<Json<sitemap::SitemapResult>, ToolError> {
+        // A bare domain means the site root
+        let target = args.url.trim();
+        let url = if target.contains("://") {
+            self.validate_url(target)?
+        } else {
+            self.validate_url(&format!("https://{}/", target.trim_end_matches('/')))?
+        };
 
-        let xml = fetch::fetch_url_text(&self.state.client, &url)
-            .await
-            .map_err(internal_err("Failed to fetch URL"))?;
+        let max_sitemaps = validation::validate_limit(args.max_sitemaps, 500)
+            .map_err(tool_err(ErrorCode::Validation, "Sitemap limit validation failed"))?;
+        let max_urls = validation::validate_limit(args.max_urls, 50000)
+            .map_err(tool_err(ErrorCode::Validation, "URL limit validation failed"))?;
+        let pattern = args.pattern.as_deref()
+            .map(|pattern| validation::validate_regex(pattern).and_then(|pattern| Ok(regex::Regex::new(&pattern)?)))
+            .transpose()
+            .map_err(tool_err(ErrorCode::Validation, "Regex validation failed"))?;
+        let lastmod = |value: &Option<String>, name: &str| {
+            value.as_deref()
+                .map(|value| sitemap::parse_lastmod(value)
+                    .ok_or_else(|| ToolError::new(ErrorCode::Validation, format!("Invalid {} date: {}", name, value))))
+                .transpose()
+        };
 
-        let sitemap_data = sitemap::parse_sitemap(&xml)
-            .map_err(internal_err("Failed to parse sitemap"))?;
+        let options = sitemap::SitemapOptions {
+            max_depth: args.max_depth,
+            max_sitemaps,
+            max_urls,
+            lastmod_after: lastmod(&args.lastmod_after, "lastmod_after")?,
+            lastmod_before: lastmod(&args.lastmod_before, "lastmod_before")?,
+            pattern,
+            request: args.control.to_fetch_options(),
+        };
 
-        let result = serde_json::to_string_pretty(&sitemap_data)
-            .map_err(internal_err("Failed to serialize sitemap"))?;
+        // Site roots have no sitemap of their own, so discover one
+        let parsed = url::Url::parse(&url).map_err(|err| ToolError::new(ErrorCode::Validation, format!("Invalid URL: {}", err)))?;
+        let result = if parsed.path() == "/" && parsed.query().is_none() {
+            sitemap::discover_sitemaps(&self.state.fetcher, &self.state.policy, &parsed.origin().ascii_serialization(), &options)
+                .await
+                .map_err(tool_err(ErrorCode::Network, "Failed to discover sitemaps"))?
+        } else {
+            sitemap::collect_sitemaps(&self.state.fetcher, &self.state.policy, &url, &options)
+                .await
+                .map_err(tool_err(ErrorCode::Parse, "Failed to read sitemap"))?
+        };
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        Ok(Json(result))
     }
 
     /// Extract links from webpage
     #[tool(name = "fetch_links", description = "Extract all links from a webpage with optional filtering (internal/external)")]
-    async fn fetch_links(&self, Parameters(args): Parameters<FetchLinksArgs>) -> Result<CallToolResult, McpError> {
+    async fn fetch_links(&self, Parameters(args): Parameters<FetchLinksArgs>) -> Result<Json<output::Extracted<output::LinkList>>, ToolError> {
         // Validate URL
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+        let url = self.validate_url(&args.url)?;
 
-        let html = fetch::fetch_url_text(&self.state.client, &url)
+        let options = args.control.to_fetch_options();
+        let (html, info) = fetch::fetch_url_text(&self.state.fetcher, &url, &options)
             .await
-            .map_err(internal_err("Failed to fetch URL"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch URL"))?;
 
         // Use convenience wrappers if specific filtering requested
         let links_data = if args.internal_only && !args.external_only {
             links::extract_internal_links(&html, &url)
-                .map_err(internal_err("Failed to extract internal links"))?
+                .map_err(tool_err(ErrorCode::Parse, "Failed to extract internal links"))?
         } else if args.external_only && !args.internal_only {
             links::extract_external_links(&html, &url)
-                .map_err(internal_err("Failed to extract external links"))?
+                .map_err(tool_err(ErrorCode::Parse, "Failed to extract external links"))?
         } else {
             let options = links::LinkExtractionOptions {
                 internal_only: args.internal_only,
@@ -486,79 +1119,132 @@ impl FetchServer {
                 deduplicate: true,
             };
             links::extract_links(&html, &url, options)
-                .map_err(internal_err("Failed to extract links"))?
+                .map_err(tool_err(ErrorCode::Parse, "Failed to extract links"))?
         };
 
-        let result = serde_json::to_string_pretty(&links_data)
-            .map_err(internal_err("Failed to serialize links"))?;
-
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        let max_bytes = self.state.fetcher.body_limit(&options);
+        Ok(Json(output::Extracted::new(output::LinkList { links: links_data }, &info, max_bytes)))
     }
 
     /// Batch fetch multiple URLs
     #[tool(name = "fetch_batch", description = "Fetch multiple URLs in parallel with rate limiting and concurrency control")]
-    async fn fetch_batch(&self, Parameters(args): Parameters<FetchBatchArgs>) -> Result<CallToolResult, McpError> {
+    async fn fetch_batch(
+        &self,
+        Parameters(args): Parameters<FetchBatchArgs>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<Json<batch::BatchFetchResult>, ToolError> {
         // Validate URLs array size
         validation::validate_array_size(&args.urls, 100, "URLs")
-            .map_err(internal_err("Array validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Array validation failed"))?;
 
         // Validate each URL
-        let validated_urls: Result<Vec<_>, _> = args.urls
+        let urls = args.urls
             .iter()
-            .map(|url| validation::validate_url(url))
-            .collect();
-        let urls = validated_urls.map_err(internal_err("URL validation failed"))?;
+            .map(|url| self.validate_url(url))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Batch results are not cached
+        let control = FetchControlArgs {
+            cache: http_cache::CacheMode::Bypass,
+            auth_profile: args.auth_profile.clone(),
+            max_bytes: args.max_bytes,
+        };
 
         let options = batch::BatchOptions {
             max_concurrent: args.max_concurrent,
             rate_limit: args.rate_limit,
-            timeout: std::time::Duration::from_secs(30),
+            timeout: std::time::Duration::from_secs(self.state.config.timeouts.request_secs),
             fail_fast: false,
             follow_redirects: true,
+            retry: args.retry,
+            request: args.request.to_fetch_options(&control)?,
         };
 
-        let batch_result = batch::fetch_batch(&self.state.client, urls, options)
+        // Report each finished URL; a cancelled request returns what finished so far
+        let progress = progress::Progress::new(&context);
+        let total = urls.len();
+        let on_result = |result: &batch::FetchResult, done: usize| {
+            progress.report(done, total, format!("Fetched {}", result.url));
+        };
+        let batch_result = batch::fetch_batch(&self.state.fetcher, urls, options, on_result, context.ct.cancelled())
             .await
-            .map_err(internal_err("Failed to batch fetch"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to batch fetch"))?;
+        progress.flush().await;
+
+        Ok(Json(batch_result))
+    }
 
-        let result = serde_json::to_string_pretty(&batch_result)
-            .map_err(internal_err("Failed to serialize batch results"))?;
+    /// Crawl a site breadth-first
+    #[tool(name = "crawl", description = "Crawl a site breadth-first from seed URLs within depth, page, byte and time limits; returns per-page Markdown or metadata and the link graph")]
+    async fn crawl(
+        &self,
+        Parameters(args): Parameters<CrawlArgs>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<Json<crawl::CrawlResult>, ToolError> {
+        validation::validate_array_size(&args.urls, 100, "URLs")
+            .map_err(tool_err(ErrorCode::Validation, "Array validation failed"))?;
+        let seeds = args.urls
+            .iter()
+            .map(|url| self.validate_url(url))
+            .collect::<Result<Vec<_>, _>>()?;
+        let max_pages = validation::validate_limit(args.max_pages, 500)
+            .map_err(tool_err(ErrorCode::Validation, "Page limit validation failed"))?;
+        let pattern = args.pattern.as_deref()
+            .map(|pattern| validation::validate_regex(pattern).and_then(|pattern| Ok(regex::Regex::new(&pattern)?)))
+            .transpose()
+            .map_err(tool_err(ErrorCode::Validation, "Regex validation failed"))?;
+        if args.scope == crawl::CrawlScope::Regex && pattern.is_none() {
+            return Err(ToolError::new(ErrorCode::Validation, "The regex scope requires a pattern"));
+        }
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        let options = crawl::CrawlOptions {
+            max_depth: args.max_depth,
+            max_pages,
+            max_bytes: args.max_total_bytes.unwrap_or(crawl::DEFAULT_MAX_BYTES),
+            max_duration: std::time::Duration::from_secs(args.max_duration_secs),
+            scope: args.scope,
+            pattern,
+            content: args.content,
+            max_content_length: Some(args.max_content_length),
+            max_concurrent: args.max_concurrent,
+            request: args.control.to_fetch_options(),
+        };
+
+        // The total is the page budget; a cancelled crawl returns the pages finished so far
+        let progress = progress::Progress::new(&context);
+        let on_page = |page: &crawl::CrawlPage, done: usize| {
+            progress.report(done, max_pages, format!("Crawled {}", page.url));
+        };
+        let result = crawl::crawl(&self.state.fetcher, &self.state.policy, seeds, options, on_page, context.ct.cancelled())
+            .await
+            .map_err(tool_err(ErrorCode::Network, "Failed to crawl"))?;
+        progress.flush().await;
+
+        Ok(Json(result))
     }
 
     /// Search in page content
     #[tool(name = "search_in_page", description = "Search for text or regex pattern in page content with context")]
-    async fn search_in_page(&self, Parameters(args): Parameters<SearchInPageArgs>) -> Result<CallToolResult, McpError> {
+    async fn search_in_page(&self, Parameters(args): Parameters<SearchInPageArgs>) -> Result<Json<output::Extracted<search::SearchResult>>, ToolError> {
         // Validate URL
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+        let url = self.validate_url(&args.url)?;
 
         // Validate regex if used
         let query = if args.use_regex {
             validation::validate_regex(&args.query)
-                .map_err(internal_err("Regex validation failed"))?
+                .map_err(tool_err(ErrorCode::Validation, "Regex validation failed"))?
         } else {
             args.query.clone()
         };
 
-        let html = fetch::fetch_url_text(&self.state.client, &url)
+        let options = args.control.to_fetch_options();
+        let (html, info) = fetch::fetch_url_text(&self.state.fetcher, &url, &options)
             .await
-            .map_err(internal_err("Failed to fetch URL"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch URL"))?;
 
         let text = html_convert::html_to_text(&html);
 
-        let options = search::SearchOptions {
+        let search_options = search::SearchOptions {
             case_sensitive: args.case_sensitive,
             use_regex: args.use_regex,
             max_matches: args.max_matches,
@@ -567,38 +1253,31 @@ impl FetchServer {
             extract_words: args.extract_words,
         };
 
-        let search_result = search::search_in_text(&text, &query, options)
-            .map_err(internal_err("Failed to search"))?;
-
-        let result = serde_json::to_string_pretty(&search_result)
-            .map_err(internal_err("Failed to serialize search results"))?;
+        let search_result = search::search_in_text(&text, &query, search_options)
+            .map_err(tool_err(ErrorCode::Validation, "Failed to search"))?;
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        let max_bytes = self.state.fetcher.body_limit(&options);
+        Ok(Json(output::Extracted::new(search_result, &info, max_bytes)))
     }
 
     /// Fetch Reddit posts
     #[tool(name = "reddit", description = "Search or fetch posts from Reddit with optional comments")]
-    async fn reddit(&self, Parameters(args): Parameters<RedditArgs>) -> Result<CallToolResult, McpError> {
+    async fn reddit(&self, Parameters(args): Parameters<RedditArgs>) -> Result<Json<output::RedditOutput>, ToolError> {
         // Validate subreddit
         let subreddit = validation::validate_subreddit(&args.subreddit)
-            .map_err(internal_err("Subreddit validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Subreddit validation failed"))?;
 
         // Validate sort
         let sort = validation::validate_reddit_sort(&args.sort)
-            .map_err(internal_err("Sort validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Sort validation failed"))?;
 
         // Validate time filter
         let time_filter = validation::validate_reddit_time(args.time_filter.as_deref())
-            .map_err(internal_err("Time filter validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Time filter validation failed"))?;
 
         // Validate limit
         let limit = validation::validate_limit(args.limit, 100)
-            .map_err(internal_err("Limit validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Limit validation failed"))?;
 
         let options = reddit::RedditOptions {
             subreddit,
@@ -609,35 +1288,27 @@ impl FetchServer {
             max_comments: 10,
         };
 
-        let posts = reddit::fetch_reddit_posts(&self.state.client, args.query.as_deref(), options)
+        let posts = reddit::fetch_reddit_posts(&self.state.fetcher, args.query.as_deref(), options)
             .await
-            .map_err(internal_err("Failed to fetch Reddit posts"))?;
-
-        let result = serde_json::to_string_pretty(&posts)
-            .map_err(internal_err("Failed to serialize posts"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch Reddit posts"))?;
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        Ok(Json(output::RedditOutput { posts }))
     }
 
     /// Wikipedia search/article
     #[tool(name = "wiki", description = "Search Wikipedia, get article summary/content, or get random article")]
-    async fn wiki(&self, Parameters(args): Parameters<WikiArgs>) -> Result<CallToolResult, McpError> {
+    async fn wiki(&self, Parameters(args): Parameters<WikiArgs>) -> Result<Json<output::WikiOutput>, ToolError> {
         // Validate language code
         let language = validation::validate_language_code(&args.language)
-            .map_err(internal_err("Language code validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Language code validation failed"))?;
 
         // Validate action
         let action_str = validation::validate_wiki_action(&args.action)
-            .map_err(internal_err("Action validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Action validation failed"))?;
 
         // Validate limit
         let limit = validation::validate_limit(args.limit, 100)
-            .map_err(internal_err("Limit validation failed"))?;
+            .map_err(tool_err(ErrorCode::Validation, "Limit validation failed"))?;
 
         let options = wiki::WikiOptions {
             language,
@@ -648,99 +1319,267 @@ impl FetchServer {
 
         let result = match options.action {
             wiki::WikiAction::Search => {
-                let results = wiki::wiki_search(&self.state.client, &args.query, &options)
+                let results = wiki::wiki_search(&self.state.fetcher, &args.query, &options)
                     .await
-                    .map_err(internal_err("Failed to search Wikipedia"))?;
-                serde_json::to_string_pretty(&results)
-                    .map_err(internal_err("Failed to serialize results"))?
+                    .map_err(tool_err(ErrorCode::Network, "Failed to search Wikipedia"))?;
+                output::WikiOutput { results: Some(results), ..Default::default() }
             }
             wiki::WikiAction::Random => {
-                let article = wiki::wiki_random(&self.state.client, &options)
+                let article = wiki::wiki_random(&self.state.fetcher, &options)
                     .await
-                    .map_err(internal_err("Failed to get random article"))?;
-                serde_json::to_string_pretty(&article)
-                    .map_err(internal_err("Failed to serialize article"))?
+                    .map_err(tool_err(ErrorCode::Network, "Failed to get random article"))?;
+                output::WikiOutput { article: Some(article), ..Default::default() }
             }
             _ => {
-                let article = wiki::wiki_get_article(&self.state.client, &args.query, &options)
+                let article = wiki::wiki_get_article(&self.state.fetcher, &args.query, &options)
                     .await
-                    .map_err(internal_err("Failed to get article"))?;
-                serde_json::to_string_pretty(&article)
-                    .map_err(internal_err("Failed to serialize article"))?
+                    .map_err(tool_err(ErrorCode::Network, "Failed to get article"))?;
+                output::WikiOutput { article: Some(article), ..Default::default() }
             }
         };
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        Ok(Json(result))
+    }
+
+    /// Show the effective server configuration
+    #[tool(name = "server_config", description = "Show the effective server configuration (config file plus command line overrides) and the enabled tools")]
+    async fn server_config(&self) -> Result<Json<output::ServerConfigOutput>, ToolError> {
+        let config = serde_json::to_value(self.state.config.redacted())
+            .map_err(tool_err(ErrorCode::Internal, "Failed to serialize config"))?;
+        let mut tools: Vec<String> = self.tool_router.list_all().into_iter().map(|tool| tool.name.to_string()).collect();
+        tools.sort();
+
+        Ok(Json(output::ServerConfigOutput {
+            config_file: self.state.config_file.as_ref().map(|path| path.display().to_string()),
+            user_agent: self.state.user_agent.clone(),
+            config,
+            tools,
+        }))
     }
 
     /// Extract text from PDF
     #[cfg(feature = "pdf")]
     #[tool(name = "fetch_pdf_text", description = "Extract text and metadata from PDF files")]
-    async fn fetch_pdf_text(&self, Parameters(args): Parameters<FetchPdfArgs>) -> Result<CallToolResult, McpError> {
+    async fn fetch_pdf_text(
+        &self,
+        Parameters(args): Parameters<FetchPdfArgs>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<Json<pdf::PdfInfo>, ToolError> {
         // Validate URL
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+        let url = self.validate_url(&args.url)?;
 
-        // Fetch PDF bytes
-        let pdf_bytes = fetch::fetch_url_bytes(&self.state.client, &url)
-            .await
-            .map_err(internal_err("Failed to fetch PDF"))?;
-
-        // Extract text
-        let pdf_info = pdf::extract_pdf_text(&pdf_bytes, args.max_pages)
-            .map_err(internal_err("Failed to extract PDF text"))?;
+        // Fetch PDF bytes, giving up on the download when cancelled
+        let options = args.control.to_fetch_options();
+        let (pdf_bytes, info) = tokio::select! {
+            result = fetch::fetch_url_bytes(&self.state.fetcher, &url, &options) => {
+                result.map_err(tool_err(ErrorCode::Network, "Failed to fetch PDF"))?
+            }
+            _ = context.ct.cancelled() => {
+                tracing::info!("PDF download cancelled: {}", url);
+                return Err(ToolError::new(ErrorCode::Network, format!("Cancelled while downloading {}", url)));
+            }
+        };
+        if info.body_truncated {
+            return Err(ToolError::too_large(&url, self.state.fetcher.body_limit(&options)));
+        }
 
-        let result = serde_json::to_string_pretty(&pdf_info)
-            .map_err(internal_err("Failed to serialize PDF info"))?;
+        // Extract text off the async runtime, reporting each page and stopping when cancelled
+        let progress = progress::Progress::new(&context);
+        let reporter = progress.clone();
+        let ct = context.ct.clone();
+        let on_page = move |done: usize, total: usize| {
+            reporter.report(done, total, format!("Extracted page {} of {}", done, total));
+            !ct.is_cancelled()
+        };
+        let pdf_info = tokio::task::spawn_blocking(move || pdf::extract_pdf_text_with(&pdf_bytes, args.max_pages, on_page))
+            .await
+            .map_err(tool_err(ErrorCode::Internal, "PDF extraction task failed"))?
+            .map_err(tool_err(ErrorCode::Parse, "Failed to extract PDF text"))?;
+        progress.flush().await;
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
-        })
+        Ok(Json(pdf_info))
     }
 
     /// Get image information
     #[cfg(feature = "images")]
     #[tool(name = "fetch_image_info", description = "Get image format, dimensions, size, and metadata")]
-    async fn fetch_image_info(&self, Parameters(args): Parameters<FetchImageArgs>) -> Result<CallToolResult, McpError> {
+    async fn fetch_image_info(&self, Parameters(args): Parameters<FetchImageArgs>) -> Result<Json<image::ImageInfo>, ToolError> {
         // Validate URL
-        let url = validation::validate_url(&args.url)
-            .map_err(internal_err("URL validation failed"))?;
+        let url = self.validate_url(&args.url)?;
 
         // Fetch image bytes
-        let image_bytes = fetch::fetch_url_bytes(&self.state.client, &url)
+        let options = args.control.to_fetch_options();
+        let (image_bytes, info) = fetch::fetch_url_bytes(&self.state.fetcher, &url, &options)
             .await
-            .map_err(internal_err("Failed to fetch image"))?;
+            .map_err(tool_err(ErrorCode::Network, "Failed to fetch image"))?;
+        if info.body_truncated {
+            return Err(ToolError::too_large(&url, self.state.fetcher.body_limit(&options)));
+        }
 
         // Extract image info
         let image_info = image::extract_image_info(&image_bytes)
-            .map_err(internal_err("Failed to extract image info"))?;
+            .map_err(tool_err(ErrorCode::Parse, "Failed to extract image info"))?;
+
+        Ok(Json(image_info))
+    }
+}
+
+// ============================================================================
+// Prompts
+// ============================================================================
+
+#[prompt_router]
+impl FetchServer {
+    /// Fetch a URL as the user and return its contents
+    #[prompt(name = "fetch", description = "Fetch a URL and extract its contents as Markdown")]
+    async fn fetch_prompt(&self, Parameters(args): Parameters<FetchPromptArgs>) -> Result<GetPromptResult, McpError> {
+        let content = self.fetch_for_prompt(&args.url).await?;
+        Ok(GetPromptResult {
+            description: Some(format!("Contents of {}", args.url)),
+            messages: vec![PromptMessage::new_text(PromptMessageRole::User, page_context(&args.url, &content))],
+        })
+    }
 
-        let result = serde_json::to_string_pretty(&image_info)
-            .map_err(internal_err("Failed to serialize image info"))?;
+    /// Fetch a page and ask for a summary
+    #[prompt(name = "summarize_page", description = "Fetch a page and ask for a summary of it")]
+    async fn summarize_page(&self, Parameters(args): Parameters<SummarizePageArgs>) -> Result<GetPromptResult, McpError> {
+        let content = self.fetch_for_prompt(&args.url).await?;
 
-        Ok(CallToolResult {
-            content: vec![Content::text(result)],
-            structured_content: None,
-            is_error: None,
-            meta: None,
+        let mut request = "Summarize the page above: its main points, conclusions and anything actionable.".to_string();
+        if let Some(focus) = args.focus.as_deref().filter(|focus| !focus.trim().is_empty()) {
+            request.push_str(&format!(" Focus on: {}.", focus.trim()));
+        }
+
+        Ok(GetPromptResult {
+            description: Some(format!("Summarize {}", args.url)),
+            messages: vec![
+                PromptMessage::new_text(PromptMessageRole::User, page_context(&args.url, &content)),
+                PromptMessage::new_text(PromptMessageRole::User, request),
+            ],
         })
     }
+
+    /// Fetch two pages and ask for a comparison
+    #[prompt(name = "compare_pages", description = "Fetch two pages and ask for a comparison of them")]
+    async fn compare_pages(&self, Parameters(args): Parameters<ComparePagesArgs>) -> Result<GetPromptResult, McpError> {
+        let (content_a, content_b) = tokio::try_join!(
+            self.fetch_for_prompt(&args.url_a),
+            self.fetch_for_prompt(&args.url_b),
+        )?;
+
+        let aspect = args.aspect.as_deref()
+            .map(str::trim)
+            .filter(|aspect| !aspect.is_empty())
+            .unwrap_or("content, claims and coverage");
+        let request = format!(
+            "Compare the two pages above ({} and {}) in terms of {}: what they agree on, where they differ, and what only one of them covers.",
+            args.url_a, args.url_b, aspect
+        );
+
+        Ok(GetPromptResult {
+            description: Some(format!("Compare {} and {}", args.url_a, args.url_b)),
+            messages: vec![
+                PromptMessage::new_text(PromptMessageRole::User, page_context(&args.url_a, &content_a)),
+                PromptMessage::new_text(PromptMessageRole::User, page_context(&args.url_b, &content_b)),
+                PromptMessage::new_text(PromptMessageRole::User, request),
+            ],
+        })
+    }
+}
+
+// Prompt message carrying the fetched contents of a page
+fn page_context(url: &str, content: &str) -> String {
+    format!("Contents of {}:\n\n{}", url, content)
 }
 
 // Implement ServerHandler trait
 #[tool_handler]
+#[prompt_handler]
 impl ServerHandler for FetchServer {
     fn get_info(&self) -> ServerInfo {
         self.server_info()
     }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let documents = self.state.documents.list();
+        let resources = documents.iter().map(|document| {
+            RawResource {
+                uri: document.uri(),
+                name: document.url.clone(),
+                title: document.title().map(str::to_string),
+                description: Some(format!("Fetched {} ({} handler)", document.final_url, document.handler)),
+                mime_type: Some(document.mime_type().to_string()),
+                size: u32::try_from(document.content.len()).ok(),
+                icons: None,
+            }
+            .no_annotation()
+        });
+        Ok(ListResourcesResult::with_all_items(resources.collect()))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult::with_all_items(self.resource_templates()))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let parsed = resources::parse_uri(&request.uri)
+            .map_err(|err| McpError::invalid_params(err.to_string(), None))?;
+
+        let (document, stored) = match parsed.target {
+            resources::ResourceTarget::Cache(hash) => {
+                let document = self.state.documents.get(&hash).ok_or_else(|| {
+                    McpError::resource_not_found(
+                        format!("No fetched document for {}; read fetch://url/{{url}} to fetch it", request.uri),
+                        None,
+                    )
+                })?;
+                (document, true)
+            }
+            resources::ResourceTarget::Url(url) => {
+                self.check_fetch_enabled()?;
+                self.fetch_document(&url).await?
+            }
+        };
+        // Documents that were not stored are paged through their URL, fetching again
+        let base_uri = if stored { document.uri() } else { resources::url_uri(&document.url) };
+
+        let page = pagination::paginate(&document.content, parsed.start_index, parsed.max_length);
+        let mut meta = serde_json::Map::new();
+        meta.insert("url".to_string(), json!(document.url));
+        meta.insert("final_url".to_string(), json!(document.final_url));
+        meta.insert("handler".to_string(), json!(document.handler));
+        if stored {
+            meta.insert("resource_uri".to_string(), json!(document.uri()));
+        }
+        meta.insert("fetched_at".to_string(), json!(document.fetched_at));
+        meta.insert("total_length".to_string(), json!(page.total_length));
+        meta.insert("truncated".to_string(), json!(page.truncated));
+        if let Some(next) = page.next_start_index {
+            meta.insert("next_start_index".to_string(), json!(next));
+            meta.insert("next_uri".to_string(), json!(resources::page_uri(&base_uri, next, parsed.max_length)));
+        }
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                mime_type: Some(document.mime_type().to_string()),
+                text: page.content,
+                meta: Some(Meta(meta)),
+            }],
+        })
+    }
 }
 
 // ============================================================================
@@ -806,31 +1645,31 @@ async fn main() -> Result<()> {
     // Initialize logging (no stderr in stdio mode)
     logging::init_logging(cli.log_file.clone(), is_stream_mode);
 
+    // Load config file if given; command line flags override it
+    let mut config = cli
+        .config
+        .as_deref()
+        .map(config::Config::load)
+        .transpose()?
+        .unwrap_or_default();
+    cli.apply(&mut config);
+
     tracing::info!(
         "fetch-mcp-rs starting (mode: {}, robots.txt: {})",
         if is_stream_mode { "HTTP stream" } else { "stdio" },
-        if cli.ignore_robots_txt { "ignored" } else { "enabled" }
+        if config.robots.enabled { "enabled" } else { "ignored" }
     );
 
-    // Determine user agent
-    let user_agent = cli.user_agent.unwrap_or_else(|| {
-        if is_stream_mode {
-            fetch::DEFAULT_USER_AGENT_MANUAL.to_string()
-        } else {
-            fetch::DEFAULT_USER_AGENT_AUTONOMOUS.to_string()
-        }
-    });
-
-    tracing::debug!("User-Agent: {}", user_agent);
+    // Default user agent depends on the transport
+    let default_user_agent = if is_stream_mode {
+        fetch::DEFAULT_USER_AGENT_MANUAL
+    } else {
+        fetch::DEFAULT_USER_AGENT_AUTONOMOUS
+    };
 
     // Create server state
-    let state = Arc::new(ServerState::new(
-        user_agent,
-        cli.ignore_robots_txt,
-        cli.proxy_url.as_deref(),
-    )?);
-
-    let server = FetchServer::new(state);
+    let state = Arc::new(ServerState::new(config, cli.config.clone(), default_user_agent)?);
+    let server = FetchServer::new(state)?;
 
     // Run appropriate transport mode
     if is_stream_mode {
@@ -841,3 +1680,165 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::PromptMessageContent;
+
+    // Server that may fetch loopback test servers
+    fn test_server() -> FetchServer {
+        let mut config = config::Config::default();
+        config.network.allow_cidrs = vec!["127.0.0.0/8".to_string()];
+        let state = ServerState::new(config, None, fetch::DEFAULT_USER_AGENT_AUTONOMOUS).unwrap();
+        FetchServer::new(Arc::new(state)).unwrap()
+    }
+
+    // Site whose robots.txt refuses every crawler; pages show the user agent that fetched them
+    async fn serve_site() -> String {
+        test_util::serve(|request| {
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            if path == "/robots.txt" {
+                return test_util::response("200 OK", &[], b"User-agent: *\nDisallow: /\n");
+            }
+            let user_agent = request
+                .lines()
+                .find_map(|line| line.split_once(':').filter(|(name, _)| name.eq_ignore_ascii_case("user-agent")))
+                .map_or("", |(_, value)| value.trim());
+            let body = format!("<html><body><p>Page {} fetched by {}</p></body></html>", path, user_agent);
+            test_util::response("200 OK", &[("Content-Type", "text/html")], body.as_bytes())
+        })
+        .await
+    }
+
+    fn message_texts(result: &GetPromptResult) -> Vec<&str> {
+        result
+            .messages
+            .iter()
+            .map(|message| match &message.content {
+                PromptMessageContent::Text { text } => text.as_str(),
+                _ => panic!("Expected a text message"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_prompt_router() {
+        let server = test_server();
+        let mut prompts = server.prompt_router.list_all();
+        prompts.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<&str> = prompts.iter().map(|prompt| prompt.name.as_str()).collect();
+        assert_eq!(names, vec!["compare_pages", "fetch", "summarize_page"]);
+
+        let required = |prompt: &rmcp::model::Prompt| -> Vec<String> {
+            prompt.arguments.iter().flatten().filter(|arg| arg.required == Some(true)).map(|arg| arg.name.clone()).collect()
+        };
+        assert_eq!(required(&prompts[0]), vec!["url_a", "url_b"]);
+        assert_eq!(required(&prompts[1]), vec!["url"]);
+        assert_eq!(required(&prompts[2]), vec!["url"]);
+
+        // The router rejects arguments that don't deserialize
+        assert!(serde_json::from_value::<FetchPromptArgs>(json!({})).is_err());
+        assert!(serde_json::from_value::<SummarizePageArgs>(json!({ "focus": "pricing" })).is_err());
+        assert!(serde_json::from_value::<ComparePagesArgs>(json!({ "url_a": "https://example.com/" })).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_disabled() {
+        let base = serve_site().await;
+        let server = test_server();
+        let names = |server: &FetchServer| -> Vec<String> {
+            server.resource_templates().iter().map(|template| template.name.clone()).collect()
+        };
+        assert_eq!(names(&server), vec!["fetch_url", "fetched_document"]);
+
+        // Disabling the fetch tool withdraws the prompts and the fetch://url/ template
+        let mut config = config::Config::default();
+        config.network.allow_cidrs = vec!["127.0.0.0/8".to_string()];
+        config.tools.disabled = vec!["fetch".to_string()];
+        let state = ServerState::new(config, None, fetch::DEFAULT_USER_AGENT_AUTONOMOUS).unwrap();
+        let server = FetchServer::new(Arc::new(state)).unwrap();
+        assert!(server.prompt_router.list_all().is_empty());
+        assert_eq!(names(&server), vec!["fetched_document"]);
+
+        let err = server.fetch_prompt(Parameters(FetchPromptArgs { url: format!("{}/doc", base) })).await.unwrap_err();
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_REQUEST);
+        assert!(server.check_fetch_enabled().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_prompt() {
+        let base = serve_site().await;
+        let server = test_server();
+        let url = format!("{}/doc", base);
+
+        // Fetched as the user: manual user agent, robots.txt not consulted
+        let content = server.fetch_for_prompt(&url).await.unwrap();
+        assert!(content.contains("Page /doc"), "{}", content);
+        assert!(content.contains(fetch::DEFAULT_USER_AGENT_MANUAL), "{}", content);
+        let err = fetch::fetch_url_text(&server.state.fetcher, &url, &Default::default()).await.unwrap_err();
+        assert!(err.chain().any(|cause| cause.is::<robots::RobotsDenied>()));
+
+        let result = server.fetch_prompt(Parameters(FetchPromptArgs { url: url.clone() })).await.unwrap();
+        assert_eq!(result.description, Some(format!("Contents of {}", url)));
+        let texts = message_texts(&result);
+        assert_eq!(texts.len(), 1);
+        assert!(texts[0].starts_with(&format!("Contents of {}:\n\n", url)));
+        assert!(texts[0].contains("Page /doc"));
+
+        for url in ["not a url", "ftp://example.com/file", "http://169.254.169.254/latest"] {
+            let err = server.fetch_prompt(Parameters(FetchPromptArgs { url: url.to_string() })).await.unwrap_err();
+            assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS, "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summarize_page_prompt() {
+        let base = serve_site().await;
+        let server = test_server();
+        let url = format!("{}/post", base);
+
+        let args = SummarizePageArgs { url: url.clone(), focus: Some(" pricing ".to_string()) };
+        let result = server.summarize_page(Parameters(args)).await.unwrap();
+        assert_eq!(result.description, Some(format!("Summarize {}", url)));
+        let texts = message_texts(&result);
+        assert_eq!(texts.len(), 2);
+        assert!(texts[0].contains("Page /post"));
+        assert!(texts[1].starts_with("Summarize the page above"));
+        assert!(texts[1].ends_with("Focus on: pricing."));
+
+        let args = SummarizePageArgs { url: url.clone(), focus: Some("  ".to_string()) };
+        let result = server.summarize_page(Parameters(args)).await.unwrap();
+        assert!(!message_texts(&result)[1].contains("Focus on"));
+
+        let args = SummarizePageArgs { url: "javascript:alert(1)".to_string(), focus: None };
+        let err = server.summarize_page(Parameters(args)).await.unwrap_err();
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_compare_pages_prompt() {
+        let base = serve_site().await;
+        let server = test_server();
+        let (url_a, url_b) = (format!("{}/a", base), format!("{}/b", base));
+
+        let args = ComparePagesArgs { url_a: url_a.clone(), url_b: url_b.clone(), aspect: None };
+        let result = server.compare_pages(Parameters(args)).await.unwrap();
+        assert_eq!(result.description, Some(format!("Compare {} and {}", url_a, url_b)));
+        let texts = message_texts(&result);
+        assert_eq!(texts.len(), 3);
+        assert!(texts[0].starts_with(&format!("Contents of {}:", url_a)) && texts[0].contains("Page /a"));
+        assert!(texts[1].starts_with(&format!("Contents of {}:", url_b)) && texts[1].contains("Page /b"));
+        assert!(texts[2].contains(&url_a) && texts[2].contains(&url_b));
+        assert!(texts[2].contains("content, claims and coverage"));
+
+        let args = ComparePagesArgs { url_a: url_a.clone(), url_b: url_b.clone(), aspect: Some("tone".to_string()) };
+        let result = server.compare_pages(Parameters(args)).await.unwrap();
+        assert!(message_texts(&result)[2].contains("in terms of tone"));
+
+        // Either URL failing validation fails the prompt
+        let args = ComparePagesArgs { url_a, url_b: "file:///etc/passwd".to_string(), aspect: None };
+        let err = server.compare_pages(Parameters(args)).await.unwrap_err();
+        assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+    }
+}
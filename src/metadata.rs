@@ -2,6 +2,9 @@ use anyhow::Result;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
+use url::Url;
+
+use crate::structured_data::{self, Entity};
 
 /// Page metadata extracted from HTML
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -27,9 +30,36 @@ pub struct PageMetadata {
     /// Publication date
     pub published_date: Option<String>,
 
-    /// Canonical URL
+    /// Canonical URL (`<link rel="canonical">`)
     pub canonical_url: Option<String>,
 
+    /// Alternate-language versions (`<link rel="alternate" hreflang>`)
+    pub alternates: Vec<AlternateLink>,
+
+    /// AMP version (`<link rel="amphtml">`)
+    pub amp_url: Option<String>,
+
+    /// Previous page of a paginated series (`rel="prev"`)
+    pub prev_url: Option<String>,
+
+    /// Next page of a paginated series (`rel="next"`)
+    pub next_url: Option<String>,
+
+    /// Feeds advertised by the page (RSS, Atom, JSON Feed)
+    pub feeds: Vec<FeedLink>,
+
+    /// oEmbed endpoints describing the page
+    pub oembed: Vec<OEmbedLink>,
+
+    /// Web app manifest (`<link rel="manifest">`)
+    pub manifest_url: Option<String>,
+
+    /// Favicons and touch icons
+    pub icons: Vec<IconLink>,
+
+    /// Identity links (`rel="me"`)
+    pub me_links: Vec<String>,
+
     /// Page language
     pub language: Option<String>,
 
@@ -38,6 +68,164 @@ pub struct PageMetadata {
 
     /// Twitter card type
     pub twitter_card: Option<String>,
+
+    /// Schema.org entities from JSON-LD, Microdata and RDFa
+    pub structured_data: Vec<Entity>,
+}
+
+/// Alternate-language version of a page
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct AlternateLink {
+    /// Language code, or `x-default`
+    pub hreflang: String,
+
+    /// URL of the version
+    pub href: String,
+}
+
+/// Feed format
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedKind {
+    Rss,
+    Atom,
+    Json,
+}
+
+/// Feed autodiscovery link
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct FeedLink {
+    /// Feed URL
+    pub href: String,
+
+    /// Feed format
+    pub kind: FeedKind,
+
+    /// Feed title
+    pub title: Option<String>,
+}
+
+/// oEmbed discovery link
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct OEmbedLink {
+    /// Endpoint URL, including the page URL
+    pub href: String,
+
+    /// Response format (json or xml)
+    pub format: String,
+
+    /// Link title
+    pub title: Option<String>,
+}
+
+/// Favicon or touch icon
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct IconLink {
+    /// Icon URL
+    pub href: String,
+
+    /// Link relation (icon, apple-touch-icon, mask-icon, ...)
+    pub rel: String,
+
+    /// Declared sizes (e.g. "32x32", "any")
+    pub sizes: Vec<String>,
+
+    /// Declared MIME type
+    pub content_type: Option<String>,
+}
+
+/// Links discovered through `<link rel>` (and `<a rel>` for prev, next and me)
+#[derive(Debug, Default)]
+struct RelLinks {
+    canonical_url: Option<String>,
+    alternates: Vec<AlternateLink>,
+    amp_url: Option<String>,
+    prev_url: Option<String>,
+    next_url: Option<String>,
+    feeds: Vec<FeedLink>,
+    oembed: Vec<OEmbedLink>,
+    manifest_url: Option<String>,
+    icons: Vec<IconLink>,
+    me_links: Vec<String>,
+}
+
+/// URL relative links resolve against: `<base href>`, or the page `url`
+fn base_url(document: &Html, url: &str) -> Option<Url> {
+    let page = Url::parse(url).ok()?;
+    let base = Selector::parse("base[href]").ok()
+        .and_then(|sel| document.select(&sel).next())
+        .and_then(|el| el.value().attr("href"))
+        .and_then(|href| page.join(href.trim()).ok());
+    Some(base.unwrap_or(page))
+}
+
+/// Collect link relations, resolving hrefs against `base`
+fn extract_rel_links(document: &Html, base: Option<&Url>) -> RelLinks {
+    let mut links = RelLinks::default();
+    let Some(base) = base else { return links };
+
+    let Ok(selector) = Selector::parse("link[rel][href], a[rel][href]") else { return links };
+    for el in document.select(&selector) {
+        let el = el.value();
+        let Some(href) = el.attr("href").and_then(|href| base.join(href.trim()).ok()).map(String::from) else {
+            continue;
+        };
+        let rels: Vec<String> = el.attr("rel").unwrap_or_default().split_ascii_whitespace().map(str::to_ascii_lowercase).collect();
+        let has = |rel: &str| rels.iter().any(|token| token == rel);
+        let attr = |name: &str| el.attr(name).map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+        let content_type = attr("type").map(|value| value.to_ascii_lowercase());
+
+        // Anchors only carry navigation and identity relations
+        if el.name() == "link" {
+            if has("canonical") && links.canonical_url.is_none() {
+                links.canonical_url = Some(href.clone());
+            }
+            if has("amphtml") && links.amp_url.is_none() {
+                links.amp_url = Some(href.clone());
+            }
+            if has("manifest") && links.manifest_url.is_none() {
+                links.manifest_url = Some(href.clone());
+            }
+            if has("alternate") {
+                let kind = match content_type.as_deref() {
+                    Some("application/rss+xml") => Some(FeedKind::Rss),
+                    Some("application/atom+xml") => Some(FeedKind::Atom),
+                    Some("application/feed+json") => Some(FeedKind::Json),
+                    _ => None,
+                };
+                let oembed = match content_type.as_deref() {
+                    Some("application/json+oembed") => Some("json"),
+                    Some("text/xml+oembed" | "application/xml+oembed") => Some("xml"),
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    links.feeds.push(FeedLink { href: href.clone(), kind, title: attr("title") });
+                } else if let Some(format) = oembed {
+                    links.oembed.push(OEmbedLink { href: href.clone(), format: format.to_string(), title: attr("title") });
+                } else if let Some(hreflang) = attr("hreflang") {
+                    links.alternates.push(AlternateLink { hreflang, href: href.clone() });
+                }
+            }
+            if let Some(rel) = rels.iter().find(|rel| matches!(rel.as_str(), "icon" | "apple-touch-icon" | "apple-touch-icon-precomposed" | "mask-icon")) {
+                links.icons.push(IconLink {
+                    href: href.clone(),
+                    rel: rel.clone(),
+                    sizes: attr("sizes").map(|sizes| sizes.split_ascii_whitespace().map(str::to_ascii_lowercase).collect()).unwrap_or_default(),
+                    content_type: content_type.clone(),
+                });
+            }
+        }
+        if (has("prev") || has("previous")) && links.prev_url.is_none() {
+            links.prev_url = Some(href.clone());
+        }
+        if has("next") && links.next_url.is_none() {
+            links.next_url = Some(href.clone());
+        }
+        if has("me") && !links.me_links.contains(&href) {
+            links.me_links.push(href);
+        }
+    }
+    links
 }
 
 /// Extract metadata from HTML content using scraper
@@ -79,6 +267,9 @@ pub fn extract_metadata(html: &str, url: &str) -> Result<PageMetadata> {
         .and_then(|el| el.value().attr("lang"))
         .map(|s| s.to_string());
 
+    let base = base_url(&document, url);
+    let links = extract_rel_links(&document, base.as_ref());
+
     Ok(PageMetadata {
         title,
         description: get_meta_name("description"),
@@ -88,10 +279,20 @@ pub fn extract_metadata(html: &str, url: &str) -> Result<PageMetadata> {
         author: get_meta_name("author"),
         published_date: get_meta_name("article:published_time")
             .or_else(|| get_meta_property("article:published_time")),
-        canonical_url: Some(url.to_string()),
+        canonical_url: links.canonical_url,
+        alternates: links.alternates,
+        amp_url: links.amp_url,
+        prev_url: links.prev_url,
+        next_url: links.next_url,
+        feeds: links.feeds,
+        oembed: links.oembed,
+        manifest_url: links.manifest_url,
+        icons: links.icons,
+        me_links: links.me_links,
         language,
         keywords,
         twitter_card: get_meta_name("twitter:card"),
+        structured_data: structured_data::extract_structured_data(&document, base.as_ref()),
     })
 }
 
@@ -126,4 +327,65 @@ mod tests {
         assert_eq!(metadata.description, Some("This is a test page".to_string()));
         assert_eq!(metadata.author, Some("John Doe".to_string()));
     }
+
+    #[test]
+    fn test_link_relations() {
+        let html = r#"
+            <html><head>
+                <base href="https://cdn.example.com/blog/">
+                <link rel="canonical" href="https://example.com/post">
+                <link rel="alternate" hreflang="de" href="/de/post">
+                <link rel="alternate" hreflang="x-default" href="https://example.com/post">
+                <link rel="amphtml" href="post.amp">
+                <link rel="prev" href="?page=1">
+                <link rel="next" href="?page=3">
+                <link rel="alternate" type="application/rss+xml" title="Posts" href="/feed.xml">
+                <link rel="alternate" type="application/atom+xml" href="/atom.xml">
+                <link rel="alternate" type="application/feed+json" href="/feed.json">
+                <link rel="alternate" type="application/json" href="/wp-json/wp/v2/posts/1">
+                <link rel="alternate" type="application/json+oembed" href="https://example.com/oembed?url=x&amp;format=json">
+                <link rel="manifest" href="/site.webmanifest">
+                <link rel="shortcut icon" href="/favicon.ico">
+                <link rel="icon" type="image/png" sizes="32x32 16X16" href="/icon-32.png">
+                <link rel="apple-touch-icon" sizes="180x180" href="/apple-touch-icon.png">
+                <link rel="me" href="https://mastodon.example/@me">
+            </head><body>
+                <a rel="me noopener" href="https://github.com/me">GitHub</a>
+                <a rel="me" href="https://mastodon.example/@me">Mastodon</a>
+            </body></html>
+        "#;
+
+        let metadata = extract_metadata(html, "https://example.com/post?page=2").unwrap();
+        assert_eq!(metadata.canonical_url.as_deref(), Some("https://example.com/post"));
+        assert_eq!(
+            metadata.alternates,
+            vec![
+                AlternateLink { hreflang: "de".to_string(), href: "https://cdn.example.com/de/post".to_string() },
+                AlternateLink { hreflang: "x-default".to_string(), href: "https://example.com/post".to_string() },
+            ]
+        );
+        assert_eq!(metadata.amp_url.as_deref(), Some("https://cdn.example.com/blog/post.amp"));
+        assert_eq!(metadata.prev_url.as_deref(), Some("https://cdn.example.com/blog/?page=1"));
+        assert_eq!(metadata.next_url.as_deref(), Some("https://cdn.example.com/blog/?page=3"));
+
+        let kinds: Vec<FeedKind> = metadata.feeds.iter().map(|feed| feed.kind).collect();
+        assert_eq!(kinds, vec![FeedKind::Rss, FeedKind::Atom, FeedKind::Json]);
+        assert_eq!(metadata.feeds[2].href, "https://cdn.example.com/feed.json");
+        assert_eq!(metadata.feeds[0].title.as_deref(), Some("Posts"));
+        assert_eq!(metadata.oembed[0].href, "https://example.com/oembed?url=x&format=json");
+        assert_eq!(metadata.oembed[0].format, "json");
+        assert_eq!(metadata.manifest_url.as_deref(), Some("https://cdn.example.com/site.webmanifest"));
+
+        assert_eq!(metadata.icons.len(), 3);
+        assert_eq!(metadata.icons[0].rel, "icon");
+        assert_eq!(metadata.icons[1].sizes, vec!["32x32", "16x16"]);
+        assert_eq!(metadata.icons[1].content_type.as_deref(), Some("image/png"));
+        assert_eq!(metadata.icons[2].rel, "apple-touch-icon");
+        assert_eq!(metadata.me_links, vec!["https://mastodon.example/@me", "https://github.com/me"]);
+
+        // Without a canonical link, no canonical URL is made up
+        let metadata = extract_metadata("<html><head><title>T</title></head></html>", "https://example.com/").unwrap();
+        assert_eq!(metadata.canonical_url, None);
+        assert!(metadata.feeds.is_empty() && metadata.icons.is_empty());
+    }
 }
@@ -0,0 +1,178 @@
+//! Structured output of the MCP tools
+//!
+//! Each tool returns one of these as `structured_content` and advertises its
+//! schema as `outputSchema`. MCP requires object outputs, so list results are
+//! wrapped in a named field.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::fetch::ResponseInfo;
+use crate::links::LinkInfo;
+use crate::pagination::Page;
+use crate::pdf::PdfMetadata;
+use crate::reddit::RedditPost;
+use crate::selector::{ElementData, TableData};
+use crate::wiki::{WikiArticle, WikiSearchResult};
+
+/// Output of the `fetch` tool
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FetchOutput {
+    /// Requested URL
+    pub url: String,
+
+    /// Handler that converted the body (html, pdf, feed, json, image, text, raw)
+    pub handler: String,
+
+    /// Requested page of the converted content
+    #[serde(flatten)]
+    pub page: Page,
+
+    /// Body returned without conversion
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub raw: bool,
+
+    /// Page count and metadata of PDF documents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pdf: Option<PdfDetails>,
+
+    /// Resource URI for re-reading the document (`fetch://cache/<hash>`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_uri: Option<String>,
+
+    /// Response status, final URL, headers and encoding
+    #[serde(flatten)]
+    pub response: ResponseInfo,
+
+    /// Body size limit, when the body was cut off at it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+}
+
+/// PDF details reported by the `fetch` tool
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PdfDetails {
+    /// Number of pages
+    pub num_pages: usize,
+
+    /// PDF metadata
+    pub metadata: PdfMetadata,
+}
+
+/// Data extracted from a single fetched page
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Extracted<T> {
+    #[serde(flatten)]
+    pub data: T,
+
+    /// Response body was cut off at `max_bytes`, so the data may be incomplete
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub body_truncated: bool,
+
+    /// Body size limit, when the body was cut off at it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+}
+
+impl<T> Extracted<T> {
+    pub fn new(data: T, info: &ResponseInfo, max_bytes: u64) -> Self {
+        Self {
+            data,
+            body_truncated: info.body_truncated,
+            max_bytes: info.body_truncated.then_some(max_bytes),
+        }
+    }
+}
+
+/// Elements matched by a CSS selector
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ElementList {
+    /// Matched elements in document order
+    pub elements: Vec<ElementData>,
+}
+
+/// Tables found on a page
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TableList {
+    /// Tables in document order
+    pub tables: Vec<TableData>,
+}
+
+/// Links found on a page
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct LinkList {
+    /// Links in document order
+    pub links: Vec<LinkInfo>,
+}
+
+/// Output of the `reddit` tool
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RedditOutput {
+    /// Matching posts
+    pub posts: Vec<RedditPost>,
+}
+
+/// Output of the `wiki` tool: search results or an article, depending on the action
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct WikiOutput {
+    /// Search results (`search` action)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<Vec<WikiSearchResult>>,
+
+    /// Article (`summary`, `content` and `random` actions)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub article: Option<WikiArticle>,
+}
+
+/// Output of the `server_config` tool
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ServerConfigOutput {
+    /// Config file the settings were loaded from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<String>,
+
+    /// User agent of tool requests
+    pub user_agent: String,
+
+    /// Effective settings: the config file with command line overrides applied
+    pub config: serde_json::Value,
+
+    /// Tools offered to clients
+    pub tools: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::handler::server::tool::schema_for_type;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_extracted_output() {
+        let mut info = ResponseInfo {
+            status: 200,
+            final_url: "https://example.com/".to_string(),
+            headers: BTreeMap::new(),
+            encoding: None,
+            body_truncated: false,
+            attempts: 1,
+            last_error: None,
+        };
+
+        let output = Extracted::new(TableList { tables: Vec::new() }, &info, 1024);
+        assert_eq!(serde_json::to_value(&output).unwrap(), serde_json::json!({ "tables": [] }));
+
+        info.body_truncated = true;
+        let output = Extracted::new(TableList { tables: Vec::new() }, &info, 1024);
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            serde_json::json!({ "tables": [], "body_truncated": true, "max_bytes": 1024 })
+        );
+
+        // MCP output schemas must describe objects
+        let schema = schema_for_type::<Extracted<TableList>>();
+        assert_eq!(schema["type"], "object");
+        assert!(schema["properties"]["tables"].is_object());
+        assert_eq!(schema_for_type::<FetchOutput>()["type"], "object");
+    }
+}
@@ -0,0 +1,140 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One page of a longer document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Page {
+    /// Page content
+    pub content: String,
+
+    /// Length of the whole document in characters
+    pub total_length: usize,
+
+    /// Character index to pass as `start_index` to read the next page
+    pub next_start_index: Option<usize>,
+
+    /// More content remains after this page
+    pub truncated: bool,
+}
+
+/// Cut a page out of `text`, measuring `start_index` and `max_length` in characters
+///
+/// When more content remains, the page end is moved back to the last heading or
+/// paragraph break (then line break, then space) in the second half of the page,
+/// so pages don't end mid-sentence. Without `max_length` the rest of the text is returned.
+pub fn paginate(text: &str, start_index: usize, max_length: Option<usize>) -> Page {
+    let total_length = text.chars().count();
+    let rest = &text[byte_offset(text, start_index)..];
+
+    let max_length = match max_length {
+        Some(max) if start_index.saturating_add(max) < total_length => max.max(1),
+        _ => {
+            return Page {
+                content: rest.to_string(),
+                total_length,
+                next_start_index: None,
+                truncated: false,
+            };
+        }
+    };
+
+    let window = &rest[..byte_offset(rest, max_length)];
+    let cut = snap_to_boundary(window, byte_offset(window, max_length / 2));
+    let content = &window[..cut];
+    let next_start_index = start_index + content.chars().count();
+
+    Page {
+        content: content.to_string(),
+        total_length,
+        next_start_index: Some(next_start_index),
+        truncated: true,
+    }
+}
+
+/// Byte offset of the character at `char_index` (or the end of the string)
+fn byte_offset(text: &str, char_index: usize) -> usize {
+    text.char_indices().nth(char_index).map_or(text.len(), |(offset, _)| offset)
+}
+
+/// Best place to end a page, searching no earlier than `min` bytes into `window`
+fn snap_to_boundary(window: &str, min: usize) -> usize {
+    let tail = &window[min..];
+
+    let block = [
+        tail.rfind("\n\n").map(|i| i + 2),
+        tail.rfind("\n#").map(|i| i + 1),
+    ]
+    .into_iter()
+    .flatten()
+    .max();
+
+    let cut = block
+        .or_else(|| tail.rfind('\n').map(|i| i + 1))
+        .or_else(|| tail.rfind(' ').map(|i| i + 1));
+
+    match cut {
+        Some(cut) if min + cut > 0 => min + cut,
+        _ => window.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate_multibyte() {
+        let text = "日本語のテキスト".repeat(10);
+        let page = paginate(&text, 3, Some(5));
+        assert_eq!(page.content, "のテキスト");
+        assert_eq!(page.total_length, 80);
+        assert_eq!(page.next_start_index, Some(8));
+        assert!(page.truncated);
+
+        // Past the end is empty, not a panic
+        let page = paginate(&text, 500, Some(5));
+        assert_eq!(page.content, "");
+        assert!(!page.truncated);
+
+        // Offsets near usize::MAX don't overflow
+        let page = paginate(&text, usize::MAX, Some(5));
+        assert_eq!(page.content, "");
+        assert!(!page.truncated);
+        let page = paginate(&text, 3, Some(usize::MAX));
+        assert_eq!(page.content.chars().count(), 77);
+        assert_eq!(page.next_start_index, None);
+    }
+
+    #[test]
+    fn test_paginate_snaps_to_paragraphs() {
+        let text = "# Title\n\nFirst paragraph here.\n\n## Section\n\nSecond paragraph is longer.";
+        let page = paginate(text, 0, Some(40));
+        assert_eq!(page.content, "# Title\n\nFirst paragraph here.\n\n");
+        assert!(page.truncated);
+
+        let next = paginate(text, page.next_start_index.unwrap(), Some(40));
+        assert!(next.content.starts_with("## Section"));
+        assert_eq!(next.next_start_index, None);
+        assert!(!next.truncated);
+    }
+
+    #[test]
+    fn test_paginate_reads_whole_document() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta iota kappa\nlambda mu";
+        let mut start = 0;
+        let mut joined = String::new();
+        loop {
+            let page = paginate(text, start, Some(12));
+            joined.push_str(&page.content);
+            match page.next_start_index {
+                Some(next) => start = next,
+                None => break,
+            }
+        }
+        assert_eq!(joined, text);
+
+        let page = paginate(text, 0, None);
+        assert_eq!(page.content, text);
+        assert_eq!(page.next_start_index, None);
+    }
+}
@@ -16,6 +16,10 @@ pub struct PdfInfo {
 
     /// PDF metadata
     pub metadata: PdfMetadata,
+
+    /// Extraction was cancelled; `text` only covers the pages done before that
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
 }
 
 /// PDF metadata
@@ -44,8 +48,19 @@ pub struct PdfMetadata {
 }
 
 /// Extract text from PDF bytes
-#[cfg(feature = "pdf")]
 pub fn extract_pdf_text(pdf_bytes: &[u8], max_pages: Option<usize>) -> Result<PdfInfo> {
+    extract_pdf_text_with(pdf_bytes, max_pages, |_, _| true)
+}
+
+/// Like [`extract_pdf_text`], calling `on_page(done, total)` after each page
+///
+/// Extraction stops early, returning the text so far, when `on_page` returns false.
+#[cfg(feature = "pdf")]
+pub fn extract_pdf_text_with(
+    pdf_bytes: &[u8],
+    max_pages: Option<usize>,
+    mut on_page: impl FnMut(usize, usize) -> bool,
+) -> Result<PdfInfo> {
     let doc = Document::load_mem(pdf_bytes).context("Failed to load PDF document")?;
 
     let num_pages = doc.get_pages().len();
@@ -53,6 +68,7 @@ pub fn extract_pdf_text(pdf_bytes: &[u8], max_pages: Option<usize>) -> Result<Pd
 
     // Extract text from specified number of pages
     let mut all_text = String::new();
+    let mut cancelled = false;
 
     for page_num in 1..=pages_to_extract {
         match extract_page_text(&doc, page_num as u32) {
@@ -64,6 +80,12 @@ pub fn extract_pdf_text(pdf_bytes: &[u8], max_pages: Option<usize>) -> Result<Pd
                 tracing::warn!("Failed to extract text from page {}: {}", page_num, e);
             }
         }
+
+        if !on_page(page_num, pages_to_extract) && page_num < pages_to_extract {
+            tracing::info!("PDF extraction cancelled after {} of {} pages", page_num, pages_to_extract);
+            cancelled = true;
+            break;
+        }
     }
 
     // Extract metadata
@@ -73,6 +95,7 @@ pub fn extract_pdf_text(pdf_bytes: &[u8], max_pages: Option<usize>) -> Result<Pd
         text: all_text.trim().to_string(),
         num_pages,
         metadata,
+        cancelled,
     })
 }
 
@@ -184,7 +207,11 @@ fn extract_pdf_metadata(doc: &Document) -> PdfMetadata {
 
 /// Fallback implementation when PDF feature is disabled
 #[cfg(not(feature = "pdf"))]
-pub fn extract_pdf_text(_pdf_bytes: &[u8], _max_pages: Option<usize>) -> Result<PdfInfo> {
+pub fn extract_pdf_text_with(
+    _pdf_bytes: &[u8],
+    _max_pages: Option<usize>,
+    _on_page: impl FnMut(usize, usize) -> bool,
+) -> Result<PdfInfo> {
     anyhow::bail!("PDF support is not enabled. Rebuild with --features pdf")
 }
 
@@ -206,4 +233,59 @@ mod tests {
         let output = decode_pdf_hex(hex);
         assert_eq!(output, Some("Hello".to_string()));
     }
+
+    // Minimal PDF with one line of text per page
+    fn sample_pdf(pages: &[&str]) -> Vec<u8> {
+        use lopdf::{dictionary, Object, Stream};
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let mut kids = Vec::new();
+        for text in pages {
+            let content = format!("BT\n/F1 12 Tf\n({}) Tj\nET", text);
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            });
+            kids.push(Object::Reference(page_id));
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => pages.len() as i64,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_extract_pages_with_progress() {
+        let pdf = sample_pdf(&["first", "second", "third"]);
+
+        let mut reported = Vec::new();
+        let info = extract_pdf_text_with(&pdf, None, |done, total| {
+            reported.push((done, total));
+            true
+        })
+        .unwrap();
+        assert_eq!(info.num_pages, 3);
+        assert_eq!(info.text, "first\n\nsecond\n\nthird");
+        assert!(!info.cancelled);
+        assert_eq!(reported, [(1, 3), (2, 3), (3, 3)]);
+
+        // Stopping after the first page returns its text only
+        let info = extract_pdf_text_with(&pdf, None, |done, _| done < 1).unwrap();
+        assert_eq!(info.text, "first");
+        assert!(info.cancelled);
+    }
 }
@@ -0,0 +1,70 @@
+//! `notifications/progress` for long-running tools
+
+use rmcp::model::{ProgressNotificationParam, ProgressToken};
+use rmcp::service::RequestContext;
+use rmcp::RoleServer;
+use tokio::sync::{mpsc, oneshot};
+
+enum Message {
+    Notify(ProgressNotificationParam),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Progress reporter for one request
+///
+/// Does nothing unless the caller sent a `progressToken`. Notifications are
+/// queued and sent in order by a background task, so reporting never waits on
+/// the transport; [`Progress::flush`] before responding so none arrive after it.
+#[derive(Clone)]
+pub struct Progress {
+    sender: Option<(ProgressToken, mpsc::UnboundedSender<Message>)>,
+}
+
+impl Progress {
+    pub fn new(context: &RequestContext<RoleServer>) -> Self {
+        let Some(token) = context.meta.get_progress_token() else {
+            return Self { sender: None };
+        };
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let peer = context.peer.clone();
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                match message {
+                    Message::Notify(param) => {
+                        if let Err(e) = peer.notify_progress(param).await {
+                            tracing::debug!("Failed to send progress notification: {}", e);
+                        }
+                    }
+                    Message::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+
+        Self { sender: Some((token, sender)) }
+    }
+
+    /// Report `progress` of `total` items done
+    pub fn report(&self, progress: usize, total: usize, message: impl Into<String>) {
+        if let Some((token, sender)) = &self.sender {
+            let _ = sender.send(Message::Notify(ProgressNotificationParam {
+                progress_token: token.clone(),
+                progress: progress as f64,
+                total: Some(total as f64),
+                message: Some(message.into()),
+            }));
+        }
+    }
+
+    /// Wait until every notification reported so far has been sent
+    pub async fn flush(&self) {
+        if let Some((_, sender)) = &self.sender {
+            let (done, sent) = oneshot::channel();
+            if sender.send(Message::Flush(done)).is_ok() {
+                let _ = sent.await;
+            }
+        }
+    }
+}
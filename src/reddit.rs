@@ -2,6 +2,8 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
 
+use crate::fetch::Fetcher;
+
 /// Reddit comment
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RedditComment {
@@ -98,7 +100,7 @@ impl Default for RedditOptions {
 
 /// Fetch posts from Reddit
 pub async fn fetch_reddit_posts(
-    client: &reqwest::Client,
+    fetcher: &Fetcher,
     query: Option<&str>,
     options: RedditOptions,
 ) -> Result<Vec<RedditPost>> {
@@ -129,10 +131,8 @@ pub async fn fetch_reddit_posts(
 
     tracing::debug!("Fetching Reddit: {}", url);
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "fetch-mcp-rs/0.1.0")
-        .send()
+    let response = fetcher
+        .send(fetcher.client.get(&url).header("User-Agent", "fetch-mcp-rs/0.1.0"), &url, None)
         .await
         .context("Failed to fetch from Reddit")?;
 
@@ -167,7 +167,7 @@ pub async fn fetch_reddit_posts(
         let is_nsfw = data["over_18"].as_bool().unwrap_or(false);
 
         let comments = if options.include_comments && num_comments > 0 {
-            fetch_reddit_comments(client, &permalink, options.max_comments)
+            fetch_reddit_comments(fetcher, &permalink, options.max_comments)
                 .await
                 .ok()
         } else {
@@ -195,16 +195,14 @@ pub async fn fetch_reddit_posts(
 
 /// Fetch comments for a specific post
 async fn fetch_reddit_comments(
-    client: &reqwest::Client,
+    fetcher: &Fetcher,
     permalink: &str,
     max_comments: usize,
 ) -> Result<Vec<RedditComment>> {
     let url = format!("{}.json?limit={}", permalink, max_comments);
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "fetch-mcp-rs/0.1.0")
-        .send()
+    let response = fetcher
+        .send(fetcher.client.get(&url).header("User-Agent", "fetch-mcp-rs/0.1.0"), &url, None)
         .await
         .context("Failed to fetch comments")?;
 
@@ -250,7 +248,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_reddit_posts() {
-        let client = reqwest::Client::new();
+        let fetcher = Fetcher::new(reqwest::Client::new(), None);
 
         let options = RedditOptions {
             subreddit: "rust".to_string(),
@@ -259,7 +257,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = fetch_reddit_posts(&client, None, options).await;
+        let result = fetch_reddit_posts(&fetcher, None, options).await;
         assert!(result.is_ok());
 
         let posts = result.unwrap();
@@ -269,7 +267,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_reddit_search() {
-        let client = reqwest::Client::new();
+        let fetcher = Fetcher::new(reqwest::Client::new(), None);
 
         let options = RedditOptions {
             subreddit: "programming".to_string(),
@@ -277,7 +275,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = fetch_reddit_posts(&client, Some("rust"), options).await;
+        let result = fetch_reddit_posts(&fetcher, Some("rust"), options).await;
         assert!(result.is_ok());
     }
 }
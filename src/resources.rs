@@ -0,0 +1,274 @@
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// URI prefix of stored documents (`fetch://cache/<hash>`)
+pub const CACHE_URI_PREFIX: &str = "fetch://cache/";
+
+/// URI prefix that fetches a URL on read (`fetch://url/<percent-encoded url>`)
+pub const URL_URI_PREFIX: &str = "fetch://url/";
+
+/// Default number of documents kept for `resources/read`
+pub const DEFAULT_MAX_DOCUMENTS: usize = 100;
+
+/// Default total size of kept documents (50 MiB)
+pub const DEFAULT_MAX_DOCUMENT_BYTES: usize = 50 * 1024 * 1024;
+
+/// Converted output of a fetch, kept so clients can re-read it as a resource
+#[derive(Debug, Clone)]
+pub struct StoredDocument {
+    /// Requested URL
+    pub url: String,
+
+    /// Final URL after redirects
+    pub final_url: String,
+
+    /// Handler that produced the content (html, pdf, feed, ...)
+    pub handler: String,
+
+    /// Converted content (Markdown for HTML)
+    pub content: String,
+
+    /// Unix time (seconds) when the document was fetched
+    pub fetched_at: u64,
+}
+
+impl StoredDocument {
+    /// Resource URI of this document
+    pub fn uri(&self) -> String {
+        cache_uri(&self.url)
+    }
+
+    /// Document title: the leading `# heading` of Markdown output
+    pub fn title(&self) -> Option<&str> {
+        let first = self.content.lines().next()?;
+        first.strip_prefix("# ").map(str::trim).filter(|title| !title.is_empty())
+    }
+
+    /// MIME type of the converted content
+    pub fn mime_type(&self) -> &'static str {
+        match self.handler.as_str() {
+            "html" => "text/markdown",
+            "feed" | "json" | "image" => "application/json",
+            _ => "text/plain",
+        }
+    }
+}
+
+/// Bounded in-memory store of fetched documents, newest first
+pub struct DocumentStore {
+    documents: Mutex<VecDeque<Arc<StoredDocument>>>,
+    max_documents: usize,
+    max_bytes: usize,
+}
+
+impl DocumentStore {
+    pub fn new(max_documents: usize, max_bytes: usize) -> Self {
+        Self {
+            documents: Mutex::new(VecDeque::new()),
+            max_documents,
+            max_bytes,
+        }
+    }
+
+    /// Store a document, replacing an older fetch of the same URL and evicting the oldest ones
+    pub fn insert(&self, document: StoredDocument) -> Arc<StoredDocument> {
+        let document = Arc::new(document);
+        let Ok(mut documents) = self.documents.lock() else {
+            return document;
+        };
+
+        documents.retain(|existing| existing.url != document.url);
+        documents.push_front(document.clone());
+
+        let mut total: usize = documents.iter().map(|doc| doc.content.len()).sum();
+        while documents.len() > 1 && (documents.len() > self.max_documents || total > self.max_bytes) {
+            if let Some(evicted) = documents.pop_back() {
+                total -= evicted.content.len();
+            }
+        }
+        document
+    }
+
+    /// Look up a document by the hash in its `fetch://cache/<hash>` URI
+    pub fn get(&self, hash: &str) -> Option<Arc<StoredDocument>> {
+        let documents = self.documents.lock().ok()?;
+        documents.iter().find(|doc| url_hash(&doc.url) == hash).cloned()
+    }
+
+    /// All stored documents, most recently fetched first
+    pub fn list(&self) -> Vec<Arc<StoredDocument>> {
+        self.documents
+            .lock()
+            .map(|documents| documents.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for DocumentStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DOCUMENTS, DEFAULT_MAX_DOCUMENT_BYTES)
+    }
+}
+
+/// What a resource URI points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceTarget {
+    /// Stored document by URL hash
+    Cache(String),
+    /// URL to fetch
+    Url(String),
+}
+
+/// Parsed resource URI with optional paging parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceRequest {
+    pub target: ResourceTarget,
+    pub start_index: usize,
+    pub max_length: Option<usize>,
+}
+
+/// Hash identifying a URL in `fetch://cache/<hash>`
+pub fn url_hash(url: &str) -> String {
+    let digest = Sha256::digest(url.as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Resource URI of a fetched URL
+pub fn cache_uri(url: &str) -> String {
+    format!("{}{}", CACHE_URI_PREFIX, url_hash(url))
+}
+
+/// Resource URI that fetches a URL on read
+pub fn url_uri(url: &str) -> String {
+    format!("{}{}", URL_URI_PREFIX, urlencoding::encode(url))
+}
+
+/// URI of one page of a resource
+pub fn page_uri(base_uri: &str, start_index: usize, max_length: Option<usize>) -> String {
+    let mut params = vec![format!("start_index={}", start_index)];
+    if let Some(max_length) = max_length {
+        params.push(format!("max_length={}", max_length));
+    }
+    format!("{}?{}", base_uri, params.join("&"))
+}
+
+/// Parse `fetch://cache/<hash>` or `fetch://url/<url>`, with `?start_index=&max_length=`
+///
+/// The URL in `fetch://url/` is percent-encoded, as RFC 6570 template expansion
+/// produces. An unencoded URL is also accepted, but then the whole rest of the
+/// URI is taken as the URL and no paging parameters are read.
+pub fn parse_uri(uri: &str) -> Result<ResourceRequest> {
+    let (target, query) = if let Some(rest) = uri.strip_prefix(CACHE_URI_PREFIX) {
+        let (hash, query) = split_query(rest);
+        if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            bail!("Invalid document hash in {}", uri);
+        }
+        (ResourceTarget::Cache(hash.to_ascii_lowercase()), query)
+    } else if let Some(rest) = uri.strip_prefix(URL_URI_PREFIX) {
+        if rest.contains("://") {
+            (ResourceTarget::Url(rest.to_string()), None)
+        } else {
+            let (encoded, query) = split_query(rest);
+            let url = urlencoding::decode(encoded).context("URL is not valid UTF-8")?;
+            (ResourceTarget::Url(url.into_owned()), query)
+        }
+    } else {
+        bail!("Unknown resource URI: {}", uri);
+    };
+
+    let mut request = ResourceRequest {
+        target,
+        start_index: 0,
+        max_length: None,
+    };
+
+    for (name, value) in url::form_urlencoded::parse(query.unwrap_or("").as_bytes()) {
+        let number = || value.parse::<usize>().with_context(|| format!("Invalid {} in {}", name, uri));
+        match name.as_ref() {
+            "start_index" => request.start_index = number()?,
+            "max_length" => request.max_length = Some(number()?),
+            _ => bail!("Unknown parameter {} in {}", name, uri),
+        }
+    }
+
+    Ok(request)
+}
+
+fn split_query(rest: &str) -> (&str, Option<&str>) {
+    match rest.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (rest, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(url: &str, content: &str) -> StoredDocument {
+        StoredDocument {
+            url: url.to_string(),
+            final_url: url.to_string(),
+            handler: "html".to_string(),
+            content: content.to_string(),
+            fetched_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_uri() {
+        let hash = url_hash("https://example.com/");
+        assert_eq!(hash.len(), 16);
+
+        let request = parse_uri(&cache_uri("https://example.com/")).unwrap();
+        assert_eq!(request.target, ResourceTarget::Cache(hash.clone()));
+        assert_eq!(request.start_index, 0);
+        assert_eq!(request.max_length, None);
+
+        let request = parse_uri(&page_uri(&cache_uri("https://example.com/"), 100, Some(50))).unwrap();
+        assert_eq!(request.start_index, 100);
+        assert_eq!(request.max_length, Some(50));
+
+        let request = parse_uri("fetch://url/https%3A%2F%2Fexample.com%2Fa%3Fb%3Dc?max_length=10").unwrap();
+        assert_eq!(request.target, ResourceTarget::Url("https://example.com/a?b=c".to_string()));
+        assert_eq!(request.max_length, Some(10));
+
+        let request = parse_uri(&page_uri(&url_uri("https://example.com/a?b=c"), 20, None)).unwrap();
+        assert_eq!(request.target, ResourceTarget::Url("https://example.com/a?b=c".to_string()));
+        assert_eq!(request.start_index, 20);
+
+        let request = parse_uri("fetch://url/https://example.com/a?b=c").unwrap();
+        assert_eq!(request.target, ResourceTarget::Url("https://example.com/a?b=c".to_string()));
+
+        assert!(parse_uri("fetch://cache/not-hex").is_err());
+        assert!(parse_uri("fetch://cache/abcd?start_index=x").is_err());
+        assert!(parse_uri("file:///etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_document_store() {
+        let store = DocumentStore::new(2, 1024);
+        store.insert(document("https://a.example/", "# A\n\nbody"));
+        store.insert(document("https://b.example/", "b"));
+        assert_eq!(store.get(&url_hash("https://a.example/")).unwrap().title(), Some("A"));
+
+        // Refetching replaces the entry and moves it to the front
+        store.insert(document("https://a.example/", "new"));
+        let urls: Vec<String> = store.list().iter().map(|doc| doc.url.clone()).collect();
+        assert_eq!(urls, ["https://a.example/", "https://b.example/"]);
+
+        // Third document evicts the oldest
+        store.insert(document("https://c.example/", "c"));
+        assert!(store.get(&url_hash("https://b.example/")).is_none());
+        assert_eq!(store.list().len(), 2);
+
+        // Size limit evicts too, but always keeps the newest document
+        let store = DocumentStore::new(10, 4);
+        store.insert(document("https://a.example/", "aaa"));
+        store.insert(document("https://b.example/", "bbbbbb"));
+        assert_eq!(store.list().len(), 1);
+        assert!(store.get(&url_hash("https://b.example/")).is_some());
+    }
+}
@@ -0,0 +1,161 @@
+//! Retries of transient fetch failures
+//!
+//! Timeouts, connection failures and 429/502/503/504 responses are retried for
+//! idempotent methods, with exponential backoff and full jitter. A server's
+//! `Retry-After` is honored as a lower bound on the wait.
+
+use reqwest::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::domains::PolicyDenied;
+use crate::ssrf::BlockedDestination;
+
+/// Default retries after the first attempt
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Default backoff ceiling before the first retry, in milliseconds
+pub const DEFAULT_BASE_DELAY_MS: u64 = 500;
+
+/// Default longest single backoff, in milliseconds
+pub const DEFAULT_MAX_DELAY_MS: u64 = 10_000;
+
+/// Default time budget for all attempts together, in milliseconds
+pub const DEFAULT_MAX_TOTAL_MS: u64 = 30_000;
+
+/// When and how often to retry transient failures
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying)
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Backoff ceiling before the first retry in milliseconds; doubles with each retry
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+
+    /// Longest single backoff in milliseconds
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+
+    /// No retry starts later than this many milliseconds after the first attempt
+    #[serde(default = "default_max_total_ms")]
+    pub max_total_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay_ms: DEFAULT_BASE_DELAY_MS,
+            max_delay_ms: DEFAULT_MAX_DELAY_MS,
+            max_total_ms: DEFAULT_MAX_TOTAL_MS,
+        }
+    }
+}
+
+fn default_max_retries() -> u32 {
+    DEFAULT_MAX_RETRIES
+}
+
+fn default_base_delay_ms() -> u64 {
+    DEFAULT_BASE_DELAY_MS
+}
+
+fn default_max_delay_ms() -> u64 {
+    DEFAULT_MAX_DELAY_MS
+}
+
+fn default_max_total_ms() -> u64 {
+    DEFAULT_MAX_TOTAL_MS
+}
+
+impl RetryPolicy {
+    /// Time budget for all attempts together
+    pub fn max_total(&self) -> Duration {
+        Duration::from_millis(self.max_total_ms)
+    }
+
+    /// Wait before retry number `retry` (starting at 1)
+    ///
+    /// Full jitter: a uniform pick between zero and the exponential ceiling,
+    /// but never less than the server's `Retry-After`.
+    pub fn backoff(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let ceiling = self
+            .base_delay_ms
+            .saturating_mul(1u64 << retry.saturating_sub(1).min(20))
+            .min(self.max_delay_ms);
+        let jittered = Duration::from_millis(fastrand::u64(0..=ceiling));
+        retry_after.map_or(jittered, |delay| delay.max(jittered))
+    }
+}
+
+/// Statuses retried, and reported as retryable: request timeouts, rate
+/// limiting and temporary upstream failures
+pub fn is_transient_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 425 | 429 | 502 | 503 | 504)
+}
+
+/// Timeouts and connection failures are worth another attempt; refused destinations are not
+pub fn is_transient_error(err: &anyhow::Error) -> bool {
+    if err.chain().any(|cause| cause.is::<BlockedDestination>() || cause.is::<PolicyDenied>()) {
+        return false;
+    }
+    err.chain().any(|cause| {
+        cause.downcast_ref::<reqwest::Error>().is_some_and(|err| {
+            !err.is_redirect() && !err.is_builder() && (err.is_timeout() || err.is_connect() || err.is_request())
+        })
+    })
+}
+
+/// Attempts behind a response, kept in its extensions
+#[derive(Debug, Clone)]
+pub struct Attempts {
+    /// Requests made, including retries
+    pub count: u32,
+
+    /// Failure that caused the last retry
+    pub last_error: Option<String>,
+}
+
+/// Context of a request that failed on every attempt
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("gave up after {attempts} attempts")]
+pub struct RetriesExhausted {
+    pub attempts: u32,
+
+    /// Failure that caused the last retry
+    pub last_error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            base_delay_ms: 100,
+            max_delay_ms: 300,
+            ..Default::default()
+        };
+
+        for _ in 0..100 {
+            assert!(policy.backoff(1, None) <= Duration::from_millis(100));
+            assert!(policy.backoff(2, None) <= Duration::from_millis(200));
+            assert!(policy.backoff(10, None) <= Duration::from_millis(300));
+        }
+        assert!(policy.backoff(1, Some(Duration::from_secs(2))) == Duration::from_secs(2));
+
+        let policy: RetryPolicy = serde_json::from_str(r#"{ "max_retries": 5 }"#).unwrap();
+        assert_eq!(policy.max_retries, 5);
+        assert_eq!(policy.max_total_ms, DEFAULT_MAX_TOTAL_MS);
+
+        assert!(is_transient_status(StatusCode::BAD_GATEWAY));
+        assert!(is_transient_status(StatusCode::REQUEST_TIMEOUT));
+        assert!(!is_transient_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!is_transient_error(&anyhow::anyhow!("not a network error")));
+    }
+}
@@ -1,84 +1,299 @@
 use anyhow::{Context, Result};
+use reqwest::StatusCode;
 use robotstxt::DefaultMatcher;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
 use url::Url;
 
-/// Check if a URL can be fetched according to robots.txt
-pub async fn check_robots_txt_allowed(
-    client: &reqwest::Client,
-    url: &str,
-    user_agent: &str,
-) -> Result<()> {
-    let parsed_url = Url::parse(url).context("Invalid URL")?;
-
-    // Construct robots.txt URL
-    let robots_url = format!(
-        "{}://{}/robots.txt",
-        parsed_url.scheme(),
-        parsed_url.host_str().context("No host in URL")?
-    );
+use crate::fetch::{self, Fetcher};
 
+/// Default time a fetched robots.txt is reused
+pub const DEFAULT_ROBOTS_TTL: Duration = Duration::from_secs(3600);
+
+/// Time an unreachable (5xx) robots.txt keeps its origin disallowed before retrying
+const UNAVAILABLE_TTL: Duration = Duration::from_secs(300);
+
+/// Longest honored `Crawl-delay`; larger values are clamped
+pub const MAX_CRAWL_DELAY: Duration = Duration::from_secs(30);
+
+/// Bytes of robots.txt parsed (RFC 9309 requires at least 500 KiB)
+pub const MAX_ROBOTS_BYTES: u64 = 512 * 1024;
+
+/// robots.txt forbids the fetch
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum RobotsDenied {
+    #[error("robots.txt disallows fetching {url} for user-agent '{user_agent}'")]
+    Disallowed { url: String, user_agent: String },
+
+    #[error("robots.txt returned {0}, assuming autonomous fetching not allowed")]
+    Restricted(StatusCode),
+
+    #[error("robots.txt returned {0}, assuming fetching disallowed until it is reachable")]
+    Unavailable(StatusCode),
+}
+
+/// What an origin's robots.txt says
+#[derive(Debug, Clone)]
+enum RobotsFile {
+    /// No robots.txt (404 and other 4xx)
+    AllowAll,
+    /// 401 or 403
+    Restricted(StatusCode),
+    /// Server error: complete disallow, per RFC 9309
+    Unavailable(StatusCode),
+    /// Rules to match against
+    Rules { content: String, crawl_delay: Option<Duration> },
+}
+
+struct CachedRobots {
+    file: Arc<RobotsFile>,
+    expires: Instant,
+}
+
+/// Per-origin robots.txt cache
+pub struct RobotsCache {
+    /// Product token matched against `User-agent` lines
+    product_token: String,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedRobots>>,
+}
+
+impl RobotsCache {
+    pub fn new(product_token: &str, ttl: Duration) -> Self {
+        Self {
+            product_token: product_token.to_string(),
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check that robots.txt allows fetching `url`, returning the origin's crawl delay
+    ///
+    /// The delay is enforced by the fetcher's scheduler, together with its rate limits.
+    pub async fn check(&self, fetcher: &Fetcher, url: &str) -> Result<Option<Duration>> {
+        let parsed = Url::parse(url).context("Invalid URL")?;
+        let origin = robots_origin(&parsed)?;
+
+        let file = match self.cached(&origin) {
+            Some(file) => file,
+            None => {
+                let file = Arc::new(fetch_robots(fetcher, &origin, &self.product_token).await?);
+                let ttl = match *file {
+                    RobotsFile::Unavailable(_) => UNAVAILABLE_TTL.min(self.ttl),
+                    _ => self.ttl,
+                };
+                if let Ok(mut entries) = self.entries.lock() {
+                    entries.insert(origin.clone(), CachedRobots { file: file.clone(), expires: Instant::now() + ttl });
+                }
+                file
+            }
+        };
+
+        let crawl_delay = match &*file {
+            RobotsFile::AllowAll => None,
+            RobotsFile::Restricted(status) => return Err(RobotsDenied::Restricted(*status).into()),
+            RobotsFile::Unavailable(status) => return Err(RobotsDenied::Unavailable(*status).into()),
+            RobotsFile::Rules { content, crawl_delay } => {
+                let mut matcher = DefaultMatcher::default();
+                if !matcher.one_agent_allowed_by_robots(content, &self.product_token, url) {
+                    return Err(RobotsDenied::Disallowed {
+                        url: url.to_string(),
+                        user_agent: self.product_token.clone(),
+                    }
+                    .into());
+                }
+                *crawl_delay
+            }
+        };
+
+        tracing::debug!("robots.txt allows fetching {}", url);
+        Ok(crawl_delay)
+    }
+
+    fn cached(&self, origin: &str) -> Option<Arc<RobotsFile>> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(origin)
+            .filter(|entry| entry.expires > Instant::now())
+            .map(|entry| entry.file.clone())
+    }
+}
+
+/// Product token of a user agent, e.g. `ModelContextProtocol` for
+/// `ModelContextProtocol/1.0 (Autonomous; ...)`
+pub fn product_token(user_agent: &str) -> &str {
+    let end = user_agent
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(user_agent.len());
+    &user_agent[..end]
+}
+
+/// Scheme, host and non-default port of a URL: robots.txt applies per origin
+fn robots_origin(url: &Url) -> Result<String> {
+    url.host_str().context("No host in URL")?;
+    Ok(url.origin().ascii_serialization())
+}
+
+async fn fetch_robots(fetcher: &Fetcher, origin: &str, product_token: &str) -> Result<RobotsFile> {
+    let robots_url = format!("{}/robots.txt", origin);
     tracing::debug!("Fetching robots.txt from: {}", robots_url);
 
-    // Fetch robots.txt
-    let response = client
-        .get(&robots_url)
-        .send()
+    let response = fetcher
+        .send(fetcher.client.get(&robots_url), &robots_url, None)
         .await
         .context("Failed to fetch robots.txt")?;
 
-    // Handle 4xx errors
-    if response.status().is_client_error() {
-        if response.status().as_u16() == 401 || response.status().as_u16() == 403 {
-            anyhow::bail!(
-                "robots.txt returned {}, assuming autonomous fetching not allowed",
-                response.status()
-            );
-        }
+    let status = response.status();
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Ok(RobotsFile::Restricted(status));
+    }
+    if status.is_client_error() {
         // 404 and other 4xx = no robots.txt = allowed
-        tracing::debug!("robots.txt returned {}, assuming allowed", response.status());
-        return Ok(());
+        tracing::debug!("robots.txt returned {}, assuming allowed", status);
+        return Ok(RobotsFile::AllowAll);
     }
-
-    if !response.status().is_success() {
-        anyhow::bail!("Failed to fetch robots.txt: HTTP {}", response.status());
+    if status.is_server_error() {
+        tracing::warn!("robots.txt at {} returned {}, disallowing for now", robots_url, status);
+        return Ok(RobotsFile::Unavailable(status));
     }
+    if !status.is_success() {
+        tracing::debug!("robots.txt returned {}, assuming allowed", status);
+        return Ok(RobotsFile::AllowAll);
+    }
+
+    let (body, _) = fetch::read_body(response, MAX_ROBOTS_BYTES)
+        .await
+        .context("Failed to read robots.txt")?;
+    let content = String::from_utf8_lossy(&body).into_owned();
+    let crawl_delay = crawl_delay(&content, product_token);
+    Ok(RobotsFile::Rules { content, crawl_delay })
+}
+
+/// `Crawl-delay` of the group that applies to `token`, falling back to the `*` group
+///
+/// A group is a run of `User-agent` lines followed by rules; product tokens are
+/// compared case-insensitively.
+pub fn crawl_delay(content: &str, token: &str) -> Option<Duration> {
+    let mut agents: Vec<String> = Vec::new();
+    let mut in_rules = false;
+    let mut specific = None;
+    let mut wildcard = None;
 
-    let robots_content = response.text().await.context("Failed to read robots.txt")?;
-
-    // Parse robots.txt
-    let mut matcher = DefaultMatcher::default();
-    let allowed = matcher.one_agent_allowed_by_robots(
-        &robots_content,
-        user_agent,
-        url,
-    );
-
-    if !allowed {
-        anyhow::bail!(
-            "robots.txt disallows fetching {} for user-agent '{}'",
-            url,
-            user_agent
-        );
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+
+        if key == "user-agent" {
+            if in_rules {
+                agents.clear();
+                in_rules = false;
+            }
+            agents.push(value.to_ascii_lowercase());
+            continue;
+        }
+        in_rules = true;
+
+        if key == "crawl-delay" {
+            let Some(delay) = value.parse::<f64>().ok().filter(|d| d.is_finite() && *d >= 0.0) else {
+                continue;
+            };
+            let delay = Duration::from_secs_f64(delay).min(MAX_CRAWL_DELAY);
+            if agents.iter().any(|agent| agent.eq_ignore_ascii_case(token)) {
+                specific.get_or_insert(delay);
+            } else if agents.iter().any(|agent| agent == "*") {
+                wildcard.get_or_insert(delay);
+            }
+        }
     }
 
-    tracing::debug!("robots.txt allows fetching {}", url);
-    Ok(())
+    specific.or(wildcard)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const ROBOTS: &str = "User-agent: *\nDisallow: /\nCrawl-delay: 5\n\n\
+        User-agent: OtherBot\nUser-agent: ModelContextProtocol\nDisallow: /private\nCrawl-delay: 0.2\n";
+
+    #[test]
+    fn test_product_token_and_crawl_delay() {
+        assert_eq!(product_token(crate::fetch::DEFAULT_USER_AGENT_AUTONOMOUS), "ModelContextProtocol");
+        assert_eq!(product_token("my-bot_2"), "my-bot_2");
+
+        assert_eq!(crawl_delay(ROBOTS, "modelcontextprotocol"), Some(Duration::from_millis(200)));
+        assert_eq!(crawl_delay(ROBOTS, "SomeoneElse"), Some(Duration::from_secs(5)));
+        assert_eq!(crawl_delay("User-agent: *\nCrawl-delay: 9999", "x"), Some(MAX_CRAWL_DELAY));
+        assert_eq!(crawl_delay("User-agent: *\nDisallow: /a", "x"), None);
+    }
+
+    #[tokio::test]
+    async fn test_robots_cache_and_crawl_delay() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let h = hits.clone();
+        let base = test_util::serve(move |request| {
+            if request.starts_with("GET /robots.txt") {
+                h.fetch_add(1, Ordering::SeqCst);
+                test_util::response("200 OK", &[], ROBOTS.as_bytes())
+            } else {
+                test_util::response("200 OK", &[], b"page")
+            }
+        })
+        .await;
+        let client = test_util::loopback_fetcher(None);
+        let robots = RobotsCache::new("ModelContextProtocol", DEFAULT_ROBOTS_TTL);
+
+        // The product token group applies, not `*`; robots.txt is fetched from the same port once
+        let delay = robots.check(&client, &format!("{}/public", base)).await.unwrap();
+        assert_eq!(delay, Some(Duration::from_millis(200)));
+        robots.check(&client, &format!("{}/other", base)).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let err = robots.check(&client, &format!("{}/private/x", base)).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<RobotsDenied>(), Some(RobotsDenied::Disallowed { .. })));
+
+        // Another token falls back to the `*` group
+        let robots = RobotsCache::new("SomeoneElse", DEFAULT_ROBOTS_TTL);
+        assert!(robots.check(&client, &format!("{}/public", base)).await.is_err());
+
+        // An expired entry is fetched again
+        let robots = RobotsCache::new("ModelContextProtocol", Duration::ZERO);
+        robots.check(&client, &format!("{}/public", base)).await.unwrap();
+        robots.check(&client, &format!("{}/public", base)).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_server_error_disallows() {
+        let base = test_util::serve(|request| {
+            if request.starts_with("GET /robots.txt") {
+                test_util::response("503 Service Unavailable", &[], b"")
+            } else {
+                test_util::response("404 Not Found", &[], b"")
+            }
+        })
+        .await;
+        let client = test_util::loopback_fetcher(None);
+        let robots = RobotsCache::new("ModelContextProtocol", DEFAULT_ROBOTS_TTL);
+
+        let err = robots.check(&client, &format!("{}/page", base)).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<RobotsDenied>(), Some(RobotsDenied::Unavailable(status)) if status.as_u16() == 503));
+    }
 
     #[tokio::test]
     async fn test_robots_txt_parsing() {
-        let client = reqwest::Client::new();
+        let client = Fetcher::new(reqwest::Client::new(), None);
+        let robots = RobotsCache::new("Mozilla", DEFAULT_ROBOTS_TTL);
 
         // Google allows crawling of homepage
-        let result = check_robots_txt_allowed(
-            &client,
-            "https://www.google.com/",
-            "Mozilla/5.0"
-        ).await;
+        let result = robots.check(&client, "https://www.google.com/").await;
 
         assert!(result.is_ok());
     }
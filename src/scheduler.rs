@@ -0,0 +1,396 @@
+//! Per-host politeness scheduling shared by every tool
+//!
+//! Each host gets a token bucket, a cap on requests in flight and a backoff
+//! window opened by 429/503 responses. Limits come from the `[scheduler]`
+//! section of the config file and can be overridden by `[[domains]]` blocks.
+//! Idle hosts are evicted once too many are tracked.
+
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use url::Url;
+
+use crate::domains::{self, DomainRule};
+
+/// Default sustained requests per second to one host
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 2.0;
+
+/// Default requests allowed at once before the rate applies
+pub const DEFAULT_BURST: u32 = 4;
+
+/// Default requests in flight to one host
+pub const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+/// First backoff after a 429/503 without `Retry-After`; doubles while throttling continues
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest backoff, including server-requested `Retry-After` delays
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Hosts tracked before idle ones are evicted
+const MAX_HOSTS: usize = 1024;
+
+/// `[scheduler]` section of the config file: limits for hosts without a `[[domains]]` override
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SchedulerConfig {
+    /// Sustained requests per second to one host (0 = unlimited)
+    #[serde(default = "default_requests_per_second")]
+    pub requests_per_second: f64,
+
+    /// Requests allowed at once before the rate applies
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+
+    /// Requests in flight to one host
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: DEFAULT_REQUESTS_PER_SECOND,
+            burst: DEFAULT_BURST,
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+        }
+    }
+}
+
+fn default_requests_per_second() -> f64 {
+    DEFAULT_REQUESTS_PER_SECOND
+}
+
+fn default_burst() -> u32 {
+    DEFAULT_BURST
+}
+
+fn default_max_concurrent() -> usize {
+    DEFAULT_MAX_CONCURRENT
+}
+
+/// Effective limits for one host
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HostLimits {
+    pub requests_per_second: f64,
+    pub burst: u32,
+    pub max_concurrent: usize,
+}
+
+/// Scheduling state of one host
+struct HostSlot {
+    limiter: Option<DefaultDirectRateLimiter>,
+    permits: Arc<Semaphore>,
+    timing: Mutex<HostTiming>,
+}
+
+#[derive(Default)]
+struct HostTiming {
+    /// No requests before this time (429/503 backoff)
+    blocked_until: Option<Instant>,
+    /// Earliest time of the next request under a crawl delay
+    next_slot: Option<Instant>,
+    /// Consecutive throttled responses
+    throttled: u32,
+}
+
+impl HostSlot {
+    fn new(limits: HostLimits) -> Self {
+        let limiter = (limits.requests_per_second.is_finite() && limits.requests_per_second > 0.0)
+            .then(|| Duration::from_secs_f64(1.0 / limits.requests_per_second))
+            .and_then(Quota::with_period)
+            .map(|quota| {
+                let burst = NonZeroU32::new(limits.burst).unwrap_or(NonZeroU32::MIN);
+                RateLimiter::direct(quota.allow_burst(burst))
+            });
+
+        Self {
+            limiter,
+            permits: Arc::new(Semaphore::new(limits.max_concurrent.max(1))),
+            timing: Mutex::new(HostTiming::default()),
+        }
+    }
+
+    /// No request in flight or waiting, and no backoff or crawl delay pending
+    fn is_idle(self: &Arc<Self>, now: Instant) -> bool {
+        if Arc::strong_count(self) > 1 || Arc::strong_count(&self.permits) > 1 {
+            return false;
+        }
+        let timing = self.timing.lock().unwrap_or_else(|e| e.into_inner());
+        timing.blocked_until.is_none_or(|until| until <= now) && timing.next_slot.is_none_or(|next| next <= now)
+    }
+}
+
+/// Slot of a request in flight; the host's concurrency permit is released on drop
+///
+/// Kept in the response's extensions so the permit lasts until the body is read.
+#[derive(Debug, Clone)]
+pub struct HostPermit(#[allow(dead_code)] Arc<OwnedSemaphorePermit>);
+
+/// Server-wide per-host scheduler
+pub struct Scheduler {
+    config: SchedulerConfig,
+    domains: Vec<DomainRule>,
+    hosts: Mutex<HashMap<String, Arc<HostSlot>>>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new(SchedulerConfig::default(), Vec::new())
+    }
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig, domains: Vec<DomainRule>) -> Self {
+        Self {
+            config,
+            domains,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limits for `host`: its `[[domains]]` block over the defaults
+    pub fn limits_for(&self, host: &str) -> HostLimits {
+        let mut limits = HostLimits {
+            requests_per_second: self.config.requests_per_second,
+            burst: self.config.burst,
+            max_concurrent: self.config.max_concurrent,
+        };
+        if let Some(rule) = domains::rule_for(&self.domains, host) {
+            limits.requests_per_second = rule.requests_per_second.unwrap_or(limits.requests_per_second);
+            limits.burst = rule.burst.unwrap_or(limits.burst);
+            limits.max_concurrent = rule.max_concurrent.unwrap_or(limits.max_concurrent);
+        }
+        limits
+    }
+
+    /// Wait for a slot to request `url`
+    ///
+    /// Waits for a concurrency permit, then out any backoff, the crawl delay
+    /// (`min_interval`) and the host's token bucket, in that order.
+    pub async fn acquire(&self, url: &str, min_interval: Option<Duration>) -> HostPermit {
+        let slot = self.slot(&host_key(url));
+        let permit = slot
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("host semaphore is never closed");
+
+        let start = {
+            let mut timing = slot.timing.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let mut start = timing.blocked_until.filter(|until| *until > now).unwrap_or(now);
+            if let Some(interval) = min_interval {
+                start = timing.next_slot.filter(|next| *next > start).unwrap_or(start);
+                timing.next_slot = Some(start + interval);
+            }
+            start
+        };
+        if start > Instant::now() {
+            tracing::debug!("Waiting {:?} before requesting {}", start - Instant::now(), url);
+            tokio::time::sleep_until(start).await;
+        }
+
+        if let Some(limiter) = &slot.limiter {
+            limiter.until_ready().await;
+        }
+
+        HostPermit(Arc::new(permit))
+    }
+
+    /// Record the status of a response from `url`, backing off on 429/503
+    ///
+    /// The backoff is the server's `Retry-After` when given, otherwise it
+    /// doubles with each consecutive throttled response.
+    pub fn observe(&self, url: &str, status: StatusCode, retry_after: Option<Duration>) {
+        let slot = self.slot(&host_key(url));
+        let mut timing = slot.timing.lock().unwrap_or_else(|e| e.into_inner());
+
+        if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+            timing.throttled = 0;
+            return;
+        }
+
+        timing.throttled += 1;
+        let backoff = retry_after
+            .unwrap_or_else(|| BASE_BACKOFF.saturating_mul(1 << (timing.throttled - 1).min(16)))
+            .min(MAX_BACKOFF);
+        let until = Instant::now() + backoff;
+        if timing.blocked_until.is_none_or(|blocked| blocked < until) {
+            timing.blocked_until = Some(until);
+        }
+        tracing::warn!("{} returned {}, backing off host for {:?}", url, status, backoff);
+    }
+
+    fn slot(&self, host: &str) -> Arc<HostSlot> {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        if hosts.len() >= MAX_HOSTS && !hosts.contains_key(host) {
+            // An evicted host starts over with a full token bucket
+            let now = Instant::now();
+            hosts.retain(|_, slot| !slot.is_idle(now));
+        }
+        hosts
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(HostSlot::new(self.limits_for(host))))
+            .clone()
+    }
+}
+
+/// Hosts are scheduled by name, regardless of scheme and port
+fn host_key(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_ascii_lowercase()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_limits_for_host() {
+        #[derive(Deserialize)]
+        struct File {
+            scheduler: SchedulerConfig,
+            domains: Vec<DomainRule>,
+        }
+        let file: File = toml::from_str(
+            r#"
+            [scheduler]
+            max_concurrent = 2
+
+            [[domains]]
+            match = "*.wikipedia.org"
+            requests_per_second = 20
+
+            [[domains]]
+            match = "slow.example"
+            requests_per_second = 0.5
+            burst = 1
+            max_concurrent = 1
+            "#,
+        )
+        .unwrap();
+        let scheduler = Scheduler::new(file.scheduler, file.domains);
+
+        let limits = scheduler.limits_for("en.wikipedia.org");
+        assert_eq!(limits.requests_per_second, 20.0);
+        assert_eq!(limits.burst, DEFAULT_BURST);
+        assert_eq!(limits.max_concurrent, 2);
+
+        assert_eq!(scheduler.limits_for("SLOW.example").max_concurrent, 1);
+        assert_eq!(scheduler.limits_for("other.example").requests_per_second, DEFAULT_REQUESTS_PER_SECOND);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_and_backoff() {
+        let scheduler = Arc::new(Scheduler::new(
+            SchedulerConfig {
+                requests_per_second: 0.0,
+                max_concurrent: 2,
+                ..Default::default()
+            },
+            Vec::new(),
+        ));
+
+        // A third request to the host waits for a permit; other hosts don't
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..6)
+            .map(|i| {
+                let (scheduler, in_flight, peak) = (scheduler.clone(), in_flight.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let _permit = scheduler.acquire(&format!("http://a.example/{}", i), None).await;
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        let start = Instant::now();
+        drop(scheduler.acquire("http://b.example/", None).await);
+        assert!(start.elapsed() < Duration::from_millis(50));
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        // Retry-After opens a backoff window for the whole host
+        scheduler.observe("https://a.example/x", StatusCode::TOO_MANY_REQUESTS, Some(Duration::from_millis(300)));
+        let start = Instant::now();
+        drop(scheduler.acquire("http://a.example:8080/y", None).await);
+        assert!(start.elapsed() >= Duration::from_millis(300));
+
+        // Crawl delay spaces requests out
+        let start = Instant::now();
+        for _ in 0..3 {
+            drop(scheduler.acquire("http://c.example/", Some(Duration::from_millis(100))).await);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // The token bucket allows a burst, then paces at the rate
+        let scheduler = Scheduler::new(
+            SchedulerConfig {
+                requests_per_second: 10.0,
+                burst: 2,
+                ..Default::default()
+            },
+            Vec::new(),
+        );
+        let start = Instant::now();
+        for _ in 0..4 {
+            drop(scheduler.acquire("http://a.example/", None).await);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn test_idle_hosts_evicted() {
+        let scheduler = Scheduler::new(SchedulerConfig { requests_per_second: 0.0, ..Default::default() }, Vec::new());
+        let _permit = scheduler.acquire("http://busy.example/", None).await;
+        scheduler.observe("http://throttled.example/", StatusCode::TOO_MANY_REQUESTS, Some(Duration::from_secs(30)));
+
+        for i in 0..MAX_HOSTS {
+            scheduler.slot(&format!("host{}.example", i));
+        }
+        // Hosts with a request in flight or a backoff window survive eviction
+        let hosts = scheduler.hosts.lock().unwrap();
+        assert!(hosts.len() < MAX_HOSTS);
+        assert!(hosts.contains_key("busy.example"));
+        assert!(hosts.contains_key("throttled.example"));
+        assert!(!hosts.contains_key("host0.example"));
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let scheduler = Scheduler::default();
+        let backoff = || {
+            let slot = scheduler.slot("b.example");
+            let timing = slot.timing.lock().unwrap();
+            timing.blocked_until.unwrap() - Instant::now()
+        };
+
+        for _ in 0..3 {
+            scheduler.observe("http://b.example/", StatusCode::SERVICE_UNAVAILABLE, None);
+        }
+        assert!(backoff() > Duration::from_millis(3900));
+
+        // Retry-After beyond the cap is clamped
+        scheduler.observe("http://b.example/", StatusCode::TOO_MANY_REQUESTS, Some(Duration::from_secs(3600)));
+        assert!(backoff() <= MAX_BACKOFF);
+
+        // Success resets the sequence, but not the window already open
+        scheduler.observe("http://b.example/", StatusCode::OK, None);
+        assert_eq!(scheduler.slot("b.example").timing.lock().unwrap().throttled, 0);
+    }
+}
@@ -88,9 +88,7 @@ pub fn search_in_text(content: &str, query: &str, options: SearchOptions) -> Res
         anyhow::bail!("Search query cannot be empty");
     }
 
-    let mut matches = Vec::new();
-
-    if options.use_regex {
+    let mut matches = if options.use_regex {
         // Regex search
         let pattern = if options.case_sensitive {
             query.to_string()
@@ -99,17 +97,17 @@ pub fn search_in_text(content: &str, query: &str, options: SearchOptions) -> Res
         };
 
         let re = Regex::new(&pattern).context("Invalid regex pattern")?;
-        matches = search_with_regex(content, &re, options.context_chars, options.line_filter.as_deref());
+        search_with_regex(content, &re, options.context_chars, options.line_filter.as_deref())
     } else {
         // Plain text search
-        matches = search_plain_text(
+        search_plain_text(
             content,
             query,
             options.case_sensitive,
             options.context_chars,
             options.line_filter.as_deref(),
-        );
-    }
+        )
+    };
 
     // Apply max_matches limit
     let total_matches = matches.len();
@@ -1,11 +1,42 @@
-use anyhow::Result;
-use quick_xml::events::Event;
-use quick_xml::Reader;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::{Namespace, ResolveResult};
+use quick_xml::NsReader;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+
+use crate::charset;
+use crate::error::{ErrorCode, ToolError};
+use crate::fetch::{self, FetchOptions, Fetcher};
+use crate::robots;
+use crate::ssrf::DestinationPolicy;
+use crate::validation;
+
+/// Default depth of nested sitemap indexes followed
+pub const DEFAULT_MAX_DEPTH: usize = 3;
+
+/// Default number of sitemap files fetched
+pub const DEFAULT_MAX_SITEMAPS: usize = 50;
+
+/// Default number of URL entries returned
+pub const DEFAULT_MAX_URLS: usize = 5000;
+
+/// Paths probed when robots.txt lists no sitemap
+pub const COMMON_SITEMAP_PATHS: &[&str] = &["/sitemap.xml", "/sitemap_index.xml", "/sitemap.xml.gz", "/sitemap.txt"];
+
+/// Namespaces of the sitemap extensions read into [`SitemapUrl`]
+const IMAGE_NS: &[u8] = b"http://www.google.com/schemas/sitemap-image/1.1";
+const VIDEO_NS: &[u8] = b"http://www.google.com/schemas/sitemap-video/1.1";
+const NEWS_NS: &[u8] = b"http://www.google.com/schemas/sitemap-news/0.9";
+const XHTML_NS: &[u8] = b"http://www.w3.org/1999/xhtml";
 
 /// Sitemap URL entry
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct SitemapUrl {
     /// URL location
     pub loc: String,
@@ -18,6 +49,93 @@ pub struct SitemapUrl {
 
     /// Priority (0.0 to 1.0)
     pub priority: Option<f32>,
+
+    /// Images on the page (`image:image`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<SitemapImage>,
+
+    /// Videos on the page (`video:video`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub videos: Vec<SitemapVideo>,
+
+    /// News article details (`news:news`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub news: Option<SitemapNews>,
+
+    /// Alternate-language versions of the page (`xhtml:link rel="alternate"`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternates: Vec<SitemapAlternate>,
+}
+
+/// Image sitemap entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SitemapImage {
+    /// Image URL
+    pub loc: String,
+
+    /// Caption (deprecated by Google, still common)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+
+    /// Title (deprecated by Google, still common)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Video sitemap entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SitemapVideo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Thumbnail image URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_loc: Option<String>,
+
+    /// URL of the video file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_loc: Option<String>,
+
+    /// URL of the video player
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player_loc: Option<String>,
+
+    /// Duration in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u32>,
+}
+
+/// News sitemap entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SitemapNews {
+    /// Publication name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publication_name: Option<String>,
+
+    /// Publication language (ISO 639 code)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publication_language: Option<String>,
+
+    /// Publication date (W3C Datetime)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publication_date: Option<String>,
+
+    /// Article title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Alternate-language version of a URL
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SitemapAlternate {
+    /// Language code, or `x-default`
+    pub hreflang: String,
+
+    /// URL of the version
+    pub href: String,
 }
 
 /// Sitemap index entry (for sitemap of sitemaps)
@@ -33,7 +151,7 @@ pub struct SitemapIndexEntry {
 /// Parsed sitemap data
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SitemapData {
-    /// Type of sitemap (urlset or sitemapindex)
+    /// Type of sitemap (urlset, sitemapindex or text)
     pub sitemap_type: String,
 
     /// URLs (if urlset type)
@@ -43,10 +161,46 @@ pub struct SitemapData {
     pub sitemaps: Vec<SitemapIndexEntry>,
 }
 
+/// Namespace of a sitemap element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ns {
+    /// The sitemap protocol itself (any other or no namespace)
+    Sitemap,
+    Image,
+    Video,
+    News,
+    Xhtml,
+}
+
+impl Ns {
+    // Undeclared prefixes are common in hand-written sitemaps, so fall back to the usual prefix
+    fn resolve(resolved: &ResolveResult) -> Self {
+        match resolved {
+            ResolveResult::Bound(Namespace(ns)) => match *ns {
+                IMAGE_NS => Ns::Image,
+                VIDEO_NS => Ns::Video,
+                NEWS_NS => Ns::News,
+                XHTML_NS => Ns::Xhtml,
+                _ => Ns::Sitemap,
+            },
+            ResolveResult::Unknown(prefix) => match prefix.as_slice() {
+                b"image" => Ns::Image,
+                b"video" => Ns::Video,
+                b"news" => Ns::News,
+                b"xhtml" => Ns::Xhtml,
+                _ => Ns::Sitemap,
+            },
+            ResolveResult::Unbound => Ns::Sitemap,
+        }
+    }
+}
+
 /// Parse sitemap XML content
+///
+/// Besides the sitemap protocol, the image, video and news extensions and
+/// `xhtml:link` alternates are read into each [`SitemapUrl`].
 pub fn parse_sitemap(xml_content: &str) -> Result<SitemapData> {
-    let mut reader = Reader::from_str(xml_content);
-    reader.config_mut().trim_text(true);
+    let mut reader = NsReader::from_str(xml_content);
 
     let mut is_urlset = false;
     let mut is_sitemapindex = false;
@@ -56,87 +210,158 @@ pub fn parse_sitemap(xml_content: &str) -> Result<SitemapData> {
 
     let mut current_url: Option<SitemapUrl> = None;
     let mut current_sitemap: Option<SitemapIndexEntry> = None;
-    let mut current_tag = String::new();
+    let mut current_image: Option<SitemapImage> = None;
+    let mut current_video: Option<SitemapVideo> = None;
+    let mut current_news: Option<SitemapNews> = None;
+
+    // Open elements, and the text of the innermost one (entities arrive as separate events)
+    let mut path: Vec<(Ns, String)> = Vec::new();
+    let mut text = String::new();
 
     let mut buf = Vec::new();
 
     loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
-                let tag_name = String::from_utf8_lossy(e.name().as_ref()).to_string();
-
-                match tag_name.as_str() {
-                    "urlset" => is_urlset = true,
-                    "sitemapindex" => is_sitemapindex = true,
-                    "url" => {
-                        current_url = Some(SitemapUrl {
-                            loc: String::new(),
-                            lastmod: None,
-                            changefreq: None,
-                            priority: None,
-                        });
-                    }
-                    "sitemap" => {
+        match reader.read_resolved_event_into(&mut buf) {
+            Ok((resolved, Event::Start(e))) => {
+                let ns = Ns::resolve(&resolved);
+                let tag_name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                text.clear();
+
+                match (ns, tag_name.as_str()) {
+                    (Ns::Sitemap, "urlset") => is_urlset = true,
+                    (Ns::Sitemap, "sitemapindex") => is_sitemapindex = true,
+                    (Ns::Sitemap, "url") => current_url = Some(SitemapUrl::default()),
+                    (Ns::Sitemap, "sitemap") => {
                         current_sitemap = Some(SitemapIndexEntry {
                             loc: String::new(),
                             lastmod: None,
                         });
                     }
-                    _ => {
-                        current_tag = tag_name;
-                    }
+                    (Ns::Image, "image") => current_image = Some(SitemapImage::default()),
+                    (Ns::Video, "video") => current_video = Some(SitemapVideo::default()),
+                    (Ns::News, "news") => current_news = Some(SitemapNews::default()),
+                    (Ns::Xhtml, "link") => push_alternate(current_url.as_mut(), &e),
+                    _ => {}
                 }
+                path.push((ns, tag_name));
             }
 
-            Ok(Event::Text(e)) => {
-                // In quick-xml 0.38+, BytesText doesn't have unescape() method
-                // Text is already decoded by the parser, just convert to string
-                let text = String::from_utf8_lossy(e.as_ref()).to_string();
-
-                if let Some(ref mut url) = current_url {
-                    match current_tag.as_str() {
-                        "loc" => url.loc = text,
-                        "lastmod" => url.lastmod = Some(text),
-                        "changefreq" => url.changefreq = Some(text),
-                        "priority" => {
-                            url.priority = text.parse::<f32>().ok();
-                        }
-                        _ => {}
-                    }
-                } else if let Some(ref mut sm) = current_sitemap {
-                    match current_tag.as_str() {
-                        "loc" => sm.loc = text,
-                        "lastmod" => sm.lastmod = Some(text),
-                        _ => {}
-                    }
+            Ok((resolved, Event::Empty(e)))
+                if Ns::resolve(&resolved) == Ns::Xhtml && e.local_name().as_ref() == b"link" =>
+            {
+                push_alternate(current_url.as_mut(), &e);
+            }
+
+            Ok((_, Event::Text(e))) => text.push_str(&e.xml_content().unwrap_or_default()),
+
+            Ok((_, Event::CData(e))) => text.push_str(&e.decode().unwrap_or_default()),
+
+            Ok((_, Event::GeneralRef(e))) => {
+                let name = e.decode().unwrap_or_default();
+                if let Ok(Some(ch)) = e.resolve_char_ref() {
+                    text.push(ch);
+                } else if let Some(value) = quick_xml::escape::resolve_predefined_entity(&name) {
+                    text.push_str(value);
                 }
             }
 
-            Ok(Event::End(e)) => {
-                let tag_name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+            Ok((_, Event::End(_))) => {
+                let Some((ns, tag_name)) = path.pop() else { continue };
+                let parent = path.last().map(|(ns, name)| (*ns, name.as_str()));
+                let value = text.trim().to_string();
+                text.clear();
 
-                match tag_name.as_str() {
-                    "url" => {
+                match (ns, tag_name.as_str(), parent) {
+                    (Ns::Sitemap, "url", _) => {
                         if let Some(url) = current_url.take() {
                             if !url.loc.is_empty() {
                                 urls.push(url);
                             }
                         }
                     }
-                    "sitemap" => {
+                    (Ns::Sitemap, "sitemap", _) => {
                         if let Some(sm) = current_sitemap.take() {
                             if !sm.loc.is_empty() {
                                 sitemaps.push(sm);
                             }
                         }
                     }
-                    _ => {
-                        current_tag.clear();
+                    (Ns::Image, "image", _) => {
+                        if let (Some(url), Some(image)) = (current_url.as_mut(), current_image.take()) {
+                            if !image.loc.is_empty() {
+                                url.images.push(image);
+                            }
+                        }
+                    }
+                    (Ns::Video, "video", _) => {
+                        if let (Some(url), Some(video)) = (current_url.as_mut(), current_video.take()) {
+                            url.videos.push(video);
+                        }
+                    }
+                    (Ns::News, "news", _) => {
+                        if let Some(url) = current_url.as_mut() {
+                            url.news = current_news.take();
+                        }
+                    }
+                    (Ns::Sitemap, field, Some((Ns::Sitemap, "url"))) => {
+                        if let Some(ref mut url) = current_url {
+                            match field {
+                                "loc" => url.loc = value,
+                                "lastmod" => url.lastmod = Some(value),
+                                "changefreq" => url.changefreq = Some(value),
+                                "priority" => url.priority = value.parse::<f32>().ok(),
+                                _ => {}
+                            }
+                        }
+                    }
+                    (Ns::Sitemap, field, Some((Ns::Sitemap, "sitemap"))) => {
+                        if let Some(ref mut sm) = current_sitemap {
+                            match field {
+                                "loc" => sm.loc = value,
+                                "lastmod" => sm.lastmod = Some(value),
+                                _ => {}
+                            }
+                        }
+                    }
+                    (Ns::Image, field, Some((Ns::Image, "image"))) => {
+                        if let Some(ref mut image) = current_image {
+                            match field {
+                                "loc" => image.loc = value,
+                                "caption" => image.caption = Some(value),
+                                "title" => image.title = Some(value),
+                                _ => {}
+                            }
+                        }
                     }
+                    (Ns::Video, field, Some((Ns::Video, "video"))) => {
+                        if let Some(ref mut video) = current_video {
+                            match field {
+                                "title" => video.title = Some(value),
+                                "description" => video.description = Some(value),
+                                "thumbnail_loc" => video.thumbnail_loc = Some(value),
+                                "content_loc" => video.content_loc = Some(value),
+                                "player_loc" => video.player_loc = Some(value),
+                                "duration" => video.duration = value.parse().ok(),
+                                _ => {}
+                            }
+                        }
+                    }
+                    (Ns::News, field, Some((Ns::News, parent))) => {
+                        if let Some(ref mut news) = current_news {
+                            match (parent, field) {
+                                ("publication", "name") => news.publication_name = Some(value),
+                                ("publication", "language") => news.publication_language = Some(value),
+                                ("news", "publication_date") => news.publication_date = Some(value),
+                                ("news", "title") => news.title = Some(value),
+                                _ => {}
+                            }
+                        }
+                    }
+                    _ => {}
                 }
             }
 
-            Ok(Event::Eof) => break,
+            Ok((_, Event::Eof)) => break,
 
             Err(e) => {
                 anyhow::bail!("Error parsing sitemap XML at position {}: {:?}", reader.buffer_position(), e);
@@ -163,9 +388,402 @@ pub fn parse_sitemap(xml_content: &str) -> Result<SitemapData> {
     })
 }
 
+/// Add an `xhtml:link rel="alternate" hreflang=".." href=".."` to the current URL
+fn push_alternate(url: Option<&mut SitemapUrl>, link: &BytesStart) {
+    let Some(url) = url else { return };
+    let mut rel = None;
+    let mut hreflang = None;
+    let mut href = None;
+    for attr in link.attributes().flatten() {
+        let value = attr.unescape_value().map(|value| value.trim().to_string()).ok();
+        match attr.key.local_name().as_ref() {
+            b"rel" => rel = value,
+            b"hreflang" => hreflang = value,
+            b"href" => href = value,
+            _ => {}
+        }
+    }
+    let is_alternate = rel.is_some_and(|rel| rel.split_whitespace().any(|token| token.eq_ignore_ascii_case("alternate")));
+    if let (true, Some(hreflang), Some(href)) = (is_alternate, hreflang, href) {
+        if !hreflang.is_empty() && !href.is_empty() {
+            url.alternates.push(SitemapAlternate { hreflang, href });
+        }
+    }
+}
+
+/// Parse a plain-text sitemap: one absolute URL per line
+pub fn parse_text_sitemap(text: &str) -> SitemapData {
+    let urls = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("http://") || line.starts_with("https://"))
+        .map(|line| SitemapUrl {
+            loc: line.to_string(),
+            ..Default::default()
+        })
+        .collect();
+
+    SitemapData {
+        sitemap_type: "text".to_string(),
+        urls,
+        sitemaps: Vec::new(),
+    }
+}
+
+/// Parse a fetched sitemap body, gzip-compressed or not, as XML or plain text
+///
+/// Compressed bodies are recognized by the gzip magic bytes rather than the
+/// `.gz` extension, and inflated up to `max_bytes`.
+pub fn parse_sitemap_body(body: &[u8], content_type: Option<&str>, max_bytes: u64) -> Result<SitemapData> {
+    let inflated;
+    let body = if body.starts_with(&[0x1f, 0x8b]) {
+        let mut buf = Vec::new();
+        GzDecoder::new(body)
+            .take(max_bytes)
+            .read_to_end(&mut buf)
+            .context("Failed to decompress gzip sitemap")?;
+        inflated = buf;
+        &inflated[..]
+    } else {
+        body
+    };
+
+    let (text, _) = charset::decode(body, content_type);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with('<') {
+        parse_sitemap(text)
+    } else {
+        Ok(parse_text_sitemap(text))
+    }
+}
+
+/// Sitemap URLs listed on `Sitemap:` lines of robots.txt
+pub fn robots_sitemaps(robots_txt: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    robots_txt
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| key.trim().eq_ignore_ascii_case("sitemap"))
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|url| !url.is_empty() && seen.insert(url.clone()))
+        .collect()
+}
+
+/// Parse a W3C Datetime `lastmod` value (a date, or a date and time with offset)
+pub fn parse_lastmod(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    // W3C Datetime also allows minutes without seconds
+    let offset = value.strip_suffix('Z').map_or_else(|| value.to_string(), |time| format!("{}+00:00", time));
+    if let Ok(time) = DateTime::parse_from_str(&offset, "%Y-%m-%dT%H:%M%:z") {
+        return Some(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+/// Limits and filters for collecting sitemap entries
+#[derive(Debug, Clone)]
+pub struct SitemapOptions {
+    /// Levels of nested sitemap indexes followed (0 reads only the first sitemap)
+    pub max_depth: usize,
+
+    /// Sitemap files fetched
+    pub max_sitemaps: usize,
+
+    /// URL entries returned
+    pub max_urls: usize,
+
+    /// Keep entries modified at or after this time
+    pub lastmod_after: Option<DateTime<Utc>>,
+
+    /// Keep entries modified at or before this time
+    pub lastmod_before: Option<DateTime<Utc>>,
+
+    /// Keep entries whose URL matches
+    pub pattern: Option<Regex>,
+
+    /// Options for each sitemap request
+    pub request: FetchOptions,
+}
+
+impl Default for SitemapOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_sitemaps: DEFAULT_MAX_SITEMAPS,
+            max_urls: DEFAULT_MAX_URLS,
+            lastmod_after: None,
+            lastmod_before: None,
+            pattern: None,
+            request: FetchOptions::default(),
+        }
+    }
+}
+
+impl SitemapOptions {
+    fn filters_lastmod(&self) -> bool {
+        self.lastmod_after.is_some() || self.lastmod_before.is_some()
+    }
+
+    // Entries without a readable lastmod are dropped when a range is set
+    fn keeps(&self, url: &SitemapUrl) -> bool {
+        if self.filters_lastmod() {
+            let Some(lastmod) = url.lastmod.as_deref().and_then(parse_lastmod) else {
+                return false;
+            };
+            if self.lastmod_after.is_some_and(|after| lastmod < after)
+                || self.lastmod_before.is_some_and(|before| lastmod > before)
+            {
+                return false;
+            }
+        }
+        self.pattern.as_ref().is_none_or(|pattern| pattern.is_match(&url.loc))
+    }
+
+    // A child sitemap last changed before the range can't hold entries inside it
+    fn skips_child(&self, child: &SitemapIndexEntry) -> bool {
+        let lastmod = child.lastmod.as_deref().and_then(parse_lastmod);
+        matches!((lastmod, self.lastmod_after), (Some(lastmod), Some(after)) if lastmod < after)
+    }
+}
+
+/// One sitemap file read while collecting
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SitemapSource {
+    /// Sitemap URL
+    pub url: String,
+
+    /// Nesting depth below the first sitemap
+    pub depth: usize,
+
+    /// Type of sitemap (urlset, sitemapindex or text); absent when the fetch failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sitemap_type: Option<String>,
+
+    /// URL entries in this file, before filtering
+    pub urls: usize,
+
+    /// Child sitemaps listed in this file
+    pub sitemaps: usize,
+
+    /// Why this file could not be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+}
+
+/// URL entries collected from one or more sitemaps
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SitemapResult {
+    /// Sitemaps found through robots.txt or common paths (empty when a sitemap URL was given)
+    pub discovered: Vec<String>,
+
+    /// Every sitemap file read, in order
+    pub sitemaps: Vec<SitemapSource>,
+
+    /// URL entries that passed the filters
+    pub urls: Vec<SitemapUrl>,
+
+    /// URL entries read, before filtering
+    pub total_urls: usize,
+
+    /// Child sitemaps not fetched because of the depth or sitemap limits
+    pub skipped_sitemaps: usize,
+
+    /// Whether `max_urls` cut the result short
+    pub truncated: bool,
+}
+
+/// Fetch a sitemap and the sitemaps nested under it
+///
+/// Fails when `url` itself can't be fetched or parsed; failures of nested
+/// sitemaps are reported on their [`SitemapSource`].
+pub async fn collect_sitemaps(
+    fetcher: &Fetcher,
+    policy: &DestinationPolicy,
+    url: &str,
+    options: &SitemapOptions,
+) -> Result<SitemapResult> {
+    let data = fetch_sitemap_file(fetcher, url, &options.request).await?;
+    Ok(collect(fetcher, policy, vec![(url.to_string(), Some(data))], Vec::new(), options).await)
+}
+
+/// Find the sitemaps of a site and collect their entries
+///
+/// Sitemaps come from the `Sitemap:` lines of the site's robots.txt; without
+/// any, [`COMMON_SITEMAP_PATHS`] are probed and the first that parses is used.
+pub async fn discover_sitemaps(
+    fetcher: &Fetcher,
+    policy: &DestinationPolicy,
+    origin: &str,
+    options: &SitemapOptions,
+) -> Result<SitemapResult> {
+    let origin = origin.trim_end_matches('/');
+    let listed = fetch_robots_sitemaps(fetcher, origin).await;
+    if !listed.is_empty() {
+        tracing::debug!("robots.txt at {} lists {} sitemaps", origin, listed.len());
+        let roots = listed.iter().map(|url| (url.clone(), None)).collect();
+        return Ok(collect(fetcher, policy, roots, listed, options).await);
+    }
+
+    for path in COMMON_SITEMAP_PATHS {
+        let url = format!("{}{}", origin, path);
+        match fetch_sitemap_file(fetcher, &url, &options.request).await {
+            Ok(data) if data.sitemap_type != "unknown" && !(data.urls.is_empty() && data.sitemaps.is_empty()) => {
+                return Ok(collect(fetcher, policy, vec![(url.clone(), Some(data))], vec![url], options).await);
+            }
+            Ok(_) => tracing::debug!("{} is not a sitemap", url),
+            Err(err) => tracing::debug!("No sitemap at {}: {:#}", url, err),
+        }
+    }
+
+    anyhow::bail!("No sitemap found for {} in robots.txt or at {}", origin, COMMON_SITEMAP_PATHS.join(", "))
+}
+
+// `Sitemap:` lines of robots.txt; a missing or unreadable file lists none
+async fn fetch_robots_sitemaps(fetcher: &Fetcher, origin: &str) -> Vec<String> {
+    let robots_url = format!("{}/robots.txt", origin);
+    let response = match fetcher.send(fetcher.client.get(&robots_url), &robots_url, None).await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::debug!("robots.txt at {} returned {}", robots_url, response.status());
+            return Vec::new();
+        }
+        Err(err) => {
+            tracing::debug!("Failed to fetch {}: {:#}", robots_url, err);
+            return Vec::new();
+        }
+    };
+    match fetch::read_body(response, robots::MAX_ROBOTS_BYTES).await {
+        Ok((body, _)) => robots_sitemaps(&String::from_utf8_lossy(&body)),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Listed sitemaps must pass the same checks as URLs passed to tools
+fn check_destination(policy: &DestinationPolicy, url: &str) -> Result<()> {
+    let url = validation::validate_url(url, &policy.access)?;
+    policy.check_url(&url)
+}
+
+async fn fetch_sitemap_file(fetcher: &Fetcher, url: &str, options: &FetchOptions) -> Result<SitemapData> {
+    let (body, info) = fetch::fetch_url_bytes(fetcher, url, options).await?;
+    let content_type = info.headers.get("content-type").map(String::as_str);
+    parse_sitemap_body(&body, content_type, fetcher.body_limit(options))
+        .with_context(|| format!("Failed to parse sitemap {}", url))
+}
+
+// Breadth-first over sitemap indexes; roots may come already fetched
+async fn collect(
+    fetcher: &Fetcher,
+    policy: &DestinationPolicy,
+    roots: Vec<(String, Option<SitemapData>)>,
+    discovered: Vec<String>,
+    options: &SitemapOptions,
+) -> SitemapResult {
+    let mut result = SitemapResult {
+        discovered,
+        sitemaps: Vec::new(),
+        urls: Vec::new(),
+        total_urls: 0,
+        skipped_sitemaps: 0,
+        truncated: false,
+    };
+    let mut seen: HashSet<String> = roots.iter().map(|(url, _)| url.clone()).collect();
+    let mut queue: VecDeque<_> = roots.into_iter().map(|(url, data)| (url, 0, data)).collect();
+
+    while let Some((url, depth, data)) = queue.pop_front() {
+        if result.sitemaps.len() >= options.max_sitemaps || result.truncated {
+            result.skipped_sitemaps += queue.len() + 1;
+            break;
+        }
+
+        let mut source = SitemapSource {
+            url: url.clone(),
+            depth,
+            sitemap_type: None,
+            urls: 0,
+            sitemaps: 0,
+            error: None,
+            error_code: None,
+        };
+        let data = match data {
+            Some(data) => Ok(data),
+            None => match check_destination(policy, &url) {
+                Ok(()) => fetch_sitemap_file(fetcher, &url, &options.request).await,
+                Err(err) => Err(err),
+            },
+        };
+        let data = match data {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::debug!("Sitemap {} failed: {:#}", url, err);
+                // Fetch failures carry a typed cause; the rest are parse errors or refused URLs
+                let failure = ToolError::classify(ErrorCode::Parse, "Failed to read sitemap", &err);
+                source.error = Some(failure.message);
+                source.error_code = Some(failure.code);
+                result.sitemaps.push(source);
+                continue;
+            }
+        };
+
+        source.sitemap_type = Some(data.sitemap_type);
+        source.urls = data.urls.len();
+        source.sitemaps = data.sitemaps.len();
+        result.sitemaps.push(source);
+        result.total_urls += data.urls.len();
+
+        for entry in data.urls {
+            if !options.keeps(&entry) {
+                continue;
+            }
+            if result.urls.len() >= options.max_urls {
+                result.truncated = true;
+                break;
+            }
+            result.urls.push(entry);
+        }
+
+        for child in data.sitemaps {
+            if options.skips_child(&child) || !seen.insert(child.loc.clone()) {
+                continue;
+            }
+            if depth >= options.max_depth {
+                result.skipped_sitemaps += 1;
+            } else {
+                queue.push_back((child.loc, depth + 1, None));
+            }
+        }
+    }
+
+    tracing::info!(
+        "Read {} sitemaps: {} of {} URLs kept",
+        result.sitemaps.len(),
+        result.urls.len(),
+        result.total_urls
+    );
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
 
     #[test]
     fn test_parse_urlset_sitemap() {
@@ -235,4 +853,188 @@ mod tests {
         assert_eq!(sitemap.urls[0].loc, "https://example.com/");
         assert_eq!(sitemap.urls[0].lastmod, None);
     }
+
+    #[test]
+    fn test_parse_sitemap_extensions() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
+                xmlns:img="http://www.google.com/schemas/sitemap-image/1.1"
+                xmlns:video="http://www.google.com/schemas/sitemap-video/1.1"
+                xmlns:news="http://www.google.com/schemas/sitemap-news/0.9"
+                xmlns:xhtml="http://www.w3.org/1999/xhtml">
+            <url>
+                <loc>https://example.com/story?id=1&amp;lang=en</loc>
+                <xhtml:link rel="alternate" hreflang="de" href="https://example.com/de/story"/>
+                <xhtml:link rel="alternate" hreflang="x-default" href="https://example.com/story"/>
+                <img:image>
+                    <img:loc>https://example.com/photo.jpg</img:loc>
+                    <img:caption><![CDATA[Dogs & cats]]></img:caption>
+                </img:image>
+                <img:image><img:loc>https://example.com/photo2.jpg</img:loc></img:image>
+                <video:video>
+                    <video:thumbnail_loc>https://example.com/thumb.jpg</video:thumbnail_loc>
+                    <video:title>Grilling steaks</video:title>
+                    <video:content_loc>https://example.com/video.mp4</video:content_loc>
+                    <video:duration>600</video:duration>
+                </video:video>
+                <news:news>
+                    <news:publication>
+                        <news:name>The Example Times</news:name>
+                        <news:language>en</news:language>
+                    </news:publication>
+                    <news:publication_date>2024-06-01T12:00:00+00:00</news:publication_date>
+                    <news:title>Companies A, B in merger talks</news:title>
+                </news:news>
+            </url>
+            <url>
+                <loc>https://example.com/plain</loc>
+                <image:image><image:loc>https://example.com/undeclared.jpg</image:loc></image:image>
+            </url>
+        </urlset>"#;
+
+        let sitemap = parse_sitemap(xml).unwrap();
+        assert_eq!(sitemap.urls.len(), 2);
+
+        // Extension elements named `loc` don't overwrite the page URL
+        let story = &sitemap.urls[0];
+        assert_eq!(story.loc, "https://example.com/story?id=1&lang=en");
+        assert_eq!(story.alternates.len(), 2);
+        assert_eq!(story.alternates[0].hreflang, "de");
+        assert_eq!(story.alternates[0].href, "https://example.com/de/story");
+        assert_eq!(story.images.len(), 2);
+        assert_eq!(story.images[0].loc, "https://example.com/photo.jpg");
+        assert_eq!(story.images[0].caption.as_deref(), Some("Dogs & cats"));
+        assert_eq!(story.videos[0].title.as_deref(), Some("Grilling steaks"));
+        assert_eq!(story.videos[0].thumbnail_loc.as_deref(), Some("https://example.com/thumb.jpg"));
+        assert_eq!(story.videos[0].duration, Some(600));
+        let news = story.news.as_ref().unwrap();
+        assert_eq!(news.publication_name.as_deref(), Some("The Example Times"));
+        assert_eq!(news.publication_language.as_deref(), Some("en"));
+        assert_eq!(news.publication_date.as_deref(), Some("2024-06-01T12:00:00+00:00"));
+        assert_eq!(news.title.as_deref(), Some("Companies A, B in merger talks"));
+
+        let plain = &sitemap.urls[1];
+        assert_eq!(plain.loc, "https://example.com/plain");
+        assert_eq!(plain.images[0].loc, "https://example.com/undeclared.jpg");
+        assert!(plain.videos.is_empty() && plain.news.is_none() && plain.alternates.is_empty());
+    }
+
+    #[test]
+    fn test_parse_sitemap_body() {
+        let xml = b"<urlset><url><loc>https://example.com/a</loc></url></urlset>";
+        let sitemap = parse_sitemap_body(&gzip(xml), None, 1024).unwrap();
+        assert_eq!(sitemap.sitemap_type, "urlset");
+        assert_eq!(sitemap.urls[0].loc, "https://example.com/a");
+
+        let text = "\u{feff}https://example.com/a\n\n  https://example.com/b  \nnot a url\n";
+        let sitemap = parse_sitemap_body(text.as_bytes(), Some("text/plain"), 1024).unwrap();
+        assert_eq!(sitemap.sitemap_type, "text");
+        let locs: Vec<&str> = sitemap.urls.iter().map(|url| url.loc.as_str()).collect();
+        assert_eq!(locs, vec!["https://example.com/a", "https://example.com/b"]);
+
+        assert!(parse_sitemap_body(&[0x1f, 0x8b, 0x00], None, 1024).is_err());
+
+        let robots = "User-agent: *\nDisallow: /private\nSitemap: https://example.com/s1.xml\nsitemap:https://example.com/s2.xml.gz\nSITEMAP: https://example.com/s1.xml\n";
+        assert_eq!(robots_sitemaps(robots), vec!["https://example.com/s1.xml", "https://example.com/s2.xml.gz"]);
+
+        let date = |value: &str| parse_lastmod(value).map(|time| time.to_rfc3339());
+        assert_eq!(date("2024-01-31").as_deref(), Some("2024-01-31T00:00:00+00:00"));
+        assert_eq!(date("2024-01-31T10:20:30+02:00").as_deref(), Some("2024-01-31T08:20:30+00:00"));
+        assert_eq!(date("2024-01-31T10:20Z").as_deref(), Some("2024-01-31T10:20:00+00:00"));
+        assert_eq!(date("January 2024"), None);
+    }
+
+    #[tokio::test]
+    async fn test_collect_sitemaps() {
+        let base = test_util::serve(|request| {
+            let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+            let host = request
+                .lines()
+                .find_map(|line| line.strip_prefix("host: ").or_else(|| line.strip_prefix("Host: ")))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            let xml = |body: String| test_util::response("200 OK", &[("Content-Type", "application/xml")], body.as_bytes());
+            match path.as_str() {
+                "/robots.txt" => test_util::response("200 OK", &[], format!("Sitemap: http://{}/index.xml\n", host).as_bytes()),
+                "/index.xml" => xml(format!(
+                    "<sitemapindex>\
+                     <sitemap><loc>http://{0}/news.xml.gz</loc><lastmod>2024-06-01</lastmod></sitemap>\
+                     <sitemap><loc>http://{0}/pages.txt</loc></sitemap>\
+                     <sitemap><loc>http://{0}/old.xml</loc><lastmod>2020-01-01</lastmod></sitemap>\
+                     <sitemap><loc>http://{0}/nested.xml</loc></sitemap>\
+                     <sitemap><loc>http://{0}/missing.xml</loc></sitemap>\
+                     </sitemapindex>",
+                    host
+                )),
+                "/news.xml.gz" => test_util::response(
+                    "200 OK",
+                    &[("Content-Type", "application/gzip")],
+                    &gzip(
+                        b"<urlset>\
+                          <url><loc>https://example.com/news/1</loc><lastmod>2024-05-01</lastmod></url>\
+                          <url><loc>https://example.com/news/2</loc><lastmod>2024-06-01T12:00:00Z</lastmod></url>\
+                          <url><loc>https://example.com/about</loc><lastmod>2024-06-01</lastmod></url>\
+                          </urlset>",
+                    ),
+                ),
+                "/pages.txt" => test_util::response("200 OK", &[], b"https://example.com/\nhttps://example.com/news/3\n"),
+                "/old.xml" => xml("<urlset><url><loc>https://example.com/news/0</loc></url></urlset>".to_string()),
+                "/nested.xml" => xml(format!("<sitemapindex><sitemap><loc>http://{}/deep.xml</loc></sitemap></sitemapindex>", host)),
+                "/deep.xml" => xml("<urlset><url><loc>https://example.com/news/deep</loc></url></urlset>".to_string()),
+                _ => test_util::response("404 Not Found", &[], b"missing"),
+            }
+        })
+        .await;
+        let fetcher = test_util::loopback_fetcher(None);
+        let policy = test_util::loopback_policy();
+
+        // A site is discovered through robots.txt and every nested sitemap is read
+        let result = discover_sitemaps(&fetcher, &policy, &base, &SitemapOptions::default()).await.unwrap();
+        assert_eq!(result.discovered, vec![format!("{}/index.xml", base)]);
+        assert_eq!(result.sitemaps.len(), 7);
+        assert_eq!(result.total_urls, 7);
+        assert_eq!(result.urls.len(), 7);
+        let missing = result.sitemaps.iter().find(|source| source.url.ends_with("/missing.xml")).unwrap();
+        assert_eq!(missing.error_code, Some(ErrorCode::HttpStatus));
+        let deep = result.sitemaps.iter().find(|source| source.url.ends_with("/deep.xml")).unwrap();
+        assert_eq!(deep.depth, 2);
+        let news = result.sitemaps.iter().find(|source| source.url.ends_with("/news.xml.gz")).unwrap();
+        assert_eq!(news.sitemap_type.as_deref(), Some("urlset"));
+        assert_eq!(news.urls, 3);
+
+        // Filters drop undated entries and skip sitemaps older than the range
+        let options = SitemapOptions {
+            max_depth: 1,
+            lastmod_after: parse_lastmod("2024-05-15"),
+            pattern: Some(Regex::new("/news/").unwrap()),
+            ..Default::default()
+        };
+        let result = collect_sitemaps(&fetcher, &policy, &format!("{}/index.xml", base), &options).await.unwrap();
+        let locs: Vec<&str> = result.urls.iter().map(|url| url.loc.as_str()).collect();
+        assert_eq!(locs, vec!["https://example.com/news/2"]);
+        assert!(!result.sitemaps.iter().any(|source| source.url.ends_with("/old.xml")));
+        assert_eq!(result.skipped_sitemaps, 1);
+        assert!(result.discovered.is_empty());
+
+        // The URL cap truncates the result
+        let options = SitemapOptions { max_urls: 2, ..Default::default() };
+        let result = collect_sitemaps(&fetcher, &policy, &format!("{}/index.xml", base), &options).await.unwrap();
+        assert_eq!(result.urls.len(), 2);
+        assert!(result.truncated);
+
+        // Without robots.txt entries, common paths are probed
+        let bare = test_util::serve(|request| match request.split_whitespace().nth(1) {
+            Some("/sitemap.txt") => test_util::response("200 OK", &[], b"https://example.com/only\n"),
+            _ => test_util::response("404 Not Found", &[], b"missing"),
+        })
+        .await;
+        let result = discover_sitemaps(&fetcher, &policy, &bare, &SitemapOptions::default()).await.unwrap();
+        assert_eq!(result.discovered, vec![format!("{}/sitemap.txt", bare)]);
+        assert_eq!(result.urls[0].loc, "https://example.com/only");
+
+        let empty = test_util::serve(|_| test_util::response("404 Not Found", &[], b"missing")).await;
+        assert!(discover_sitemaps(&fetcher, &policy, &empty, &SitemapOptions::default()).await.is_err());
+        assert!(collect_sitemaps(&fetcher, &policy, &format!("{}/sitemap.xml", empty), &options).await.is_err());
+    }
 }
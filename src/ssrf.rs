@@ -0,0 +1,522 @@
+use anyhow::{bail, Context, Result};
+use ipnet::IpNet;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::{Host, Url};
+
+use crate::domains::{AccessPolicy, PolicyDenied};
+use crate::validation;
+
+/// Destination policy applied to every outgoing connection
+///
+/// Private, loopback, link-local, CGNAT, ULA and cloud metadata addresses are
+/// denied unless explicitly allowed by CIDR or hostname. The domain allow and
+/// deny lists are applied on top, to public destinations too.
+#[derive(Debug, Clone, Default)]
+pub struct DestinationPolicy {
+    /// CIDR ranges that may be reached even if they are restricted
+    pub allowed_cidrs: Vec<IpNet>,
+
+    /// Hostnames exempt from the address check (exact or `*.suffix`)
+    pub allowed_hosts: Vec<String>,
+
+    /// Domain allow and deny lists
+    pub access: AccessPolicy,
+
+    /// Targets checked for the proxy (connections go direct when `None`)
+    pub proxy: Option<Arc<ProxyApprovals>>,
+}
+
+/// How long a target checked for the proxy stays approved
+const PROXY_APPROVAL_TTL: Duration = Duration::from_secs(60);
+
+/// Approvals kept before expired ones are dropped
+const MAX_PROXY_APPROVALS: usize = 1024;
+
+/// Targets (`host:port`) that passed the policy and may be sent through the proxy
+#[derive(Debug, Default)]
+pub struct ProxyApprovals {
+    approved: Mutex<HashMap<String, Instant>>,
+}
+
+impl ProxyApprovals {
+    fn is_approved(&self, key: &str) -> bool {
+        let approved = self.approved.lock().unwrap_or_else(|e| e.into_inner());
+        approved.get(key).is_some_and(|at| at.elapsed() < PROXY_APPROVAL_TTL)
+    }
+
+    fn approve(&self, key: String) {
+        let mut approved = self.approved.lock().unwrap_or_else(|e| e.into_inner());
+        if approved.len() >= MAX_PROXY_APPROVALS {
+            approved.retain(|_, at| at.elapsed() < PROXY_APPROVAL_TTL);
+        }
+        approved.insert(key, Instant::now());
+    }
+}
+
+// Approval key of a proxied target
+fn proxy_key(url: &Url) -> Option<String> {
+    Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?))
+}
+
+impl DestinationPolicy {
+    /// Build policy from CLI allowlist values
+    pub fn new(allowed_cidrs: &[String], allowed_hosts: &[String]) -> Result<Self> {
+        let allowed_cidrs = allowed_cidrs
+            .iter()
+            .map(|cidr| parse_cidr(cidr))
+            .collect::<Result<Vec<_>>>()?;
+
+        let allowed_hosts = allowed_hosts
+            .iter()
+            .map(|host| host.trim().trim_end_matches('.').to_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect();
+
+        Ok(Self {
+            allowed_cidrs,
+            allowed_hosts,
+            access: AccessPolicy::default(),
+            proxy: None,
+        })
+    }
+
+    /// Connections go through a proxy, so targets are checked before sending
+    pub fn with_proxy(mut self) -> Self {
+        self.proxy = Some(Arc::default());
+        self
+    }
+
+    /// Apply domain allow and deny lists
+    pub fn with_access(mut self, access: AccessPolicy) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Check if hostname is on the allowlist
+    pub fn is_host_allowed(&self, host: &str) -> bool {
+        self.allowed_hosts
+            .iter()
+            .any(|pattern| validation::host_matches(pattern, host))
+    }
+
+    /// Check if an IP address may be connected to
+    pub fn is_ip_allowed(&self, ip: IpAddr) -> bool {
+        restricted_range(ip).is_none() || self.allowed_cidrs.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Check URL host against the policy
+    ///
+    /// The domain lists are checked first. IP literals are checked here because
+    /// they never reach the DNS resolver; hostnames are checked after
+    /// resolution by [`GuardedResolver`].
+    pub fn check_url(&self, url: &str) -> Result<()> {
+        let parsed = Url::parse(url).context("Invalid URL")?;
+        if let Some(host) = parsed.host_str() {
+            self.access.check_host(host)?;
+        }
+
+        match parsed.host() {
+            Some(Host::Ipv4(ip)) => self.check_ip(IpAddr::V4(ip), url),
+            Some(Host::Ipv6(ip)) => self.check_ip(IpAddr::V6(ip), url),
+            Some(Host::Domain(_)) => Ok(()),
+            None => bail!("URL must have a host"),
+        }
+    }
+
+    /// Check a destination that will be reached through the proxy and approve it
+    ///
+    /// The proxy resolves target names itself, so [`GuardedResolver`] never
+    /// sees them. The name is resolved here instead and every address must
+    /// pass the policy. Approvals are kept for a minute.
+    pub async fn approve_proxied(&self, url: &Url) -> Result<()> {
+        let (Some(approvals), Some(key)) = (&self.proxy, proxy_key(url)) else {
+            return Ok(());
+        };
+        if approvals.is_approved(&key) {
+            return Ok(());
+        }
+        self.check_url(url.as_str())?;
+
+        if let Some(Host::Domain(host)) = url.host() {
+            let resolved: Vec<SocketAddr> = tokio::net::lookup_host(key.as_str())
+                .await
+                .with_context(|| format!("Failed to resolve {} to check it before proxying", host))?
+                .collect();
+            if resolved.is_empty() {
+                bail!("{} did not resolve to any address", host);
+            }
+
+            let host_allowed = self.is_host_allowed(host);
+            for addr in resolved {
+                self.access.check_addr(host, addr.ip())?;
+                if !host_allowed && !self.is_ip_allowed(addr.ip()) {
+                    let reason = restricted_range(addr.ip()).unwrap_or("restricted");
+                    return Err(BlockedDestination { host: host.to_string(), reason }.into());
+                }
+            }
+        }
+
+        approvals.approve(key);
+        Ok(())
+    }
+
+    /// Target was approved by [`DestinationPolicy::approve_proxied`], or no proxy is used
+    pub fn is_proxy_approved(&self, url: &Url) -> bool {
+        match (&self.proxy, proxy_key(url)) {
+            (Some(approvals), Some(key)) => approvals.is_approved(&key),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    fn check_ip(&self, ip: IpAddr, url: &str) -> Result<()> {
+        if self.is_ip_allowed(ip) {
+            return Ok(());
+        }
+        bail!(
+            "Destination {} for {} is a {} address and is blocked by the SSRF policy",
+            ip,
+            url,
+            restricted_range(ip).unwrap_or("restricted")
+        )
+    }
+}
+
+/// Parse CIDR, accepting bare addresses as single-host ranges
+pub fn parse_cidr(value: &str) -> Result<IpNet> {
+    let value = value.trim();
+    if let Ok(net) = value.parse::<IpNet>() {
+        return Ok(net);
+    }
+    let ip = value
+        .parse::<IpAddr>()
+        .with_context(|| format!("Invalid CIDR or IP address: {}", value))?;
+    Ok(IpNet::from(ip))
+}
+
+/// Classify an address that must not be reached by default
+///
+/// Returns the name of the restricted range, or `None` for public addresses.
+pub fn restricted_range(ip: IpAddr) -> Option<&'static str> {
+    match ip {
+        IpAddr::V4(ip) => restricted_v4(ip),
+        IpAddr::V6(ip) => restricted_v6(ip),
+    }
+}
+
+fn restricted_v4(ip: Ipv4Addr) -> Option<&'static str> {
+    let [a, b, c, _] = ip.octets();
+
+    if ip == Ipv4Addr::new(169, 254, 169, 254) || ip == Ipv4Addr::new(100, 100, 100, 200) {
+        return Some("cloud metadata");
+    }
+
+    match (a, b, c) {
+        (0, _, _) => Some("unspecified"),
+        (10, _, _) => Some("private"),
+        (100, 64..=127, _) => Some("CGNAT"),
+        (127, _, _) => Some("loopback"),
+        (169, 254, _) => Some("link-local"),
+        (172, 16..=31, _) => Some("private"),
+        (192, 0, 0) => Some("IETF protocol assignment"),
+        (192, 168, _) => Some("private"),
+        (198, 18..=19, _) => Some("benchmarking"),
+        (224..=239, _, _) => Some("multicast"),
+        (240..=255, _, _) => Some("reserved"),
+        _ => None,
+    }
+}
+
+fn restricted_v6(ip: Ipv6Addr) -> Option<&'static str> {
+    if ip.is_unspecified() {
+        return Some("unspecified");
+    }
+    if ip.is_loopback() {
+        return Some("loopback");
+    }
+
+    // IPv4-mapped (::ffff:a.b.c.d), IPv4-compatible (::a.b.c.d), NAT64
+    // (64:ff9b::a.b.c.d) and 6to4 (2002:aabb:ccdd::) embed an IPv4 target
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return restricted_v4(v4);
+    }
+    let segments = ip.segments();
+    match segments {
+        [0, 0, 0, 0, 0, 0, hi, lo] | [0x64, 0xff9b, 0, 0, 0, 0, hi, lo] | [0x2002, hi, lo, ..] => {
+            return restricted_v4(embedded_v4(hi, lo));
+        }
+        // Teredo hides the client address; the tunnel is never a legitimate fetch target
+        [0x2001, 0, ..] => return Some("Teredo"),
+        _ => {}
+    }
+
+    match segments[0] {
+        0xfc00..=0xfdff => Some("unique local"),
+        0xfe80..=0xfebf => Some("link-local"),
+        0xfec0..=0xfeff => Some("site-local"),
+        0xff00..=0xffff => Some("multicast"),
+        _ => None,
+    }
+}
+
+// IPv4 address stored in two IPv6 segments
+fn embedded_v4(hi: u16, lo: u16) -> Ipv4Addr {
+    let [a, b] = hi.to_be_bytes();
+    let [c, d] = lo.to_be_bytes();
+    Ipv4Addr::new(a, b, c, d)
+}
+
+/// Destination refused by the policy; kept typed so it is never retried
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Host {host} resolves to a {reason} address and is blocked by the SSRF policy")]
+pub struct BlockedDestination {
+    pub host: String,
+    pub reason: &'static str,
+}
+
+/// Redirect through the proxy to a target that was not checked yet
+///
+/// The hop is refused inside the client, since the check resolves the name;
+/// [`crate::fetch::Fetcher`] checks the target and sends the request again.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Redirect to {url} has not been checked for the proxy")]
+pub struct UncheckedRedirect {
+    pub url: Url,
+}
+
+/// DNS resolver that drops addresses denied by the destination policy
+///
+/// Checking resolved addresses at connect time also covers redirect hops and
+/// DNS rebinding, since every new connection goes through this resolver.
+pub struct GuardedResolver {
+    policy: Arc<DestinationPolicy>,
+}
+
+impl GuardedResolver {
+    pub fn new(policy: Arc<DestinationPolicy>) -> Self {
+        Self { policy }
+    }
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        let host = name.as_str().to_string();
+
+        Box::pin(async move {
+            let mut resolved: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect();
+
+            // Domain lists first: CIDR rules apply to resolved addresses
+            if !policy.access.is_empty() {
+                let mut denied = None;
+                resolved.retain(|addr| match policy.access.check_addr(&host, addr.ip()) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        denied.get_or_insert(err);
+                        false
+                    }
+                });
+                if let (true, Some(denied)) = (resolved.is_empty(), denied) {
+                    tracing::warn!("Domain policy blocked {}: {}", host, denied);
+                    return Err(Box::new(denied) as _);
+                }
+            }
+
+            if policy.is_host_allowed(&host) {
+                return Ok(Box::new(resolved.into_iter()) as Addrs);
+            }
+
+            let (allowed, blocked): (Vec<SocketAddr>, Vec<SocketAddr>) = resolved
+                .into_iter()
+                .partition(|addr| policy.is_ip_allowed(addr.ip()));
+
+            if allowed.is_empty() {
+                let reason = blocked
+                    .first()
+                    .and_then(|addr| restricted_range(addr.ip()))
+                    .unwrap_or("restricted");
+                tracing::warn!("SSRF policy blocked {} ({} address)", host, reason);
+                return Err(Box::new(BlockedDestination { host, reason }) as _);
+            }
+
+            if !blocked.is_empty() {
+                tracing::debug!("Dropped {} restricted addresses for {}", blocked.len(), host);
+            }
+
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Redirect policy that re-checks every hop against the destination policy
+///
+/// With `same_origin_only`, redirects leaving the origin of the first request
+/// are refused (used for requests carrying credentials).
+pub fn redirect_policy(
+    policy: Arc<DestinationPolicy>,
+    max_redirects: usize,
+    same_origin_only: bool,
+) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= max_redirects {
+            return attempt.error(format!("Too many redirects (max {})", max_redirects));
+        }
+
+        if same_origin_only {
+            let origin = attempt.previous().first().map(|url| url.origin());
+            if origin.as_ref() != Some(&attempt.url().origin()) {
+                let message = format!(
+                    "Redirect to {} leaves the origin of an authenticated request",
+                    attempt.url()
+                );
+                return attempt.error(message);
+            }
+        }
+
+        match policy.check_url(attempt.url().as_str()) {
+            Ok(()) if !policy.is_proxy_approved(attempt.url()) => {
+                let url = attempt.url().clone();
+                attempt.error(UncheckedRedirect { url })
+            }
+            Ok(()) => attempt.follow(),
+            // Keep the typed error so the failure is reported as `policy_denied`
+            Err(e) => match e.downcast::<PolicyDenied>() {
+                Ok(denied) => attempt.error(denied),
+                Err(e) => attempt.error(e.to_string()),
+            },
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restricted_ranges() {
+        assert_eq!(restricted_range("127.0.0.1".parse().unwrap()), Some("loopback"));
+        assert_eq!(restricted_range("10.0.0.5".parse().unwrap()), Some("private"));
+        assert_eq!(restricted_range("172.20.1.1".parse().unwrap()), Some("private"));
+        assert_eq!(restricted_range("192.168.1.1".parse().unwrap()), Some("private"));
+        assert_eq!(restricted_range("100.64.0.1".parse().unwrap()), Some("CGNAT"));
+        assert_eq!(restricted_range("169.254.169.254".parse().unwrap()), Some("cloud metadata"));
+        assert_eq!(restricted_range("169.254.1.1".parse().unwrap()), Some("link-local"));
+        assert_eq!(restricted_range("::1".parse().unwrap()), Some("loopback"));
+        assert_eq!(restricted_range("fd00:ec2::254".parse().unwrap()), Some("unique local"));
+        assert_eq!(restricted_range("fe80::1".parse().unwrap()), Some("link-local"));
+        assert_eq!(restricted_range("::ffff:10.0.0.1".parse().unwrap()), Some("private"));
+        assert_eq!(restricted_range("64:ff9b::a9fe:a9fe".parse().unwrap()), Some("cloud metadata"));
+        assert_eq!(restricted_range("::127.0.0.1".parse().unwrap()), Some("loopback"));
+        assert_eq!(restricted_range("::169.254.169.254".parse().unwrap()), Some("cloud metadata"));
+        assert_eq!(restricted_range("2002:a00:1::".parse().unwrap()), Some("private"));
+        assert_eq!(restricted_range("2002:a9fe:a9fe::1".parse().unwrap()), Some("cloud metadata"));
+        assert_eq!(restricted_range("2001:0:4136:e378:8000:63bf:3fff:fdd2".parse().unwrap()), Some("Teredo"));
+        assert!(DestinationPolicy::default().check_url("http://[::10.0.0.1]/").is_err());
+
+        assert_eq!(restricted_range("93.184.216.34".parse().unwrap()), None);
+        assert_eq!(restricted_range("2606:2800:220:1::".parse().unwrap()), None);
+        assert_eq!(restricted_range("::5db8:d822".parse().unwrap()), None);
+        assert_eq!(restricted_range("2002:5db8:d822::".parse().unwrap()), None);
+        assert_eq!(restricted_range("2001:db8::1".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn test_check_url_literals() {
+        let policy = DestinationPolicy::default();
+        assert!(policy.check_url("http://169.254.169.254/latest/meta-data/").is_err());
+        assert!(policy.check_url("http://10.0.0.5:9200/").is_err());
+        assert!(policy.check_url("http://[::1]:8080/").is_err());
+        assert!(policy.check_url("http://0x7f.1/").is_err());
+        assert!(policy.check_url("https://example.com/").is_ok());
+        assert!(policy.check_url("http://93.184.216.34/").is_ok());
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let policy = DestinationPolicy::new(
+            &["10.0.0.0/8".to_string(), "192.168.1.10".to_string()],
+            &["wiki.corp".to_string(), "*.internal.example".to_string()],
+        )
+        .unwrap();
+
+        assert!(policy.check_url("http://10.1.2.3/").is_ok());
+        assert!(policy.check_url("http://192.168.1.10/").is_ok());
+        assert!(policy.check_url("http://192.168.1.11/").is_err());
+        assert!(policy.check_url("http://127.0.0.1/").is_err());
+
+        assert!(policy.is_host_allowed("wiki.corp"));
+        assert!(policy.is_host_allowed("WIKI.corp."));
+        assert!(policy.is_host_allowed("api.internal.example"));
+        assert!(!policy.is_host_allowed("internal.example"));
+        assert!(!policy.is_host_allowed("evil-wiki.corp"));
+    }
+
+    #[test]
+    fn test_invalid_cidr() {
+        assert!(DestinationPolicy::new(&["not-a-cidr".to_string()], &[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolver_blocks_loopback() {
+        let resolver = GuardedResolver::new(Arc::new(DestinationPolicy::default()));
+        let name: Name = "localhost".parse().unwrap();
+        assert!(resolver.resolve(name).await.is_err());
+
+        let policy = DestinationPolicy::new(&["127.0.0.0/8".to_string(), "::1".to_string()], &[]).unwrap();
+        let resolver = GuardedResolver::new(Arc::new(policy));
+        let name: Name = "localhost".parse().unwrap();
+        assert!(resolver.resolve(name).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_domain_policy_on_redirects_and_resolution() {
+        use crate::domains::DomainPattern;
+        use crate::error::{ErrorCode, ToolError};
+        use crate::fetch::{self, FetchOptions, Fetcher};
+        use crate::test_util;
+
+        let base = test_util::serve(|request| {
+            if request.starts_with("GET /away") {
+                test_util::response("302 Found", &[("Location", "http://tracker.blocked.example/")], b"")
+            } else {
+                test_util::response("200 OK", &[], b"ok")
+            }
+        })
+        .await;
+        let fetcher_with = |deny: &[&str]| {
+            let deny = deny.iter().map(|pattern| DomainPattern::new(pattern).unwrap()).collect();
+            let policy = DestinationPolicy::new(&["127.0.0.0/8".to_string(), "::1".to_string()], &[])
+                .unwrap()
+                .with_access(AccessPolicy::new(Vec::new(), deny));
+            let client = fetch::create_client(&fetch::ClientOptions::new("test"), Arc::new(policy)).unwrap();
+            Fetcher::new(client, None)
+        };
+
+        // A redirect hop into a denied domain
+        let fetcher = fetcher_with(&["*.blocked.example"]);
+        let err = fetch::fetch_url_text(&fetcher, &format!("{}/away", base), &FetchOptions::default())
+            .await
+            .unwrap_err();
+        let error = ToolError::classify(ErrorCode::Network, "Failed to fetch URL", &err);
+        assert_eq!(error.code, ErrorCode::PolicyDenied);
+        assert!(error.message.contains("'*.blocked.example'"), "{}", error.message);
+        assert!(!error.retryable);
+
+        // A host name resolving into a denied range, refused without retries
+        let fetcher = fetcher_with(&["127.0.0.0/8", "::1"]);
+        let port = base.rsplit(':').next().unwrap();
+        let err = fetch::fetch_url_text(&fetcher, &format!("http://localhost:{}/", port), &FetchOptions::default())
+            .await
+            .unwrap_err();
+        let error = ToolError::classify(ErrorCode::Network, "Failed to fetch URL", &err);
+        assert_eq!(error.code, ErrorCode::PolicyDenied);
+        assert!(error.message.contains("deny_domains rule"), "{}", error.message);
+        assert!(!err.is::<crate::retry::RetriesExhausted>());
+    }
+}
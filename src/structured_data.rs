@@ -0,0 +1,684 @@
+//! Schema.org structured data: JSON-LD, Microdata and RDFa
+//!
+//! Every syntax is normalized into the same [`Entity`] shape: short type names
+//! (`Product` rather than `https://schema.org/Product`), and properties as JSON
+//! with nested items as objects carrying their own `@type`. Common types also
+//! get typed convenience fields read from those properties.
+
+use scraper::{ElementRef, Html, Selector};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use url::Url;
+
+/// Syntax an entity was found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntitySource {
+    JsonLd,
+    Microdata,
+    Rdfa,
+}
+
+/// Schema.org entity found on a page
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Entity {
+    /// Types without the vocabulary prefix, e.g. `["Product"]`
+    pub types: Vec<String>,
+
+    /// Syntax the entity was found in
+    pub source: EntitySource,
+
+    /// Identifier (`@id`, `itemid` or `resource`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// All properties; repeated properties become arrays
+    pub properties: Map<String, Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product: Option<Product>,
+
+    /// Article and its subtypes (NewsArticle, BlogPosting, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub article: Option<Article>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe: Option<Recipe>,
+
+    /// Event and its subtypes (MusicEvent, SportsEvent, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<Event>,
+
+    /// Organization and common subtypes (Corporation, LocalBusiness, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization: Option<Organization>,
+
+    /// BreadcrumbList items, by position
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breadcrumbs: Option<Vec<BreadcrumbItem>>,
+
+    /// FAQPage questions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub faq: Option<Vec<FaqItem>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Product {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub brand: Option<String>,
+    pub sku: Option<String>,
+    pub image: Option<String>,
+    /// Price of the first offer (`lowPrice` for an AggregateOffer)
+    pub price: Option<String>,
+    pub price_currency: Option<String>,
+    /// Availability without the vocabulary prefix, e.g. `InStock`
+    pub availability: Option<String>,
+    pub rating_value: Option<f64>,
+    pub review_count: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Article {
+    pub headline: Option<String>,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub date_published: Option<String>,
+    pub date_modified: Option<String>,
+    pub publisher: Option<String>,
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Recipe {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub ingredients: Vec<String>,
+    /// Steps in order, flattened from HowToStep and HowToSection items
+    pub instructions: Vec<String>,
+    /// ISO 8601 durations, e.g. `PT20M`
+    pub prep_time: Option<String>,
+    pub cook_time: Option<String>,
+    pub total_time: Option<String>,
+    pub recipe_yield: Option<String>,
+    pub rating_value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Event {
+    pub name: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    /// Place name, or its address
+    pub location: Option<String>,
+    pub organizer: Option<String>,
+    /// Status without the vocabulary prefix, e.g. `EventScheduled`
+    pub event_status: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Organization {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub logo: Option<String>,
+    pub telephone: Option<String>,
+    pub email: Option<String>,
+    /// Profiles of the organization elsewhere
+    pub same_as: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BreadcrumbItem {
+    pub position: Option<u64>,
+    pub name: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FaqItem {
+    pub question: String,
+    pub answer: Option<String>,
+}
+
+/// Extract JSON-LD, Microdata and RDFa entities, in that order
+///
+/// URL-valued Microdata and RDFa properties (`href`, `src`, `data`) are
+/// resolved against `base`, the page's `<base href>` or URL.
+pub fn extract_structured_data(document: &Html, base: Option<&Url>) -> Vec<Entity> {
+    let mut entities = Vec::new();
+    extract_json_ld(document, &mut entities);
+    extract_microdata(document, base, &mut entities);
+    extract_rdfa(document, base, &mut entities);
+    entities
+}
+
+fn extract_json_ld(document: &Html, entities: &mut Vec<Entity>) {
+    let Ok(selector) = Selector::parse(r#"script[type="application/ld+json"]"#) else { return };
+    for script in document.select(&selector) {
+        let text = script.text().collect::<String>();
+        // Some sites wrap the block in an HTML comment
+        let text = text.trim().trim_start_matches("<!--").trim_end_matches("-->");
+        match serde_json::from_str::<Value>(text) {
+            Ok(value) => json_ld_nodes(value, entities),
+            Err(err) => tracing::debug!("Skipping invalid JSON-LD block: {}", err),
+        }
+    }
+}
+
+// Top-level nodes, array members and `@graph` members each become an entity
+fn json_ld_nodes(value: Value, entities: &mut Vec<Entity>) {
+    match value {
+        Value::Array(values) => values.into_iter().for_each(|value| json_ld_nodes(value, entities)),
+        Value::Object(mut map) => {
+            if let Some(graph) = map.remove("@graph") {
+                json_ld_nodes(graph, entities);
+            }
+            let Some(types) = map.remove("@type") else { return };
+            let types = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).map(short_name).collect(),
+                Value::String(name) => vec![short_name(&name)],
+                _ => return,
+            };
+            let id = map.remove("@id").and_then(|id| id.as_str().map(str::to_string));
+            map.remove("@context");
+            entities.push(Entity::new(types, EntitySource::JsonLd, id, map));
+        }
+        _ => {}
+    }
+}
+
+fn extract_microdata(document: &Html, base: Option<&Url>, entities: &mut Vec<Entity>) {
+    let Ok(selector) = Selector::parse("[itemscope]") else { return };
+    for element in document.select(&selector) {
+        // Items that are property values are nested in their parent's properties
+        if element.value().attr("itemprop").is_some() {
+            continue;
+        }
+        let (types, id, properties) = item(element, &MICRODATA, base);
+        entities.push(Entity::new(types, EntitySource::Microdata, id, properties));
+    }
+}
+
+fn extract_rdfa(document: &Html, base: Option<&Url>, entities: &mut Vec<Entity>) {
+    let Ok(selector) = Selector::parse("[typeof]") else { return };
+    for element in document.select(&selector) {
+        if element.value().attr("property").is_some() {
+            continue;
+        }
+        let (types, id, properties) = item(element, &RDFA, base);
+        entities.push(Entity::new(types, EntitySource::Rdfa, id, properties));
+    }
+}
+
+/// Attributes of an HTML syntax for items
+struct Syntax {
+    /// Starts an item
+    scope: &'static str,
+    /// Holds the item's types
+    types: &'static str,
+    /// Names the properties an element holds
+    property: &'static str,
+    /// Item identifiers
+    ids: &'static [&'static str],
+}
+
+const MICRODATA: Syntax = Syntax {
+    scope: "itemscope",
+    types: "itemtype",
+    property: "itemprop",
+    ids: &["itemid"],
+};
+
+const RDFA: Syntax = Syntax {
+    scope: "typeof",
+    types: "typeof",
+    property: "property",
+    ids: &["resource", "about"],
+};
+
+impl Syntax {
+    fn starts_item(&self, element: ElementRef) -> bool {
+        element.value().attr(self.scope).is_some()
+    }
+}
+
+fn item(element: ElementRef, syntax: &Syntax, base: Option<&Url>) -> (Vec<String>, Option<String>, Map<String, Value>) {
+    let types = element
+        .value()
+        .attr(syntax.types)
+        .map(|types| types.split_whitespace().map(short_name).collect())
+        .unwrap_or_default();
+    let id = syntax.ids.iter().find_map(|attr| element.value().attr(attr)).map(str::to_string);
+    let mut properties = Map::new();
+    item_properties(element, syntax, base, &mut properties);
+    (types, id, properties)
+}
+
+// Properties below `parent`, stopping at nested items
+fn item_properties(parent: ElementRef, syntax: &Syntax, base: Option<&Url>, properties: &mut Map<String, Value>) {
+    for child in parent.children().filter_map(ElementRef::wrap) {
+        let nested = syntax.starts_item(child);
+        if let Some(names) = child.value().attr(syntax.property) {
+            let value = if nested {
+                let (types, id, mut nested_properties) = item(child, syntax, base);
+                match types.as_slice() {
+                    [] => {}
+                    [name] => {
+                        nested_properties.insert("@type".to_string(), Value::String(name.clone()));
+                    }
+                    _ => {
+                        nested_properties.insert("@type".to_string(), types.into_iter().map(Value::String).collect());
+                    }
+                }
+                if let Some(id) = id {
+                    nested_properties.insert("@id".to_string(), Value::String(id));
+                }
+                Value::Object(nested_properties)
+            } else {
+                Value::String(property_value(child, base))
+            };
+            for name in names.split_whitespace() {
+                add_property(properties, short_name(name), value.clone());
+            }
+        }
+        if !nested {
+            item_properties(child, syntax, base, properties);
+        }
+    }
+}
+
+/// Value of a property element per the Microdata rules (RDFa Lite is the same in practice)
+fn property_value(element: ElementRef, base: Option<&Url>) -> String {
+    let el = element.value();
+    let link = match el.name() {
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => el.attr("src"),
+        "a" | "area" | "link" => el.attr("href"),
+        "object" => el.attr("data"),
+        _ => None,
+    };
+    if let Some(link) = link {
+        return match base.and_then(|base| base.join(link.trim()).ok()) {
+            Some(url) => url.to_string(),
+            None => link.to_string(),
+        };
+    }
+    let attr = match el.name() {
+        "meta" => el.attr("content"),
+        "data" | "meter" => el.attr("value"),
+        "time" => el.attr("datetime"),
+        _ => None,
+    };
+    attr.or_else(|| el.attr("content"))
+        .map(str::to_string)
+        .unwrap_or_else(|| element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn add_property(properties: &mut Map<String, Value>, name: String, value: Value) {
+    match properties.get_mut(&name) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+        None => {
+            properties.insert(name, value);
+        }
+    }
+}
+
+/// `https://schema.org/Product`, `schema:Product` and `Product` are all `Product`
+fn short_name(name: &str) -> String {
+    let name = name.trim();
+    let name = if name.contains("://") {
+        name.rsplit(['/', '#']).next().unwrap_or(name)
+    } else {
+        name.rsplit(':').next().unwrap_or(name)
+    };
+    name.to_string()
+}
+
+impl Entity {
+    fn new(types: Vec<String>, source: EntitySource, id: Option<String>, properties: Map<String, Value>) -> Self {
+        let is = |names: &[&str]| types.iter().any(|name| names.contains(&name.as_str()));
+        let get = |key: &str| properties.get(key);
+
+        let product = is(&["Product", "ProductModel", "IndividualProduct"]).then(|| {
+            let offer = get("offers").map(first);
+            let offer_field = |key: &str| offer.and_then(|offer| offer.get(key));
+            let rating = get("aggregateRating").map(first);
+            let rating_field = |key: &str| rating.and_then(|rating| rating.get(key));
+            Product {
+                name: text(get("name")),
+                description: text(get("description")),
+                brand: text(get("brand")),
+                sku: text(get("sku")),
+                image: link(get("image")),
+                price: text(offer_field("price")).or_else(|| text(offer_field("lowPrice"))),
+                price_currency: text(offer_field("priceCurrency")),
+                availability: text(offer_field("availability")).map(|value| short_name(&value)),
+                rating_value: number(rating_field("ratingValue")),
+                review_count: number(rating_field("reviewCount"))
+                    .or_else(|| number(rating_field("ratingCount")))
+                    .map(|count| count as u64),
+            }
+        });
+
+        let article = is(&[
+            "Article",
+            "NewsArticle",
+            "BlogPosting",
+            "TechArticle",
+            "ScholarlyArticle",
+            "Report",
+            "SocialMediaPosting",
+            "LiveBlogPosting",
+        ])
+        .then(|| Article {
+            headline: text(get("headline")).or_else(|| text(get("name"))),
+            description: text(get("description")),
+            authors: texts(get("author")),
+            date_published: text(get("datePublished")),
+            date_modified: text(get("dateModified")),
+            publisher: text(get("publisher")),
+            image: link(get("image")),
+        });
+
+        let recipe = is(&["Recipe"]).then(|| {
+            let mut instructions = Vec::new();
+            if let Some(value) = get("recipeInstructions") {
+                recipe_steps(value, &mut instructions);
+            }
+            Recipe {
+                name: text(get("name")),
+                description: text(get("description")),
+                ingredients: texts(get("recipeIngredient")).into_iter().chain(texts(get("ingredients"))).collect(),
+                instructions,
+                prep_time: text(get("prepTime")),
+                cook_time: text(get("cookTime")),
+                total_time: text(get("totalTime")),
+                recipe_yield: text(get("recipeYield")),
+                rating_value: number(get("aggregateRating").map(first).and_then(|rating| rating.get("ratingValue"))),
+            }
+        });
+
+        let event = types.iter().any(|name| name.ends_with("Event")).then(|| Event {
+            name: text(get("name")),
+            start_date: text(get("startDate")),
+            end_date: text(get("endDate")),
+            location: get("location").map(first).and_then(place),
+            organizer: text(get("organizer")),
+            event_status: text(get("eventStatus")).map(|value| short_name(&value)),
+            url: link(get("url")),
+        });
+
+        let organization = is(&[
+            "Organization",
+            "Corporation",
+            "LocalBusiness",
+            "NewsMediaOrganization",
+            "EducationalOrganization",
+            "OnlineBusiness",
+            "NGO",
+        ])
+        .then(|| Organization {
+            name: text(get("name")),
+            url: link(get("url")),
+            logo: link(get("logo")),
+            telephone: text(get("telephone")),
+            email: text(get("email")),
+            same_as: texts(get("sameAs")),
+        });
+
+        let breadcrumbs = is(&["BreadcrumbList"]).then(|| {
+            let mut items: Vec<BreadcrumbItem> = list(get("itemListElement"))
+                .iter()
+                .map(|element| {
+                    let item = element.get("item");
+                    BreadcrumbItem {
+                        position: number(element.get("position")).map(|position| position as u64),
+                        name: text(element.get("name")).or_else(|| text(item.and_then(|item| item.get("name")))),
+                        url: link(item).or_else(|| link(element.get("url"))),
+                    }
+                })
+                .collect();
+            items.sort_by_key(|item| item.position.unwrap_or(u64::MAX));
+            items
+        });
+
+        let faq = is(&["FAQPage"]).then(|| {
+            list(get("mainEntity"))
+                .iter()
+                .filter_map(|question| {
+                    Some(FaqItem {
+                        question: text(question.get("name"))?,
+                        answer: question.get("acceptedAnswer").map(first).and_then(|answer| text(answer.get("text"))),
+                    })
+                })
+                .collect()
+        });
+
+        Self {
+            types,
+            source,
+            id,
+            properties,
+            product,
+            article,
+            recipe,
+            event,
+            organization,
+            breadcrumbs,
+            faq,
+        }
+    }
+}
+
+// The first of repeated values
+fn first(value: &Value) -> &Value {
+    match value {
+        Value::Array(values) => values.first().unwrap_or(value),
+        _ => value,
+    }
+}
+
+fn list(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(Value::Array(values)) => values.iter().collect(),
+        Some(value) => vec![value],
+        None => Vec::new(),
+    }
+}
+
+/// Text of a value; items are represented by their name
+fn text(value: Option<&Value>) -> Option<String> {
+    match first(value?) {
+        Value::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Object(map) => text(map.get("name")).or_else(|| text(map.get("@value"))),
+        _ => None,
+    }
+}
+
+fn texts(value: Option<&Value>) -> Vec<String> {
+    list(value).into_iter().filter_map(|value| text(Some(value))).collect()
+}
+
+/// URL of a value; items are represented by their `url` or `@id`
+fn link(value: Option<&Value>) -> Option<String> {
+    match first(value?) {
+        Value::String(url) => Some(url.trim().to_string()).filter(|url| !url.is_empty()),
+        Value::Object(map) => link(map.get("url")).or_else(|| link(map.get("@id"))).or_else(|| link(map.get("contentUrl"))),
+        _ => None,
+    }
+}
+
+fn number(value: Option<&Value>) -> Option<f64> {
+    match first(value?) {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Place name, or its address joined into one line
+fn place(value: &Value) -> Option<String> {
+    if let Some(name) = text(value.get("name")) {
+        return Some(name);
+    }
+    let address = value.get("address").map(first).unwrap_or(value);
+    match address {
+        Value::Object(map) => {
+            let parts: Vec<String> = ["streetAddress", "addressLocality", "addressRegion", "postalCode", "addressCountry"]
+                .iter()
+                .filter_map(|key| text(map.get(*key)))
+                .collect();
+            (!parts.is_empty()).then(|| parts.join(", "))
+        }
+        _ => text(Some(address)),
+    }
+}
+
+// recipeInstructions: text, HowToStep items, or HowToSection items holding steps
+fn recipe_steps(value: &Value, steps: &mut Vec<String>) {
+    match value {
+        Value::String(text) => steps.extend(text.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string)),
+        Value::Array(values) => values.iter().for_each(|value| recipe_steps(value, steps)),
+        Value::Object(map) => {
+            if let Some(items) = map.get("itemListElement") {
+                recipe_steps(items, steps);
+            } else if let Some(step) = text(map.get("text")).or_else(|| text(map.get("name"))) {
+                steps.push(step);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_ld() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">
+            {
+                "@context": "https://schema.org",
+                "@graph": [
+                    {"@type": "Organization", "@id": "https://example.com/#org", "name": "Example Inc",
+                     "logo": {"@type": "ImageObject", "url": "https://example.com/logo.png"},
+                     "sameAs": ["https://x.com/example", "https://github.com/example"]},
+                    {"@type": "BreadcrumbList", "itemListElement": [
+                        {"@type": "ListItem", "position": 2, "name": "Books", "item": "https://example.com/books"},
+                        {"@type": "ListItem", "position": 1, "item": {"@id": "https://example.com/", "name": "Home"}}
+                    ]},
+                    {"@type": ["NewsArticle"], "headline": "Big news", "datePublished": "2024-06-01",
+                     "author": [{"@type": "Person", "name": "Ann"}, {"@type": "Person", "name": "Bob"}],
+                     "publisher": {"@id": "https://example.com/#org", "name": "Example Inc"}}
+                ]
+            }
+            </script>
+            <script type="application/ld+json">
+            [{"@context": "https://schema.org", "@type": "FAQPage", "mainEntity": [
+                {"@type": "Question", "name": "Why?", "acceptedAnswer": {"@type": "Answer", "text": "Because."}}
+            ]},
+            {"@type": "Recipe", "name": "Pancakes", "recipeIngredient": ["Flour", "Milk"], "totalTime": "PT20M",
+             "recipeInstructions": [{"@type": "HowToSection", "itemListElement": [
+                {"@type": "HowToStep", "text": "Mix."}, {"@type": "HowToStep", "text": "Fry."}]}]},
+            {"@type": "MusicEvent", "name": "Concert", "startDate": "2025-05-01T20:00",
+             "location": {"@type": "Place", "address": {"@type": "PostalAddress", "addressLocality": "Berlin", "addressCountry": "DE"}},
+             "eventStatus": "https://schema.org/EventScheduled"}]
+            </script>
+            <script type="application/ld+json">{ not json </script>
+        </head><body></body></html>"#;
+
+        let entities = extract_structured_data(&Html::parse_document(html), None);
+        assert_eq!(entities.len(), 6);
+        assert!(entities.iter().all(|entity| entity.source == EntitySource::JsonLd));
+
+        let org = entities[0].organization.as_ref().unwrap();
+        assert_eq!(entities[0].id.as_deref(), Some("https://example.com/#org"));
+        assert_eq!(org.name.as_deref(), Some("Example Inc"));
+        assert_eq!(org.logo.as_deref(), Some("https://example.com/logo.png"));
+        assert_eq!(org.same_as.len(), 2);
+        assert!(!entities[0].properties.contains_key("@type"));
+
+        let crumbs = entities[1].breadcrumbs.as_ref().unwrap();
+        assert_eq!(crumbs[0].name.as_deref(), Some("Home"));
+        assert_eq!(crumbs[0].url.as_deref(), Some("https://example.com/"));
+        assert_eq!(crumbs[1].url.as_deref(), Some("https://example.com/books"));
+
+        let article = entities[2].article.as_ref().unwrap();
+        assert_eq!(article.headline.as_deref(), Some("Big news"));
+        assert_eq!(article.authors, vec!["Ann", "Bob"]);
+        assert_eq!(article.publisher.as_deref(), Some("Example Inc"));
+
+        let faq = entities[3].faq.as_ref().unwrap();
+        assert_eq!(faq[0].question, "Why?");
+        assert_eq!(faq[0].answer.as_deref(), Some("Because."));
+
+        let recipe = entities[4].recipe.as_ref().unwrap();
+        assert_eq!(recipe.ingredients, vec!["Flour", "Milk"]);
+        assert_eq!(recipe.instructions, vec!["Mix.", "Fry."]);
+        assert_eq!(recipe.total_time.as_deref(), Some("PT20M"));
+
+        let event = entities[5].event.as_ref().unwrap();
+        assert_eq!(event.location.as_deref(), Some("Berlin, DE"));
+        assert_eq!(event.event_status.as_deref(), Some("EventScheduled"));
+    }
+
+    #[test]
+    fn test_microdata_and_rdfa() {
+        let html = r#"<html><body>
+            <div itemscope itemtype="https://schema.org/Product">
+                <h1 itemprop="name">Widget</h1>
+                <img itemprop="image" src="/w.jpg">
+                <span itemprop="brand" itemscope itemtype="https://schema.org/Brand"><span itemprop="name">Acme</span></span>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <meta itemprop="priceCurrency" content="USD"><span itemprop="price" content="19.99">$19.99</span>
+                    <link itemprop="availability" href="https://schema.org/InStock">
+                </div>
+                <div itemprop="aggregateRating" itemscope itemtype="https://schema.org/AggregateRating">
+                    <span itemprop="ratingValue">4.5</span> from <span itemprop="reviewCount">12</span> reviews
+                </div>
+                <span itemprop="color">red</span> <span itemprop="color">blue</span>
+            </div>
+            <ol vocab="https://schema.org/" typeof="BreadcrumbList">
+                <li property="itemListElement" typeof="ListItem">
+                    <a property="item" href="https://example.com/"><span property="name">Home</span></a>
+                    <meta property="position" content="1">
+                </li>
+                <li property="itemListElement" typeof="ListItem">
+                    <a property="item" href="widgets"><span property="name">Widgets</span></a>
+                    <meta property="position" content="2">
+                </li>
+            </ol>
+        </body></html>"#;
+
+        let base = Url::parse("https://example.com/shop/").unwrap();
+        let entities = extract_structured_data(&Html::parse_document(html), Some(&base));
+        assert_eq!(entities.len(), 2);
+
+        let product = &entities[0];
+        assert_eq!(product.source, EntitySource::Microdata);
+        assert_eq!(product.types, vec!["Product"]);
+        assert_eq!(product.properties["offers"]["@type"], "Offer");
+        assert_eq!(product.properties["color"], serde_json::json!(["red", "blue"]));
+        let details = product.product.as_ref().unwrap();
+        assert_eq!(details.name.as_deref(), Some("Widget"));
+        assert_eq!(details.brand.as_deref(), Some("Acme"));
+        assert_eq!(details.image.as_deref(), Some("https://example.com/w.jpg"));
+        assert_eq!(details.price.as_deref(), Some("19.99"));
+        assert_eq!(details.price_currency.as_deref(), Some("USD"));
+        assert_eq!(details.availability.as_deref(), Some("InStock"));
+        assert_eq!(details.rating_value, Some(4.5));
+        assert_eq!(details.review_count, Some(12));
+
+        let breadcrumbs = &entities[1];
+        assert_eq!(breadcrumbs.source, EntitySource::Rdfa);
+        let crumbs = breadcrumbs.breadcrumbs.as_ref().unwrap();
+        assert_eq!(crumbs.len(), 2);
+        assert_eq!(crumbs[1].name.as_deref(), Some("Widgets"));
+        assert_eq!(crumbs[1].url.as_deref(), Some("https://example.com/shop/widgets"));
+        assert_eq!(crumbs[1].position, Some(2));
+    }
+}
@@ -0,0 +1,67 @@
+//! Helpers for tests that need a local HTTP server
+
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::fetch::{self, Fetcher};
+use crate::http_cache::HttpCache;
+use crate::ssrf::DestinationPolicy;
+
+/// Serve raw HTTP responses on a loopback port, returning the base URL
+///
+/// The handler receives the raw request head and returns the full response bytes.
+/// Every connection is closed after one response.
+pub async fn serve<F>(handler: F) -> String
+where
+    F: Fn(&str) -> Vec<u8> + Send + Sync + 'static,
+{
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = Arc::new(handler);
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let response = handler(&String::from_utf8_lossy(&request));
+                let _ = socket.write_all(&response).await;
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+
+    format!("http://{}", addr)
+}
+
+/// Build a simple HTTP/1.1 response with the given status line, headers and body
+pub fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    let mut out = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    for (name, value) in headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str("\r\n");
+    let mut out = out.into_bytes();
+    out.extend_from_slice(body);
+    out
+}
+
+/// SSRF policy that allows loopback test servers
+pub fn loopback_policy() -> Arc<DestinationPolicy> {
+    Arc::new(DestinationPolicy::new(&["127.0.0.0/8".to_string()], &[]).unwrap())
+}
+
+/// Fetcher whose SSRF policy allows loopback test servers
+pub fn loopback_fetcher(cache: Option<Arc<HttpCache>>) -> Fetcher {
+    let options = fetch::ClientOptions::new(fetch::DEFAULT_USER_AGENT_AUTONOMOUS);
+    let policy = loopback_policy();
+    let client = fetch::create_client(&options, policy.clone()).unwrap();
+    Fetcher::new(client, cache).with_policy(policy)
+}
@@ -1,9 +1,29 @@
 use anyhow::{bail, Context, Result};
 use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+use std::collections::HashMap;
 use url::Url;
 
-/// Validate and normalize URL
-pub fn validate_url(url_str: &str) -> Result<String> {
+use crate::domains::AccessPolicy;
+
+/// Request headers callers may not set (managed by the HTTP client or hop-by-hop)
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "connection",
+    "content-length",
+    "expect",
+    "host",
+    "keep-alive",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Validate and normalize URL, checking its host against the domain lists
+pub fn validate_url(url_str: &str, access: &AccessPolicy) -> Result<String> {
     // Parse URL to validate format
     let url = Url::parse(url_str).context("Invalid URL format")?;
 
@@ -13,15 +33,67 @@ pub fn validate_url(url_str: &str) -> Result<String> {
         bail!("Only HTTP and HTTPS URLs are allowed, got: {}", scheme);
     }
 
-    // Ensure URL has a host
-    if url.host_str().is_none() {
+    // Ensure URL has a host the domain lists accept
+    let Some(host) = url.host_str() else {
         bail!("URL must have a host");
-    }
+    };
+    access.check_host(host)?;
 
     // Return normalized URL (Url::parse already normalizes)
     Ok(url.to_string())
 }
 
+/// Check hostname against a pattern (exact or `*.suffix`, case-insensitive)
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    let pattern = pattern.trim_end_matches('.').to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host.ends_with(&format!(".{}", suffix)),
+        None => host == pattern,
+    }
+}
+
+/// Validate HTTP method
+pub fn validate_method(method: &str) -> Result<Method> {
+    let normalized = sanitize_string(method).to_uppercase();
+
+    match normalized.as_str() {
+        "GET" | "HEAD" | "POST" | "PUT" | "PATCH" | "DELETE" | "OPTIONS" => {
+            Method::from_bytes(normalized.as_bytes()).context("Invalid HTTP method")
+        }
+        _ => bail!("Invalid HTTP method. Must be: GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS"),
+    }
+}
+
+/// Validate request headers against the denylist
+pub fn validate_headers(headers: &HashMap<String, String>) -> Result<HeaderMap> {
+    if headers.len() > 50 {
+        bail!("Too many headers (max 50)");
+    }
+
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let header_name = HeaderName::from_bytes(name.trim().as_bytes())
+            .with_context(|| format!("Invalid header name: {}", name))?;
+
+        if FORBIDDEN_HEADERS.contains(&header_name.as_str()) {
+            bail!("Header '{}' cannot be set", name);
+        }
+
+        if value.len() > 8192 {
+            bail!("Header '{}' value too long (max 8192 characters)", name);
+        }
+
+        // Rejects CR/LF and other control characters
+        let header_value = HeaderValue::from_str(value)
+            .with_context(|| format!("Invalid value for header '{}'", name))?;
+
+        map.append(header_name, header_value);
+    }
+
+    Ok(map)
+}
+
 /// Validate CSS selector syntax
 pub fn validate_selector(selector: &str) -> Result<String> {
     use scraper::Selector;
@@ -174,15 +246,51 @@ mod tests {
 
     #[test]
     fn test_validate_url() {
+        let open = AccessPolicy::default();
+
         // Valid URLs
-        assert!(validate_url("https://example.com").is_ok());
-        assert!(validate_url("http://example.com/path?q=test").is_ok());
+        assert!(validate_url("https://example.com", &open).is_ok());
+        assert!(validate_url("http://example.com/path?q=test", &open).is_ok());
 
         // Invalid URLs
-        assert!(validate_url("ftp://example.com").is_err());
-        assert!(validate_url("javascript:alert(1)").is_err());
-        assert!(validate_url("not a url").is_err());
-        assert!(validate_url("file:///etc/passwd").is_err());
+        assert!(validate_url("ftp://example.com", &open).is_err());
+        assert!(validate_url("javascript:alert(1)", &open).is_err());
+        assert!(validate_url("not a url", &open).is_err());
+        assert!(validate_url("file:///etc/passwd", &open).is_err());
+
+        // Hosts refused by the domain lists
+        let deny = vec![crate::domains::DomainPattern::new("*.social.example").unwrap()];
+        let policy = AccessPolicy::new(Vec::new(), deny);
+        let err = validate_url("https://www.social.example/feed", &policy).unwrap_err();
+        assert!(err.to_string().contains("'*.social.example'"));
+        assert!(validate_url("https://docs.example/", &policy).is_ok());
+    }
+
+    #[test]
+    fn test_validate_method() {
+        assert_eq!(validate_method("post").unwrap(), Method::POST);
+        assert_eq!(validate_method(" GET ").unwrap(), Method::GET);
+        assert!(validate_method("TRACE").is_err());
+        assert!(validate_method("CONNECT").is_err());
+    }
+
+    #[test]
+    fn test_validate_headers() {
+        let mut headers = HashMap::new();
+        headers.insert("Accept".to_string(), "application/json".to_string());
+        headers.insert("X-Api-Key".to_string(), "abc".to_string());
+        let map = validate_headers(&headers).unwrap();
+        assert_eq!(map.len(), 2);
+
+        for forbidden in ["Host", "Content-Length", "Transfer-Encoding", "connection"] {
+            let mut headers = HashMap::new();
+            headers.insert(forbidden.to_string(), "x".to_string());
+            assert!(validate_headers(&headers).is_err(), "{} should be rejected", forbidden);
+        }
+
+        let mut headers = HashMap::new();
+        headers.insert("X-Evil".to_string(), "a\r\nHost: other".to_string());
+        assert!(validate_headers(&headers).is_err());
     }
 
     #[test]
@@ -2,6 +2,8 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
 
+use crate::fetch::Fetcher;
+
 /// Wikipedia search result
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WikiSearchResult {
@@ -100,7 +102,7 @@ impl Default for WikiOptions {
 
 /// Search Wikipedia articles
 pub async fn wiki_search(
-    client: &reqwest::Client,
+    fetcher: &Fetcher,
     query: &str,
     options: &WikiOptions,
 ) -> Result<Vec<WikiSearchResult>> {
@@ -113,10 +115,8 @@ pub async fn wiki_search(
 
     tracing::debug!("Wikipedia search: {}", url);
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "fetch-mcp-rs/0.1.0")
-        .send()
+    let response = fetcher
+        .send(fetcher.client.get(&url).header("User-Agent", "fetch-mcp-rs/0.1.0"), &url, None)
         .await
         .context("Failed to search Wikipedia")?;
 
@@ -145,7 +145,7 @@ pub async fn wiki_search(
 
 /// Get Wikipedia article content
 pub async fn wiki_get_article(
-    client: &reqwest::Client,
+    fetcher: &Fetcher,
     title: &str,
     options: &WikiOptions,
 ) -> Result<WikiArticle> {
@@ -158,10 +158,8 @@ pub async fn wiki_get_article(
 
     tracing::debug!("Fetching Wikipedia article: {}", url);
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "fetch-mcp-rs/0.1.0")
-        .send()
+    let response = fetcher
+        .send(fetcher.client.get(&url).header("User-Agent", "fetch-mcp-rs/0.1.0"), &url, None)
         .await
         .context("Failed to fetch Wikipedia article")?;
 
@@ -215,7 +213,7 @@ pub async fn wiki_get_article(
 
     // Extract images if requested
     let images = if options.extract_images {
-        extract_wikipedia_images(client, &options.language, &title).await?
+        extract_wikipedia_images(fetcher, &options.language, &title).await?
     } else {
         Vec::new()
     };
@@ -237,7 +235,7 @@ pub async fn wiki_get_article(
 
 /// Get random Wikipedia article
 pub async fn wiki_random(
-    client: &reqwest::Client,
+    fetcher: &Fetcher,
     options: &WikiOptions,
 ) -> Result<WikiArticle> {
     let url = format!(
@@ -245,10 +243,8 @@ pub async fn wiki_random(
         options.language
     );
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "fetch-mcp-rs/0.1.0")
-        .send()
+    let response = fetcher
+        .send(fetcher.client.get(&url).header("User-Agent", "fetch-mcp-rs/0.1.0"), &url, None)
         .await
         .context("Failed to get random article")?;
 
@@ -266,12 +262,12 @@ pub async fn wiki_random(
         .as_str()
         .context("No title in random article")?;
 
-    wiki_get_article(client, title, options).await
+    wiki_get_article(fetcher, title, options).await
 }
 
 /// Extract image URLs from Wikipedia article
 async fn extract_wikipedia_images(
-    client: &reqwest::Client,
+    fetcher: &Fetcher,
     language: &str,
     title: &str,
 ) -> Result<Vec<String>> {
@@ -281,10 +277,8 @@ async fn extract_wikipedia_images(
         urlencoding::encode(title)
     );
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "fetch-mcp-rs/0.1.0")
-        .send()
+    let response = fetcher
+        .send(fetcher.client.get(&url).header("User-Agent", "fetch-mcp-rs/0.1.0"), &url, None)
         .await?;
 
     let json: serde_json::Value = response.json().await?;
@@ -315,7 +309,7 @@ async fn extract_wikipedia_images(
     // Get actual image URLs
     let mut image_urls = Vec::new();
     for img_title in image_titles.iter().take(5) {
-        if let Ok(img_url) = get_image_url(client, language, img_title).await {
+        if let Ok(img_url) = get_image_url(fetcher, language, img_title).await {
             image_urls.push(img_url);
         }
     }
@@ -324,14 +318,14 @@ async fn extract_wikipedia_images(
 }
 
 /// Get actual image URL from image title
-async fn get_image_url(client: &reqwest::Client, language: &str, image_title: &str) -> Result<String> {
+async fn get_image_url(fetcher: &Fetcher, language: &str, image_title: &str) -> Result<String> {
     let url = format!(
         "https://{}.wikipedia.org/w/api.php?action=query&titles={}&prop=imageinfo&iiprop=url&format=json",
         language,
         urlencoding::encode(image_title)
     );
 
-    let response = client.get(&url).send().await?;
+    let response = fetcher.send(fetcher.client.get(&url), &url, None).await?;
     let json: serde_json::Value = response.json().await?;
 
     let pages = json["query"]["pages"].as_object().context("Invalid response")?;
@@ -356,14 +350,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_wiki_search() {
-        let client = reqwest::Client::new();
+        let fetcher = Fetcher::new(reqwest::Client::new(), None);
         let options = WikiOptions {
             language: "en".to_string(),
             limit: 5,
             ..Default::default()
         };
 
-        let result = wiki_search(&client, "Rust programming language", &options).await;
+        let result = wiki_search(&fetcher, "Rust programming language", &options).await;
         assert!(result.is_ok());
 
         let results = result.unwrap();
@@ -372,7 +366,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_wiki_get_article() {
-        let client = reqwest::Client::new();
+        let fetcher = Fetcher::new(reqwest::Client::new(), None);
         let options = WikiOptions {
             language: "en".to_string(),
             action: WikiAction::Summary,
@@ -380,7 +374,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = wiki_get_article(&client, "Rust (programming language)", &options).await;
+        let result = wiki_get_article(&fetcher, "Rust (programming language)", &options).await;
         assert!(result.is_ok());
 
         let article = result.unwrap();
@@ -390,10 +384,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_wiki_random() {
-        let client = reqwest::Client::new();
+        let fetcher = Fetcher::new(reqwest::Client::new(), None);
         let options = WikiOptions::default();
 
-        let result = wiki_random(&client, &options).await;
+        let result = wiki_random(&fetcher, &options).await;
         assert!(result.is_ok());
 
         let article = result.unwrap();
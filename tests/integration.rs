@@ -199,6 +199,7 @@ async fn test_list_tools() -> Result<()> {
     assert!(tool_names.contains(&"batch_fetch"));
     assert!(tool_names.contains(&"search_in_page"));
     assert!(tool_names.contains(&"extract_links"));
+    assert!(tool_names.contains(&"server_config"));
 
     Ok(())
 }